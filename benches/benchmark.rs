@@ -1,10 +1,14 @@
 use async_wss::aggregator::{Aggregator, ZenohEvent};
+use async_wss::binance::DepthMessage;
+use async_wss::bybit::{OrderbookEvent, PublicResponse};
 use async_wss::flatbuffer::event_factory::{make_snapshot_event, make_update_event};
+use async_wss::okx::BooksMessage;
 use async_wss::orderbook::l2::Level;
 use async_wss::spsc::SPSCQueue;
 use async_wss::utils::{CcyPair, Exchange, FlatbufferEvent};
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use rand::Rng;
+use serde::Deserialize;
 
 fn setup_data(num_levels: i32) -> (Vec<Level>, Vec<Level>, CcyPair) {
     // Setup data
@@ -110,5 +114,155 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn levels(num_levels: usize, width: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let levels = (0..num_levels)
+        .map(|_| {
+            let mut level = vec![
+                format!("\"{:.2}\"", rng.gen_range(1.0..10000.0)),
+                format!("\"{:.8}\"", rng.gen_range(0.0..10.0)),
+            ];
+            level.resize(width, "\"0\"".to_string());
+            format!("[{}]", level.join(","))
+        })
+        .collect::<Vec<String>>();
+    format!("[{}]", levels.join(","))
+}
+
+/// Levels decoded the way they were before in-place parsing: a `String` per price and
+/// quantity, parsed afterwards
+fn parse_allocated(levels: &[Vec<String>]) -> Vec<f64> {
+    levels
+        .iter()
+        .flat_map(|l| l.iter().map(|v| v.parse::<f64>().unwrap()))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct AllocatingBinance {
+    #[serde(rename = "s")]
+    _symbol: String,
+    b: Vec<Vec<String>>,
+    a: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct AllocatingOkx {
+    #[serde(rename = "action")]
+    _action: String,
+    data: Vec<AllocatingOkxUpdate>,
+}
+
+#[derive(Deserialize)]
+struct AllocatingOkxUpdate {
+    #[serde(rename = "ts")]
+    _ts: String,
+    bids: Vec<Vec<String>>,
+    asks: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct AllocatingBybit {
+    #[serde(rename = "topic")]
+    _topic: String,
+    data: AllocatingBybitEvent,
+}
+
+#[derive(Deserialize)]
+struct AllocatingBybitEvent {
+    #[serde(rename = "s")]
+    _symbol: String,
+    b: Vec<Vec<String>>,
+    a: Vec<Vec<String>>,
+}
+
+fn decoding_benchmark(c: &mut Criterion) {
+    /*
+    Binance diff depth event with 100 levels a side
+    */
+    let frame = format!(
+        r#"{{"e":"depthUpdate","E":1672515782136,"s":"BTCUSDT","U":157,"u":160,"b":{},"a":{}}}"#,
+        levels(100, 2),
+        levels(100, 2)
+    );
+    let mut group = c.benchmark_group("Binance depth decoding");
+    group.bench_function("allocating", |b| {
+        b.iter(|| {
+            let msg: AllocatingBinance = serde_json::from_str(black_box(&frame)).unwrap();
+            (parse_allocated(&msg.b), parse_allocated(&msg.a))
+        })
+    });
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            serde_json::from_str::<DepthMessage>(black_box(&frame))
+                .unwrap()
+                .into_owned()
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| serde_json::from_str::<DepthMessage>(black_box(&frame)).unwrap())
+    });
+    group.finish();
+
+    /*
+    Okx books snapshot with 400 levels a side
+    */
+    let frame = format!(
+        r#"{{"arg":{{"channel":"books","instId":"BTC-USDT"}},"action":"snapshot","data":[{{"asks":{},"bids":{},"ts":"1597026383085","checksum":-855196043,"prevSeqId":-1,"seqId":123456}}]}}"#,
+        levels(400, 4),
+        levels(400, 4)
+    );
+    let mut group = c.benchmark_group("Okx books decoding");
+    group.bench_function("allocating", |b| {
+        b.iter(|| {
+            let msg: AllocatingOkx = serde_json::from_str(black_box(&frame)).unwrap();
+            msg.data
+                .iter()
+                .map(|d| (parse_allocated(&d.bids), parse_allocated(&d.asks)))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            serde_json::from_str::<BooksMessage>(black_box(&frame))
+                .unwrap()
+                .into_owned()
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| serde_json::from_str::<BooksMessage>(black_box(&frame)).unwrap())
+    });
+    group.finish();
+
+    /*
+    Bybit orderbook.50 snapshot
+    */
+    let frame = format!(
+        r#"{{"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":1672304484978,"data":{{"s":"BTCUSDT","b":{},"a":{},"u":177400507,"seq":66544703342}},"cts":1672304484976}}"#,
+        levels(50, 2),
+        levels(50, 2)
+    );
+    let mut group = c.benchmark_group("Bybit orderbook decoding");
+    group.bench_function("allocating", |b| {
+        b.iter(|| {
+            let msg: AllocatingBybit = serde_json::from_str(black_box(&frame)).unwrap();
+            (parse_allocated(&msg.data.b), parse_allocated(&msg.data.a))
+        })
+    });
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            serde_json::from_str::<PublicResponse<OrderbookEvent>>(black_box(&frame))
+                .unwrap()
+                .into_owned(OrderbookEvent::into_owned)
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            serde_json::from_str::<PublicResponse<OrderbookEvent>>(black_box(&frame)).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, decoding_benchmark);
 criterion_main!(benches);
@@ -286,7 +286,7 @@ impl Aggregator {
                     .ok_or_else(|| TorrentError::BadParse("Snapshot data".to_string()));
                 let (bids, asks) = data.map_or_else(
                     |e| {
-                        eprintln!("{e}");
+                        log::error!("{e}");
                         (Vector::default(), Vector::default())
                     },
                     |data| {
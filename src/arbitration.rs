@@ -0,0 +1,364 @@
+use crate::pool::FORWARD_QUEUE;
+use crate::utils::{Exchange, Result, TorrentError};
+use crate::websocket::{
+    Acknowledgement, AsyncMessageCallback, Decode, DepthCallback, Event, Inline, MessageCallback,
+    SocketReader, WebSocketClient,
+};
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use tokio::sync::mpsc;
+
+/// Messages carrying the exchange's own sequence, identical across connections
+pub trait Sequenced {
+    /// Stream the message belongs to (e.g. a symbol's book) and its position in it. `None`
+    /// for messages outside any sequenced stream, such as acknowledgements
+    fn sequence(&self) -> Option<(String, u64)>;
+    /// Whether the message restarts its stream, e.g. a book snapshot. Snapshots are always
+    /// forwarded: a resync's snapshot may carry no newer sequence than what came before it
+    fn is_snapshot(&self) -> bool {
+        false
+    }
+    /// Stream of `topic` of `channel`, as named by [`Sequenced::sequence`]
+    fn stream(channel: &str, topic: &str) -> String;
+    /// Tells apart distinct messages of a stream sharing a sequence, which copies of a
+    /// message don't. `None` if the sequence alone identifies the message
+    fn digest(&self) -> Option<u64> {
+        None
+    }
+    /// Position of the message on its own connection, such as Coinbase's `sequence_num`.
+    /// Each leg of a [`RedundantFeed`] checks it before arbitration
+    fn connection_sequence(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Two independent connections to the same exchange subscribed to the same topics. Of
+/// every sequenced message, whichever copy arrives first is forwarded and the other is
+/// dropped, so a stalled or reconnecting connection doesn't hold the feed up. A gap in a
+/// leg's connection sequence reaches the manager as [`TorrentError::OutOfSync`]
+pub struct RedundantFeed {
+    legs: [WebSocketClient; 2],
+    readers: Vec<SocketReader>,
+}
+
+impl RedundantFeed {
+    /// Opens both connections, each with the settings of `client`
+    pub async fn connect(exchange: Exchange, client: WebSocketClient) -> Result<Self> {
+        let legs = [client.detached(), client.detached()];
+        let mut readers = Vec::with_capacity(legs.len());
+        for leg in &legs {
+            readers.push(leg.connect(exchange).await?);
+        }
+        Ok(Self { legs, readers })
+    }
+
+    /// Subscribes both connections. Resolves once both acknowledge
+    pub async fn subscribe(&self, channel: String, topics: Vec<String>) -> Result<Acknowledgement> {
+        let mut acks = Vec::with_capacity(self.legs.len());
+        for leg in &self.legs {
+            acks.push(leg.subscribe(channel.clone(), topics.clone()).await?);
+        }
+        Ok(Acknowledgement::join(acks))
+    }
+
+    pub async fn unsubscribe(
+        &self,
+        channel: String,
+        topics: Vec<String>,
+    ) -> Result<Acknowledgement> {
+        let mut acks = Vec::with_capacity(self.legs.len());
+        for leg in &self.legs {
+            acks.push(leg.unsubscribe(channel.clone(), topics.clone()).await?);
+        }
+        Ok(Acknowledgement::join(acks))
+    }
+
+    /// Handles to the connections, e.g. to (un)subscribe while the feed is listened to
+    pub fn legs(&self) -> &[WebSocketClient; 2] {
+        &self.legs
+    }
+
+    /// Listens to both connections, reconnecting each on its own, and feeds the first copy
+    /// of every message to `callback_manager`. Never returns
+    pub async fn listen_with<T, M>(self, callback_manager: M)
+    where
+        T: Sequenced + Decode + Send + 'static,
+        M: MessageCallback<T>,
+    {
+        self.listen_with_async(Inline(callback_manager)).await
+    }
+
+    /// Listens to both connections' depth streams, subscribed to beforehand, and feeds the
+    /// first copy of every message to `callback_manager` together with REST snapshots, as
+    /// [`WebSocketClient::depth_subscribe`] does. A reconnecting leg takes no new snapshots
+    /// since the other one carries on, books that missed updates anyway request their own.
+    /// Only returns if the snapshots can't be requested
+    pub async fn listen_with_depth<M, E, T, Snapshot>(self, callback_manager: M) -> Result<()>
+    where
+        M: DepthCallback<T, Snapshot> + Send + 'static,
+        E: Display + DeserializeOwned,
+        T: Sequenced + Decode + Send + 'static,
+        Snapshot: Debug + DeserializeOwned + Send + 'static,
+    {
+        let leg = &self.legs[0];
+        let mut manager = leg.depth_manager(leg.depth_exchange()?, callback_manager);
+        leg.request_depth_snapshots::<_, E, _, _>(&mut manager)
+            .await?;
+        self.listen_with_async(manager).await;
+        Ok(())
+    }
+
+    async fn listen_with_async<T, M>(self, callback_manager: M)
+    where
+        T: Sequenced + Decode + Send + 'static,
+        M: AsyncMessageCallback<T>,
+    {
+        let (events_tx, mut events_rx) = mpsc::channel(FORWARD_QUEUE);
+        let mut listeners = Vec::with_capacity(self.legs.len());
+        for (idx, (leg, reader)) in self.legs.into_iter().zip(self.readers).enumerate() {
+            let forwarder = Leg::new(idx, events_tx.clone());
+            listeners.push(tokio::spawn(
+                leg.listen_with_reconnect_async(reader, forwarder),
+            ));
+        }
+
+        let mut arbiter = Arbiter::new(callback_manager);
+        // both connections report an unsubscription; only the first copy of each topic's is
+        // forwarded. The legs that reported it are kept until they subscribe to it again
+        let mut unsubscribed: HashMap<(String, String), HashSet<usize>> = HashMap::new();
+        while let Some((idx, event)) = events_rx.recv().await {
+            let result = match event {
+                Event::Message(msg) => {
+                    AsyncMessageCallback::message_callback(&mut arbiter, msg).await
+                }
+                Event::Subscribed(channel, topics) => {
+                    for topic in topics {
+                        let key = (channel.clone(), topic);
+                        if let Some(legs) = unsubscribed.get_mut(&key) {
+                            legs.remove(&idx);
+                            if legs.is_empty() {
+                                unsubscribed.remove(&key);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                Event::Unsubscribed(channel, topics) => {
+                    let topics = topics
+                        .into_iter()
+                        .filter(|t| {
+                            let legs = unsubscribed
+                                .entry((channel.clone(), t.clone()))
+                                .or_default();
+                            let is_first = legs.is_empty();
+                            legs.insert(idx);
+                            is_first
+                        })
+                        .collect::<Vec<String>>();
+                    if topics.is_empty() {
+                        continue;
+                    }
+                    AsyncMessageCallback::on_unsubscribe(&mut arbiter, &channel, &topics).await
+                }
+            };
+            if let Err(e) = result {
+                log::warn!("{}", e);
+            }
+        }
+
+        for listener in listeners {
+            listener.abort();
+        }
+    }
+}
+
+/// Forwards a leg's messages to the feed, checking its connection sequence on the way
+struct Leg<T> {
+    idx: usize,
+    events: mpsc::Sender<(usize, Event<T>)>,
+    /// connection sequence of the leg's latest message
+    sequence: Option<u64>,
+}
+
+impl<T> Leg<T> {
+    fn new(idx: usize, events: mpsc::Sender<(usize, Event<T>)>) -> Self {
+        Self {
+            idx,
+            events,
+            sequence: None,
+        }
+    }
+}
+
+impl<T> Leg<T> {
+    async fn send(&self, event: Event<T>) -> Result<()> {
+        self.events
+            .send((self.idx, event))
+            .await
+            .map_err(|_| TorrentError::BadConnection("redundant feed stopped".to_string()))
+    }
+
+    /// Gap between the leg's latest message and `msg`, if any
+    fn check_sequence<M: Sequenced>(&mut self, msg: &Result<M>) -> Option<TorrentError> {
+        match msg {
+            Ok(m) => {
+                let sequence = m.connection_sequence()?;
+                let expected = self.sequence.map_or(0, |last| last + 1);
+                self.sequence = Some(sequence);
+                if sequence != expected && sequence != 0 {
+                    return Some(TorrentError::OutOfSync(format!(
+                        "leg {} expected connection sequence {} but got {}",
+                        self.idx, expected, sequence
+                    )));
+                }
+            }
+            // the next connection numbers its messages from 0 again
+            Err(e) if e.is_disconnect() => self.sequence = None,
+            Err(_) => {}
+        }
+        None
+    }
+}
+
+impl<T: Sequenced + Decode> AsyncMessageCallback<T> for Leg<T> {
+    async fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
+        if let Some(gap) = self.check_sequence(&msg) {
+            self.send(Event::Message(Err(gap))).await?;
+        }
+        self.send(Event::Message(msg)).await
+    }
+
+    async fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.send(Event::Unsubscribed(channel.to_string(), topics.to_vec()))
+            .await
+    }
+
+    async fn on_subscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.send(Event::Subscribed(channel.to_string(), topics.to_vec()))
+            .await
+    }
+}
+
+/// Sequence last forwarded on a stream, with the digests of the messages carrying it
+struct Last {
+    sequence: u64,
+    digests: Vec<u64>,
+}
+
+impl Last {
+    fn new(sequence: u64, digest: Option<u64>) -> Self {
+        Self {
+            sequence,
+            digests: digest.into_iter().collect(),
+        }
+    }
+
+    /// Whether a message is a copy of one already forwarded or behind them
+    fn is_behind(&self, sequence: u64, digest: Option<u64>) -> bool {
+        sequence < self.sequence
+            || (sequence == self.sequence && digest.is_none_or(|d| self.digests.contains(&d)))
+    }
+}
+
+/// Forwards a sequenced message only if it is ahead of everything already forwarded for
+/// its stream
+pub struct Arbiter<M> {
+    manager: M,
+    /// stream -> last forwarded sequence
+    last: HashMap<String, Last>,
+    duplicates: u64,
+}
+
+impl<M> Arbiter<M> {
+    pub fn new(callback_manager: M) -> Self {
+        Self {
+            manager: callback_manager,
+            last: HashMap::new(),
+            duplicates: 0,
+        }
+    }
+
+    /// Number of messages dropped as copies of already forwarded ones
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    pub fn into_inner(self) -> M {
+        self.manager
+    }
+}
+
+impl<M> Arbiter<M> {
+    /// Whether `msg` is a copy of, or behind, a message already forwarded
+    fn is_duplicate<T: Sequenced>(&mut self, msg: &Result<T>) -> bool {
+        let Ok(m) = msg else {
+            return false;
+        };
+        let Some((stream, seq)) = m.sequence() else {
+            return false;
+        };
+        let digest = m.digest();
+        match self.last.get_mut(&stream) {
+            // the stream restarts from the snapshot
+            _ if m.is_snapshot() => {
+                self.last.insert(stream, Last::new(seq, digest));
+            }
+            Some(last) if last.is_behind(seq, digest) => {
+                self.duplicates += 1;
+                return true;
+            }
+            // distinct messages sharing the sequence
+            Some(last) if seq == last.sequence => last.digests.extend(digest),
+            Some(last) => *last = Last::new(seq, digest),
+            None => {
+                self.last.insert(stream, Last::new(seq, digest));
+            }
+        }
+        false
+    }
+
+    /// A later subscription starts the streams of `topics` over
+    fn forget<T: Sequenced>(&mut self, channel: &str, topics: &[String]) {
+        for topic in topics {
+            self.last.remove(&T::stream(channel, topic));
+        }
+    }
+}
+
+impl<T, M> MessageCallback<T> for Arbiter<M>
+where
+    T: Sequenced + Decode,
+    M: MessageCallback<T>,
+{
+    fn message_callback(&mut self, msg: Result<T::Frame<'_>>) -> Result<()> {
+        let msg = msg.map(T::into_owned);
+        if self.is_duplicate(&msg) {
+            return Ok(());
+        }
+        self.manager.message_callback(msg.map(T::into_frame))
+    }
+
+    fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.forget::<T>(channel, topics);
+        self.manager.on_unsubscribe(channel, topics)
+    }
+}
+
+impl<T, M> AsyncMessageCallback<T> for Arbiter<M>
+where
+    T: Sequenced + Decode,
+    M: AsyncMessageCallback<T>,
+{
+    async fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
+        if self.is_duplicate(&msg) {
+            return Ok(());
+        }
+        self.manager.message_callback(msg).await
+    }
+
+    async fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.forget::<T>(channel, topics);
+        self.manager.on_unsubscribe(channel, topics).await
+    }
+}
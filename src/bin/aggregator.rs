@@ -1,18 +1,26 @@
 use async_wss::{
     aggregator::{Aggregator, ZenohEvent},
+    shutdown::ShutdownFlag,
     spsc::{QueueError, SPSCQueue},
     utils::{FlatbufferEvent, DATA_FEED},
 };
 use std::thread;
+use std::time::Duration;
 use zenoh::{
     config::Config,
     key_expr::keyexpr,
     prelude::{sync::SyncResolve, Encoding, KnownEncoding},
 };
 
+/// How often the zenoh consumer checks for a shutdown request while no sample arrives
+const SHUTDOWN_POLL: Duration = Duration::from_millis(100);
+
 fn main() {
     let (mut tx_z, mut rx_z) = SPSCQueue::new::<ZenohEvent>(50000);
     let (tx_f, mut rx_f) = SPSCQueue::new::<FlatbufferEvent>(50000);
+    // on SIGINT/SIGTERM the zenoh consumer stops, and each following thread exits once
+    // the queue in front of it is closed and drained
+    let shutdown = ShutdownFlag::on_signal();
 
     // consumes zenoh events and push to the queue
     let zenoh_rx_thread = thread::spawn(move || {
@@ -26,8 +34,11 @@ fn main() {
             .res()
             .expect("failed to declare a zenoh subscriber");
 
-        loop {
-            let sample = subscriber.recv().expect("all senders have been dropped");
+        let mut received = 0_u64;
+        while !shutdown.is_set() {
+            let Ok(sample) = subscriber.recv_timeout(SHUTDOWN_POLL) else {
+                continue;
+            };
 
             let stream_id;
             match sample.encoding.prefix() {
@@ -59,16 +70,27 @@ fn main() {
                 .collect::<Vec<u8>>();
 
             tx_z.push(ZenohEvent { stream_id, buff });
+            received += 1;
+        }
+
+        drop(subscriber);
+        if let Err(e) = session.close().res() {
+            log::error!("failed to close zenoh session\n{:?}", e);
         }
+        received
     });
 
     // poll the queue for zenoh events and send to aggregator to process it
     let aggregator_thread = thread::spawn(move || {
         let mut aggregator = Aggregator::new(tx_f);
+        let (mut processed, mut failed) = (0_u64, 0_u64);
+        let mut upstream_closed = false;
         loop {
             match rx_z.pop() {
                 Ok(event) => {
+                    processed += 1;
                     if let Err(e) = aggregator.process(event) {
+                        failed += 1;
                         log::error!("{e}");
                     }
 
@@ -77,10 +99,15 @@ fn main() {
                     log::info!("Mid price: {:?}", mid_price);
                 }
                 Err(QueueError::EmptyQueue) => {
+                    if upstream_closed {
+                        break;
+                    }
+                    upstream_closed = rx_z.is_closed();
                     log::trace!("no zenoh event for the aggregator");
                 }
             }
         }
+        (processed, failed)
     });
 
     // produce to remote/external services
@@ -91,6 +118,8 @@ fn main() {
             .expect("failed to open zenoh session");
         let key_expr = keyexpr::new(DATA_FEED).expect("failed to get a zenoh key experession");
 
+        let mut published = 0_u64;
+        let mut aggregator_closed = false;
         loop {
             match rx_f.pop() {
                 Ok(event) => {
@@ -102,27 +131,45 @@ fn main() {
                                 continue;
                             }
                         };
-                        if let Err(e) = session.put(key_expr, event.buff).encoding(encoding).res() {
-                            log::error!("failed to send encoding for pricing details\n{:?}", e);
+                        match session.put(key_expr, event.buff).encoding(encoding).res() {
+                            Ok(_) => published += 1,
+                            Err(e) => {
+                                log::error!("failed to send encoding for pricing details\n{:?}", e)
+                            }
                         }
                     } else {
                         // continue;
                     }
                 }
                 Err(QueueError::EmptyQueue) => {
+                    if aggregator_closed {
+                        break;
+                    }
+                    aggregator_closed = rx_f.is_closed();
                     log::trace!("no event to be sent to external service")
                 }
             }
         }
+
+        // flushes the puts still pending
+        if let Err(e) = session.close().res() {
+            log::error!("failed to close zenoh session\n{:?}", e);
+        }
+        published
     });
 
-    zenoh_rx_thread
+    let received = zenoh_rx_thread
         .join()
         .expect("zenoh event conusmer thread has panicked");
-    aggregator_thread
+    let (processed, failed) = aggregator_thread
         .join()
         .expect("aggregator thread has panicked");
-    extern_producing_thread
+    let published = extern_producing_thread
         .join()
         .expect("external services producing thread has panicked");
+
+    println!(
+        "aggregator stopped: {} zenoh events received, {} processed ({} failed), {} pricing details published",
+        received, processed, failed, published
+    );
 }
@@ -1,4 +1,8 @@
-use async_wss::binance::{DepthSnapshot, Manager as BinanceManager, Message, RequestError};
+use async_wss::binance::{
+    Binance, DepthSnapshot, Manager as BinanceManager, Market, Message, RequestError,
+};
+use async_wss::config::{Endpoint, Endpoints};
+use async_wss::shutdown;
 use async_wss::utils::Exchange;
 use async_wss::websocket::WebSocketClient;
 
@@ -9,13 +13,30 @@ async fn main() {
     }
     dotenv::dotenv().ok();
 
+    // coin margined perpetuals would be `Market::COIN_M` with "BTCUSD_PERP"
+    let market = Market::SPOT;
     let products = vec!["BTC-USDT".to_string() /* "ETH-USDT".to_string() */];
-    let cb_obj = BinanceManager::new();
-    let mut wss = WebSocketClient::new();
+    let mut endpoints = Endpoints::from_env();
+    if market != Market::SPOT {
+        // futures stream and snapshot from endpoints of their own
+        let endpoint = Endpoint::new(market.url(), market.rest_url());
+        endpoints = endpoints.with(Exchange::BINANCE, endpoint);
+    }
+    let mut cb_obj = BinanceManager::new().with_market(market);
+    if market == Market::COIN_M {
+        // coin margined books are converted from contracts with their contract sizes
+        let rest_url = endpoints.get(&Exchange::BINANCE).rest.clone();
+        let instruments = Binance::instruments(&rest_url, market).await.unwrap();
+        cb_obj = cb_obj.with_instruments(instruments);
+    }
+    let wss = WebSocketClient::new()
+        .with_endpoints(endpoints)
+        .with_binance_market(market);
 
     let socket_reader = wss.connect(Exchange::BINANCE).await.unwrap();
 
     let listener = wss
+        .clone()
         .depth_subscribe::<BinanceManager, RequestError, Message, DepthSnapshot>(
             socket_reader,
             products,
@@ -24,5 +45,13 @@ async fn main() {
         .await
         .unwrap();
 
-    listener.await.unwrap();
+    let signal = shutdown::signal().await;
+    log::info!("received {}, shutting down", signal);
+    if let Err(e) = wss.shutdown().await {
+        log::error!("{}", e);
+    }
+    let manager = listener.await.unwrap().unwrap();
+    if let Err(e) = manager.close() {
+        log::error!("{}", e);
+    }
 }
@@ -1,6 +1,8 @@
-use async_wss::bybit::{Channel as BybitChannel, Depth, Manager as BybitManager};
+use async_wss::bybit::{Bybit, Category, Channel as BybitChannel, Depth, Manager as BybitManager};
+use async_wss::config::{Endpoint, Endpoints};
+use async_wss::shutdown;
 use async_wss::utils::Exchange;
-use async_wss::websocket::WebSocketClient;
+use async_wss::websocket::{Pipeline, WebSocketClient};
 
 #[tokio::main]
 async fn main() {
@@ -9,16 +11,58 @@ async fn main() {
     }
     dotenv::dotenv().ok();
 
+    // linear perpetuals would be `Category::LINEAR` with "BTCUSDT"
+    let category = Category::SPOT;
     let products = vec!["BTC-USDT".to_string() /* "ETH-USDT".to_string() */];
-    let manager = BybitManager::new();
-    let mut wss = WebSocketClient::new();
+    let mut endpoints = Endpoints::from_env();
+    if category != Category::SPOT {
+        // every category streams from an endpoint of its own
+        let endpoint = Endpoint::new(category.url(), Bybit::REST_URL);
+        endpoints = endpoints.with(Exchange::BYBIT, endpoint);
+    }
+    let wss = WebSocketClient::new().with_endpoints(endpoints);
+    // resubscribes books that miss an update
+    let manager = BybitManager::new()
+        .with_category(category)
+        .with_client(wss.clone());
 
     let socket_reader = wss.connect(Exchange::BYBIT).await.unwrap();
-    let listener = WebSocketClient::listen_with(socket_reader, manager);
-
-    wss.subscribe(BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(), products)
+    let books = wss
+        .subscribe(
+            BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+            products.clone(),
+        )
         .await
         .unwrap();
+    // trades and tickers are published on feeds of their own
+    let trades = wss
+        .subscribe(BybitChannel::PUBLIC_TRADE.to_string(), products.clone())
+        .await
+        .unwrap();
+    let tickers = wss
+        .subscribe(BybitChannel::TICKERS.to_string(), products)
+        .await
+        .unwrap();
+
+    // publishing blocks, so the manager runs on a thread of its own
+    let (pipeline, worker) = Pipeline::spawn(manager, 1024);
+    let listener = tokio::spawn(
+        wss.clone()
+            .listen_with_reconnect_async(socket_reader, pipeline),
+    );
+    books.await.unwrap();
+    trades.await.unwrap();
+    tickers.await.unwrap();
 
-    listener.await;
+    let signal = shutdown::signal().await;
+    log::info!("received {}, shutting down", signal);
+    if let Err(e) = wss.shutdown().await {
+        log::error!("{}", e);
+    }
+    // the pipeline is dropped with the listener, after which its worker drains the queue
+    listener.await.unwrap();
+    let manager = worker.await.unwrap();
+    if let Err(e) = manager.close() {
+        log::error!("{}", e);
+    }
 }
@@ -0,0 +1,43 @@
+use async_wss::coinbase::{Channel as CoinbaseChannel, Manager as CoinbaseManager};
+use async_wss::shutdown;
+use async_wss::utils::Exchange;
+use async_wss::websocket::{Pipeline, WebSocketClient};
+
+#[tokio::main]
+async fn main() {
+    if std::env::var_os("RUST_LOG").is_none() {
+        std::env::set_var("RUST_LOG", "api=info");
+    }
+    dotenv::dotenv().ok();
+
+    let products = vec!["BTC-USD".to_string() /* "ETH-USD".to_string() */];
+    let wss = WebSocketClient::new();
+    // resubscribes products whose book misses a message
+    let manager = CoinbaseManager::new().with_client(wss.clone());
+
+    let socket_reader = wss.connect(Exchange::COINBASE).await.unwrap();
+    let ack = wss
+        .subscribe(CoinbaseChannel::LEVEL2.to_string(), products)
+        .await
+        .unwrap();
+
+    // publishing blocks, so the manager runs on a thread of its own
+    let (pipeline, worker) = Pipeline::spawn(manager, 1024);
+    let listener = tokio::spawn(
+        wss.clone()
+            .listen_with_reconnect_async(socket_reader, pipeline),
+    );
+    ack.await.unwrap();
+
+    let signal = shutdown::signal().await;
+    log::info!("received {}, shutting down", signal);
+    if let Err(e) = wss.shutdown().await {
+        log::error!("{}", e);
+    }
+    // the pipeline is dropped with the listener, after which its worker drains the queue
+    listener.await.unwrap();
+    let manager = worker.await.unwrap();
+    if let Err(e) = manager.close() {
+        log::error!("{}", e);
+    }
+}
@@ -1,6 +1,8 @@
-use async_wss::okx::{Channel as OkxChannel, Manager as OkxManager};
+use async_wss::config::Endpoints;
+use async_wss::okx::{Channel as OkxChannel, InstType, Manager as OkxManager, Okx};
+use async_wss::shutdown;
 use async_wss::utils::Exchange;
-use async_wss::websocket::WebSocketClient;
+use async_wss::websocket::{Pipeline, WebSocketClient};
 
 #[tokio::main]
 async fn main() {
@@ -9,16 +11,53 @@ async fn main() {
     }
     dotenv::dotenv().ok();
 
-    let products = vec!["BTC-USDT".to_string() /* "ETH-USDT".to_string() */];
-    let manager = OkxManager::new();
-    let mut wss = WebSocketClient::new();
+    let products = vec![
+        "BTC-USDT".to_string(), /* "BTC-USDT-SWAP".to_string() */
+    ];
+    let wss = WebSocketClient::new();
+    // resubscribes books that fail their checksum
+    let mut manager = OkxManager::new().with_client(wss.clone());
+    // derivative books are converted from contracts with their instruments' contract values
+    let rest_url = Endpoints::from_env().get(&Exchange::OKX).rest.clone();
+    for inst_type in [InstType::SWAP, InstType::FUTURES] {
+        if products
+            .iter()
+            .any(|p| InstType::from_inst_id(p) == inst_type)
+        {
+            let instruments = Okx::instruments(&rest_url, inst_type, None).await.unwrap();
+            manager = manager.with_instruments(instruments);
+        }
+    }
 
     let socket_reader = wss.connect(Exchange::OKX).await.unwrap();
-    let listener = WebSocketClient::listen_with(socket_reader, manager);
-
-    wss.subscribe(OkxChannel::BOOKS.to_string(), products)
+    let books = wss
+        .subscribe(OkxChannel::BOOKS.to_string(), products.clone())
+        .await
+        .unwrap();
+    // trades are published on a feed of their own
+    let trades = wss
+        .subscribe(OkxChannel::TRADES.to_string(), products)
         .await
         .unwrap();
 
-    listener.await;
+    // publishing blocks, so the manager runs on a thread of its own
+    let (pipeline, worker) = Pipeline::spawn(manager, 1024);
+    let listener = tokio::spawn(
+        wss.clone()
+            .listen_with_reconnect_async(socket_reader, pipeline),
+    );
+    books.await.unwrap();
+    trades.await.unwrap();
+
+    let signal = shutdown::signal().await;
+    log::info!("received {}, shutting down", signal);
+    if let Err(e) = wss.shutdown().await {
+        log::error!("{}", e);
+    }
+    // the pipeline is dropped with the listener, after which its worker drains the queue
+    listener.await.unwrap();
+    let manager = worker.await.unwrap();
+    if let Err(e) = manager.close() {
+        log::error!("{}", e);
+    }
 }
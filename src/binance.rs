@@ -1,23 +1,61 @@
 use crate::aggregator::Transmitor;
+use crate::arbitration::Sequenced;
 use crate::flatbuffer::{make_snapshot_event, make_update_event};
 use crate::orderbook::l2::Level;
+use crate::rest::RestClient;
 use crate::utils::{
-    CcyPair, Exchange, Result, Symbol, TorrentError, ASSET_CONSTANT_MULTIPLIER, DATA_FEED,
+    CcyPair, Decimal, Exchange, Result, Symbol, TorrentError, ASSET_CONSTANT_MULTIPLIER, DATA_FEED,
+};
+use crate::websocket::{
+    Ack, Decode, DepthCallback, Limits, RateLimit, Request, SnapshotRequests, Wss,
 };
-use crate::websocket::{DepthCallback, Wss};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Display};
 use std::ops::Deref;
 use std::result;
+use std::time::Duration;
 use zenoh::prelude::sync::SyncResolve;
 use zenoh::prelude::Encoding;
 
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
-pub enum Message {
+pub enum Message<'a> {
     Subscribe(SubscribeMessage),
-    Depth(DepthMessage),
+    #[serde(borrow)]
+    Depth(DepthMessage<'a>),
+}
+
+impl Decode for Message<'_> {
+    type Frame<'a> = Message<'a>;
+
+    fn into_owned(frame: Message<'_>) -> Self {
+        match frame {
+            Message::Subscribe(m) => Message::Subscribe(m),
+            Message::Depth(m) => Message::Depth(m.into_owned()),
+        }
+    }
+
+    fn into_frame<'a>(self) -> Message<'a>
+    where
+        Self: 'a,
+    {
+        self
+    }
+}
+
+impl Sequenced for Message<'_> {
+    fn sequence(&self) -> Option<(String, u64)> {
+        match self {
+            Message::Depth(m) => Some((m.symbol.to_string(), m.final_update_id)),
+            Message::Subscribe(_) => None,
+        }
+    }
+
+    fn stream(_channel: &str, topic: &str) -> String {
+        Binance::symbol(topic).unwrap_or_else(|| topic.to_string())
+    }
 }
 
 #[derive(Debug)]
@@ -56,13 +94,8 @@ impl<'de> serde::de::Visitor<'de> for LevelUpdateVisitor {
         let mut arr = [0.0; 2];
         let mut i = 0;
 
-        while let Some(v) = seq.next_element::<String>()? {
-            // v is a valid string but not parsable to f64
-            let non_f64 = serde::de::Unexpected::Str(v.as_str());
-            let element = v
-                .parse()
-                .map_err(|_| serde::de::Error::invalid_value(non_f64, &self))?;
-            arr[i] = element;
+        while let Some(Decimal(v)) = seq.next_element()? {
+            arr[i] = v;
             i += 1;
         }
         Ok(LevelUpdate(arr))
@@ -73,27 +106,48 @@ impl<'de> serde::de::Visitor<'de> for LevelUpdateVisitor {
 #[serde(rename_all = "camelCase")]
 pub struct SubscribeMessage {
     pub result: Option<String>,
+    pub error: Option<RequestError>,
     pub id: usize,
 }
 
+/// Diff depth event. Its strings are borrowed from the frame unless they had to be unescaped
 #[derive(Debug, Deserialize)]
-pub struct DepthMessage {
-    #[serde(rename = "e")]
-    pub event_type: String,
+pub struct DepthMessage<'a> {
+    #[serde(rename = "e", borrow)]
+    pub event_type: Cow<'a, str>,
     #[serde(rename = "E")]
     pub event_time: u64,
-    #[serde(rename = "s")]
-    pub symbol: String,
+    #[serde(rename = "s", borrow)]
+    pub symbol: Cow<'a, str>,
     #[serde(rename = "U")]
     pub first_update_id: u64,
     #[serde(rename = "u")]
     pub final_update_id: u64,
+    /// final update id of the previous event, only sent by futures streams
+    #[serde(rename = "pu", default)]
+    pub previous_final_update_id: Option<u64>,
     #[serde(rename = "b")]
     pub bids: Vec<LevelUpdate>,
     #[serde(rename = "a")]
     pub asks: Vec<LevelUpdate>,
 }
 
+impl DepthMessage<'_> {
+    /// Copies the strings borrowed from the frame
+    pub fn into_owned(self) -> DepthMessage<'static> {
+        DepthMessage {
+            event_type: Cow::Owned(self.event_type.into_owned()),
+            event_time: self.event_time,
+            symbol: Cow::Owned(self.symbol.into_owned()),
+            first_update_id: self.first_update_id,
+            final_update_id: self.final_update_id,
+            previous_final_update_id: self.previous_final_update_id,
+            bids: self.bids,
+            asks: self.asks,
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 struct Subscription {
     method: String,
@@ -115,52 +169,195 @@ impl Display for Channel {
     }
 }
 
+/// Binance's markets, each streamed and snapshotted from endpoints of their own
+#[allow(non_camel_case_types)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Market {
+    #[default]
+    SPOT,
+    /// USDT and USDC margined perpetuals and quarterly futures
+    USD_M,
+    /// coin margined perpetuals and quarterly futures, sized in USD contracts
+    COIN_M,
+}
+
+impl Display for Market {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Market::SPOT => write!(f, "spot"),
+            Market::USD_M => write!(f, "usd-m"),
+            Market::COIN_M => write!(f, "coin-m"),
+        }
+    }
+}
+
+impl Market {
+    /// Websocket endpoint of the market
+    pub fn url(&self) -> &'static str {
+        match self {
+            Market::SPOT => Binance::URL,
+            Market::USD_M => "wss://fstream.binance.com/ws",
+            Market::COIN_M => "wss://dstream.binance.com/ws",
+        }
+    }
+
+    /// REST endpoint of the market
+    pub fn rest_url(&self) -> &'static str {
+        match self {
+            Market::SPOT => Binance::REST_URL,
+            Market::USD_M => "https://fapi.binance.com",
+            Market::COIN_M => "https://dapi.binance.com",
+        }
+    }
+
+    /// Route of the market's depth snapshots
+    pub fn depth_api(&self) -> API {
+        match self {
+            Market::SPOT => API::Spot(Spot::Depth),
+            Market::USD_M => API::UsdM(UsdM::Depth),
+            Market::COIN_M => API::CoinM(CoinM::Depth),
+        }
+    }
+
+    /// Route of the market's symbols and their contract sizes
+    pub fn exchange_info_api(&self) -> API {
+        match self {
+            Market::SPOT => API::Spot(Spot::ExchangeInfo),
+            Market::USD_M => API::UsdM(UsdM::ExchangeInfo),
+            Market::COIN_M => API::CoinM(CoinM::ExchangeInfo),
+        }
+    }
+
+    /// Query of a depth snapshot of `symbol`, as deep as the market allows
+    pub fn depth_param(&self, symbol: Symbol) -> [(String, String); 2] {
+        let limit = match self {
+            Market::SPOT => "5000",
+            Market::USD_M | Market::COIN_M => "1000",
+        };
+        [
+            ("symbol".to_string(), symbol),
+            ("limit".to_string(), limit.to_string()),
+        ]
+    }
+}
+
 #[derive(Debug, Default)]
-pub struct Binance(usize);
+pub struct Binance {
+    /// id of the next request
+    id: usize,
+    /// market whose streams the connection carries
+    market: Market,
+}
 
 impl Binance {
     pub const URL: &'static str = "wss://stream.binance.com:9443/ws";
+    pub const REST_URL: &'static str = "https://api.binance.com";
 
     pub fn new() -> Self {
-        Self(0)
+        Default::default()
     }
-}
 
-impl Display for Binance {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Binance")
+    /// Market the connection streams from, spot by default
+    pub fn with_market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
     }
-}
 
-impl Wss for Binance {
-    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<String> {
+    /// Binance's symbol of a topic. `BTC-USDT` pairs are joined into `BTCUSDT`, topics without
+    /// a dash are taken as Binance's own symbol, e.g. `BTCUSD_PERP` or `BTCUSDT_240329`
+    pub fn symbol(topic: &str) -> Option<Symbol> {
+        let parts = topic.split('-').collect::<Vec<&str>>();
+        match parts.len() {
+            1 | 2 => Some(parts.join("").to_uppercase()),
+            _ => None,
+        }
+    }
+
+    /// Builds a `method` (`SUBSCRIBE` or `UNSUBSCRIBE`) request for `topics` of `channel`
+    fn request(&mut self, method: &str, channel: String, topics: Vec<String>) -> Result<Request> {
         let params = topics
-            .into_iter()
-            .filter_map(|t| {
-                let parts = t.split('-').collect::<Vec<&str>>();
-                if parts.len() == 2 {
-                    Some(parts.join(""))
-                } else {
-                    None
-                }
-            })
+            .iter()
+            .filter_map(|t| Self::symbol(t))
             .map(|sym| format!("{}@{}", sym.to_lowercase(), channel))
             .collect::<Vec<String>>();
         let sub = Subscription {
-            method: "subscribe".to_uppercase(),
+            method: method.to_uppercase(),
             params,
-            id: self.0,
+            id: self.id,
         };
-        self.0 += 1;
+        self.id += 1;
 
         match serde_json::to_string(&sub) {
-            Ok(s) => Ok(s),
+            Ok(payload) => Ok(Request {
+                payload,
+                ack_ids: vec![sub.id.to_string()],
+            }),
             Err(e) => Err(TorrentError::BadParse(format!("serde parse error: {}", e))),
         }
     }
+
+    /// Lists the symbols of `market`, whose contract sizes [`Manager::with_instruments`]
+    /// converts COIN-M quantities with
+    pub async fn instruments(rest_url: &str, market: Market) -> Result<Vec<Instrument>> {
+        let rest_client = RestClient::new(rest_url);
+        let info = rest_client
+            .get::<ExchangeInfo, RequestError, ()>(market.exchange_info_api(), None)
+            .await?;
+        Ok(info.symbols)
+    }
+
+    /// Binance answers every request with its `id` and either a `result` or an `error`
+    pub fn parse_ack(msg: &str) -> Option<Ack> {
+        if !msg.contains("\"id\"") {
+            return None;
+        }
+        let ack = serde_json::from_str::<SubscribeMessage>(msg).ok()?;
+        let id = Some(ack.id.to_string());
+        match ack.error {
+            Some(e) => Some(Ack::Failure(id, e.to_string())),
+            None => Some(Ack::Success(id)),
+        }
+    }
+}
+
+impl Display for Binance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Binance")
+    }
+}
+
+impl Wss for Binance {
+    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("subscribe", channel, topics)
+    }
+    fn unsubscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("unsubscribe", channel, topics)
+    }
     fn to_enum(&self) -> Exchange {
         Exchange::BINANCE
     }
+
+    fn ack_fn(&self) -> fn(&str) -> Option<Ack> {
+        Self::parse_ack
+    }
+
+    /// Streams and incoming messages (requests, pings and pongs) a connection of the market
+    /// may carry. A request may subscribe to as many streams as the connection allows
+    fn limits(&self) -> Limits {
+        let (streams, messages) = match self.market {
+            // https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams
+            Market::SPOT => (1024, 5),
+            // https://developers.binance.com/docs/derivatives/usds-margined-futures/websocket-market-streams
+            Market::USD_M => (200, 10),
+            // https://developers.binance.com/docs/derivatives/coin-margined-futures/websocket-market-streams
+            Market::COIN_M => (200, 10),
+        };
+        Limits {
+            topics_per_connection: streams,
+            topics_per_request: streams,
+            messages: Some(RateLimit::new(messages, Duration::from_secs(1))),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -177,6 +374,28 @@ pub struct DepthSnapshot {
     pub asks: Vec<LevelUpdate>,
 }
 
+/// Symbols listed by a market's exchange info
+#[derive(Debug, Deserialize)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<Instrument>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Instrument {
+    pub symbol: String,
+    /// USD value of a COIN-M contract, absent on spot and USD-M
+    #[serde(default)]
+    pub contract_size: f64,
+}
+
+impl Instrument {
+    /// Converts `qty` COIN-M contracts at `price` into base currency units
+    pub fn base_qty(&self, price: f64, qty: f64) -> f64 {
+        qty * self.contract_size / price
+    }
+}
+
 impl Display for RequestError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "code: {}, msg: {}", self.code, self.msg)
@@ -185,10 +404,23 @@ impl Display for RequestError {
 
 pub enum API {
     Spot(Spot),
+    UsdM(UsdM),
+    CoinM(CoinM),
 }
 
 pub enum Spot {
     Depth,
+    ExchangeInfo,
+}
+
+pub enum UsdM {
+    Depth,
+    ExchangeInfo,
+}
+
+pub enum CoinM {
+    Depth,
+    ExchangeInfo,
 }
 
 impl From<API> for String {
@@ -196,15 +428,30 @@ impl From<API> for String {
         String::from(match item {
             API::Spot(route) => match route {
                 Spot::Depth => "/api/v3/depth",
+                Spot::ExchangeInfo => "/api/v3/exchangeInfo",
+            },
+            API::UsdM(route) => match route {
+                UsdM::Depth => "/fapi/v1/depth",
+                UsdM::ExchangeInfo => "/fapi/v1/exchangeInfo",
+            },
+            API::CoinM(route) => match route {
+                CoinM::Depth => "/dapi/v1/depth",
+                CoinM::ExchangeInfo => "/dapi/v1/exchangeInfo",
             },
         })
     }
 }
 
+/// Diffs kept for a book waiting on its snapshot. Older ones are dropped past this, the
+/// snapshot being fetched is newer than them anyway
+const MAX_BUFFERED: usize = 1000;
+
 #[derive(Debug)]
 struct Metadata {
     small_u: u64,
     is_first_update: bool,
+    /// diffs received while waiting on a snapshot, replayed on top of it
+    buffer: VecDeque<DepthMessage<'static>>,
 }
 
 impl Metadata {
@@ -212,8 +459,16 @@ impl Metadata {
         Self {
             small_u,
             is_first_update,
+            buffer: VecDeque::new(),
         }
     }
+
+    fn buffer(&mut self, update: DepthMessage<'static>) {
+        if self.buffer.len() == MAX_BUFFERED {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(update);
+    }
 }
 
 #[derive(Debug)]
@@ -221,6 +476,11 @@ pub struct Manager {
     zenoh: zenoh::Session,
     metadata_mp: HashMap<Symbol, Metadata>,
     snapshots_mp: HashMap<Symbol, Option<DepthSnapshot>>,
+    /// fresh snapshots of books that went out of sync are requested through it
+    snapshot_requests: Option<SnapshotRequests>,
+    market: Market,
+    /// COIN-M symbols, whose quantities are converted from contracts to base units
+    instruments: HashMap<Symbol, Instrument>,
 }
 
 impl Default for Manager {
@@ -233,6 +493,9 @@ impl Default for Manager {
             zenoh: session,
             metadata_mp: HashMap::default(),
             snapshots_mp: HashMap::default(),
+            snapshot_requests: None,
+            market: Market::default(),
+            instruments: HashMap::default(),
         }
     }
 }
@@ -241,97 +504,233 @@ impl Manager {
     pub fn new() -> Self {
         Default::default()
     }
-}
 
-impl DepthCallback<Message, DepthSnapshot> for Manager {
-    const REST_URL: &'static str = "https://api.binance.com";
+    /// Market whose books are managed, spot by default
+    pub fn with_market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Converts the quantities of COIN-M books from USD contracts into the base currency, so
+    /// that they aggregate with spot books. See [`Binance::instruments`]
+    pub fn with_instruments(mut self, instruments: Vec<Instrument>) -> Self {
+        self.instruments
+            .extend(instruments.into_iter().map(|i| (i.symbol.clone(), i)));
+        self
+    }
+
+    /// Closes the zenoh session once the puts already made have been flushed
+    pub fn close(self) -> Result<()> {
+        self.zenoh
+            .close()
+            .res()
+            .map_err(|e| TorrentError::BadConnection(format!("failed to close zenoh: {}", e)))
+    }
+
+    /// Id of the last update applied to the book of `symbol`, `None` while it is out of sync
+    pub fn last_update_id(&self, symbol: &str) -> Option<u64> {
+        self.metadata_mp
+            .get(symbol)
+            .filter(|metadata| !metadata.is_first_update)
+            .map(|metadata| metadata.small_u)
+    }
+
+    // https://binance-docs.github.io/apidocs/spot/en/#how-to-manage-a-local-order-book-correctly
+    // https://binance-docs.github.io/apidocs/futures/en/#how-to-manage-a-local-order-book-correctly
+    fn update(&mut self, update: DepthMessage<'static>) -> Result<()> {
+        let symbol = update.symbol.to_string();
+        // updates still in flight after an unsubscription are dropped
+        let Some(metadata) = self.metadata_mp.get_mut(&symbol) else {
+            log::warn!("Received updates for {symbol} which is not subscribed to");
+            return Ok(());
+        };
+        // 1. Buffer the events until the book is synced with a snapshot
+        if metadata.is_first_update {
+            metadata.buffer(update);
+            return self.sync(&symbol);
+        }
+        if update.final_update_id <= metadata.small_u {
+            return Ok(());
+        }
+        // 6. New event's U == previous event's u + 1, futures' pu == previous event's u
+        let reason = match update.previous_final_update_id {
+            Some(pu) if pu != metadata.small_u => Some(format!(
+                "expected previous update {} but got {}",
+                metadata.small_u, pu
+            )),
+            None if update.first_update_id != metadata.small_u + 1 => Some(format!(
+                "expected update {} but got {}",
+                metadata.small_u + 1,
+                update.first_update_id
+            )),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            metadata.buffer(update);
+            return self.resync(&symbol, reason);
+        }
+        metadata.small_u = update.final_update_id;
+        self.transmit(symbol, update.bids, update.asks, false)
+    }
+
+    /// Applies the snapshot of `symbol` once it and the buffered events line up, then replays
+    /// the buffer on top of it
+    fn sync(&mut self, symbol: &Symbol) -> Result<()> {
+        let Some(metadata) = self.metadata_mp.get_mut(symbol) else {
+            return Ok(());
+        };
+        let Some(snapshot) = self.snapshots_mp.get_mut(symbol).and_then(Option::take) else {
+            return Ok(());
+        };
+        let last_update_id = snapshot.last_update_id;
+        // spot's first event follows the snapshot, futures' one contains lastUpdateId
+        let first_update_id = match self.market {
+            Market::SPOT => last_update_id + 1,
+            Market::USD_M | Market::COIN_M => last_update_id,
+        };
+        // 4. Drop any event where u < the first update id
+        metadata
+            .buffer
+            .retain(|update| update.final_update_id >= first_update_id);
+        let Some(first) = metadata.buffer.front() else {
+            self.snapshots_mp.insert(symbol.clone(), Some(snapshot));
+            return Ok(());
+        };
+        // 5. The first processed event should have U <= the first update id AND u >= it
+        if first.first_update_id > first_update_id {
+            let reason = format!(
+                "snapshot {} is older than buffered update {}",
+                last_update_id, first.first_update_id
+            );
+            return self.resync(symbol, reason);
+        }
+
+        let mut buffer = std::mem::take(&mut metadata.buffer);
+        let first = buffer
+            .pop_front()
+            .expect("the buffer holds the first event");
+        metadata.is_first_update = false;
+        metadata.small_u = first.final_update_id;
+        self.transmit(symbol.clone(), snapshot.bids, snapshot.asks, true)?;
+        self.transmit(symbol.clone(), first.bids, first.asks, false)?;
+        // past a gap the rest is buffered again for the next snapshot
+        let mut replayed = Ok(());
+        for update in buffer {
+            let result = self.update(update);
+            if replayed.is_ok() {
+                replayed = result;
+            }
+        }
+        replayed
+    }
 
+    /// COIN-M books are sized in USD contracts. Fails for symbols whose instrument wasn't
+    /// given to [`Manager::with_instruments`]
+    fn to_base_units(
+        &self,
+        symbol: &Symbol,
+        mut levels: Vec<LevelUpdate>,
+    ) -> Result<Vec<LevelUpdate>> {
+        if self.market != Market::COIN_M || levels.is_empty() {
+            return Ok(levels);
+        }
+        let instrument = self
+            .instruments
+            .get(symbol)
+            .filter(|i| i.contract_size > 0.0)
+            .ok_or_else(|| {
+                TorrentError::BadParse(format!("no contract size for Binance {}", symbol))
+            })?;
+        for level in levels.iter_mut().filter(|l| l.0[0] > 0.0) {
+            level.0[1] = instrument.base_qty(level.0[0], level.0[1]);
+        }
+        Ok(levels)
+    }
+
+    /// Drops the book of `symbol`, clears it downstream and asks for a fresh snapshot. Diffs
+    /// are buffered until the snapshot arrives
+    fn resync(&mut self, symbol: &Symbol, reason: String) -> Result<()> {
+        if let Some(metadata) = self.metadata_mp.get_mut(symbol) {
+            metadata.is_first_update = true;
+        }
+        self.snapshots_mp.insert(symbol.clone(), None);
+        if let Some(requests) = &self.snapshot_requests {
+            requests.request(symbol.clone())?;
+        }
+        self.transmit(symbol.clone(), vec![], vec![], true)?;
+        Err(TorrentError::OutOfSync(format!(
+            "Binance {}: {}",
+            symbol, reason
+        )))
+    }
+}
+
+impl DepthCallback<Message<'static>, DepthSnapshot> for Manager {
     fn depth_callback(
         &mut self,
-        msg: Result<Message>,
+        msg: Result<Message<'static>>,
         snapshots_mp: Option<HashMap<Symbol, DepthSnapshot>>,
     ) {
         if let Some(snapshots_mp) = snapshots_mp {
             for (symbol, snapshot) in snapshots_mp {
-                let metadata = Metadata::new(0, true);
-                self.metadata_mp.insert(symbol.clone(), metadata);
+                // diffs buffered while the snapshot was fetched are kept for the replay
+                self.metadata_mp
+                    .entry(symbol.clone())
+                    .or_insert_with(|| Metadata::new(0, true))
+                    .is_first_update = true;
                 self.snapshots_mp.insert(symbol, Some(snapshot));
             }
         }
 
         match msg {
-            Ok(msg) => {
-                // https://binance-docs.github.io/apidocs/spot/en/#how-to-manage-a-local-order-book-correctly
-                if let Message::Depth(update) = msg {
-                    let symbol = update.symbol;
-                    let small_u = &update.final_update_id;
-                    let big_u = &update.first_update_id;
-
-                    let metadata = self.metadata_mp.get_mut(&symbol).unwrap_or_else(|| {
-                        panic!("Received updates for {symbol} which was not subscribed to")
-                    });
-                    let is_first_update = metadata.is_first_update;
-                    let previous_small_u = metadata.small_u;
-
-                    let mut bids_buff = vec![];
-                    let mut asks_buff = vec![];
-                    let mut is_snapshot = false;
-
-                    if is_first_update {
-                        // - `is_first_update` and `maybe_snapshot.is_some()` will be true together
-                        // (only once; at first) and false together all the time
-                        // - `maybe_snapshot` is placed inside the if block to avoid an additional
-                        // unnecessary hashmap lookup (at the expense of readability)
-                        let maybe_snapshot =
-                            self.snapshots_mp.get_mut(&symbol).unwrap_or_else(|| {
-                                panic!("Received updates for {symbol} which was not subscribed to")
-                            });
-
-                        // 4. Drop (skip) event where u <= lastUpdateId
-                        // 5. U <= lastUpdateId + 1 AND u >= lastUpdateId
-                        #[allow(clippy::blocks_in_if_conditions)]
-                        if maybe_snapshot.as_ref().is_some_and(|snapshot| {
-                            let cond = snapshot.last_update_id + 1;
-                            (*big_u <= cond) && (*small_u >= cond)
-                        }) {
-                            let snapshot = maybe_snapshot.take().unwrap();
-                            bids_buff = snapshot.bids;
-                            asks_buff = snapshot.asks;
-                            is_snapshot = true;
-                            *maybe_snapshot = None;
-                            metadata.is_first_update = false;
-                            metadata.small_u = *small_u;
-                        }
-                    // 6. New event's U == previous event's u + 1
-                    } else if *big_u == previous_small_u + 1 {
-                        bids_buff = update.bids;
-                        asks_buff = update.asks;
-                        metadata.small_u = *small_u;
-                    }
-
-                    if !bids_buff.is_empty() || !asks_buff.is_empty() {
-                        // TODO: handle error
-                        let _ = self.transmit(symbol, bids_buff, asks_buff, is_snapshot);
-                    }
+            Ok(Message::Depth(update)) => {
+                if let Err(e) = self.update(update) {
+                    log::warn!("{}", e);
                 }
             }
-            Err(e) => eprintln!("{:?}", e),
+            Ok(Message::Subscribe(_)) => {}
+            Err(e) => log::error!("{}", e),
+        }
+    }
+
+    fn on_snapshot_requests(&mut self, requests: SnapshotRequests) {
+        self.snapshot_requests = Some(requests);
+    }
+
+    fn on_unsubscribe(&mut self, topics: &[String]) {
+        for symbol in topics.iter().filter_map(|t| Binance::symbol(t)) {
+            self.metadata_mp.remove(&symbol);
+            self.snapshots_mp.remove(&symbol);
+            // an empty snapshot clears Binance's levels for `symbol` in the aggregator
+            if let Err(e) = self.transmit(symbol, vec![], vec![], true) {
+                log::error!("{}", e);
+            }
         }
     }
 }
 
 impl Transmitor<Vec<LevelUpdate>> for Manager {
     fn resolve_symbol(&self, symbol: &Symbol) -> Option<CcyPair> {
-        let regex =
-            regex::Regex::new(r"^(\w+)(BTC|TRY|ETH|BNB|USDT|PAX|TUSD|USDC|XRP|USDS)$").ok()?;
-        let capture = regex.captures(symbol)?;
+        let pattern = match self.market {
+            Market::SPOT => r"^(\w+)(BTC|TRY|ETH|BNB|USDT|PAX|TUSD|USDC|XRP|USDS)$",
+            // BTCUSDT and BTCUSDT_240329
+            Market::USD_M => r"^([A-Z0-9]+?)(USDT|USDC|BUSD)(?:_(\d{6}))?$",
+            // BTCUSD_PERP and BTCUSD_240329
+            Market::COIN_M => r"^([A-Z0-9]+?)(USD)_(?:PERP|(\d{6}))$",
+        };
+        let capture = regex::Regex::new(pattern).ok()?.captures(symbol)?;
         let (base, quote) = (capture.get(1)?, capture.get(2)?);
+        let product = match (self.market, capture.get(3)) {
+            (Market::SPOT, _) => "spot".to_string(),
+            (_, Some(expiry)) => format!("futures-{}", expiry.as_str()),
+            (_, None) => "swap".to_string(),
+        };
 
-        return Some(CcyPair {
+        Some(CcyPair {
             base: base.as_str().to_string(),
             quote: quote.as_str().to_string(),
-            product: "spot".to_string(),
-        });
+            product,
+        })
     }
 
     fn standardise_updates(&self, updates: Vec<LevelUpdate>) -> Vec<Level> {
@@ -353,11 +752,11 @@ impl Transmitor<Vec<LevelUpdate>> for Manager {
         asks: Vec<LevelUpdate>,
         is_snapshot: bool,
     ) -> Result<()> {
-        let bids = self.standardise_updates(bids);
-        let asks = self.standardise_updates(asks);
-        let ccy_pair = self
-            .resolve_symbol(&symbol)
-            .unwrap_or_else(|| panic!("{symbol} is not supported for Binance"));
+        let ccy_pair = self.resolve_symbol(&symbol).ok_or_else(|| {
+            TorrentError::BadParse(format!("{} is not supported for Binance", symbol))
+        })?;
+        let bids = self.standardise_updates(self.to_base_units(&symbol, bids)?);
+        let asks = self.standardise_updates(self.to_base_units(&symbol, asks)?);
 
         let (event, encoding) = if is_snapshot {
             let event = make_snapshot_event(bids, asks, ccy_pair, Exchange::BINANCE)
@@ -1,22 +1,82 @@
 use crate::aggregator::Transmitor;
-use crate::flatbuffer::{make_snapshot_event, make_update_event};
+use crate::arbitration::Sequenced;
+use crate::flatbuffer::{
+    make_sequenced_snapshot_event, make_sequenced_update_event, make_ticker_event, make_trade_event,
+};
 use crate::orderbook::l2::Level;
 use crate::utils::{
-    CcyPair, Exchange, Result, Symbol, TorrentError, ASSET_CONSTANT_MULTIPLIER, DATA_FEED,
+    from_str, CcyPair, Decimal, Exchange, Origin, Result, Symbol, Ticker, TorrentError, Trade,
+    ASSET_CONSTANT_MULTIPLIER, DATA_FEED, TICKER_FEED, TRADE_FEED,
+};
+use crate::websocket::{
+    Ack, Decode, Limits, MessageCallback, PingRoutine, RateLimit, Request, WebSocketClient, Wss,
 };
-use crate::websocket::{MessageCallback, Wss};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::{self, Display};
 use std::ops::Deref;
 use std::result;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite;
 use zenoh::prelude::sync::SyncResolve;
 use zenoh::prelude::Encoding;
 
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
-pub enum Message {
+pub enum Message<'a> {
     Subscribe(SubscribeMessage),
-    Orderbook(PublicResponse<OrderbookEvent>),
+    #[serde(borrow)]
+    Orderbook(PublicResponse<'a, OrderbookEvent<'a>>),
+    #[serde(borrow)]
+    Trades(PublicResponse<'a, Vec<TradeEvent>>),
+    #[serde(borrow)]
+    Ticker(PublicResponse<'a, TickerEvent>),
+}
+
+impl Decode for Message<'_> {
+    type Frame<'a> = Message<'a>;
+
+    fn into_owned(frame: Message<'_>) -> Self {
+        match frame {
+            Message::Subscribe(m) => Message::Subscribe(m),
+            Message::Orderbook(m) => Message::Orderbook(m.into_owned(OrderbookEvent::into_owned)),
+            Message::Trades(m) => Message::Trades(m.into_owned(|data| data)),
+            Message::Ticker(m) => Message::Ticker(m.into_owned(|data| data)),
+        }
+    }
+
+    fn into_frame<'a>(self) -> Message<'a>
+    where
+        Self: 'a,
+    {
+        self
+    }
+}
+
+impl Sequenced for Message<'_> {
+    /// `u` restarts at 1 when Bybit's service restarts, the cross sequence `seq` doesn't
+    fn sequence(&self) -> Option<(String, u64)> {
+        match self {
+            Message::Orderbook(m) => Some((m.topic.to_string(), m.data.seq.unwrap_or(m.data.u))),
+            Message::Trades(m) => {
+                let seq = m.data.iter().filter_map(|t| t.seq).max()?;
+                Some((m.topic.to_string(), seq))
+            }
+            // spot tickers carry no cross sequence
+            Message::Ticker(m) => Some((m.topic.to_string(), m.cs?)),
+            Message::Subscribe(_) => None,
+        }
+    }
+
+    /// After a service restart, the snapshot comes as a delta with `u` = 1
+    fn is_snapshot(&self) -> bool {
+        matches!(self, Message::Orderbook(m) if m.r#type == "snapshot" || m.data.u == 1)
+    }
+
+    fn stream(channel: &str, topic: &str) -> String {
+        format!("{}.{}", channel, Bybit::symbol(topic))
+    }
 }
 
 #[derive(Debug)]
@@ -55,13 +115,8 @@ impl<'de> serde::de::Visitor<'de> for LevelUpdateVisitor {
         let mut arr = [0.0; 2];
         let mut i = 0;
 
-        while let Some(v) = seq.next_element::<String>()? {
-            // v is a valid string but not parsable to f64
-            let non_f64 = serde::de::Unexpected::Str(v.as_str());
-            let element = v
-                .parse()
-                .map_err(|_| serde::de::Error::invalid_value(non_f64, &self))?;
-            arr[i] = element;
+        while let Some(Decimal(v)) = seq.next_element()? {
+            arr[i] = v;
             i += 1;
         }
         Ok(LevelUpdate(arr))
@@ -80,28 +135,111 @@ pub struct SubscribeMessage {
 
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
-pub struct OrderbookEvent {
-    /// Symbol name
-    pub s: String,
+pub struct OrderbookEvent<'a> {
+    /// Symbol name, borrowed from the frame unless it had to be unescaped
+    #[serde(borrow)]
+    pub s: Cow<'a, str>,
     /// Bids. For `snapshot` stream, the element is sorted by price in descending order
     pub b: Vec<LevelUpdate>,
     /// Asks. For `snapshot` stream, the element is sorted by price in ascending order
     pub a: Vec<LevelUpdate>,
     /// Update ID, which is a sequence. Occasionally, you'll receive "u"=1, which is a
     /// snapshot data due to the restart of the service
-    u: u64,
+    pub u: u64,
     /// Cross sequence.
     /// You can use this field to compare different levels orderbook data, and for the smaller seq,
     /// then it means the data is generated earlier
     pub seq: Option<u64>,
 }
 
+impl OrderbookEvent<'_> {
+    /// Copies the symbol borrowed from the frame
+    pub fn into_owned(self) -> OrderbookEvent<'static> {
+        OrderbookEvent {
+            s: Cow::Owned(self.s.into_owned()),
+            b: self.b,
+            a: self.a,
+            u: self.u,
+            seq: self.seq,
+        }
+    }
+}
+
+/// Public trade. Inverse sizes are in USD contracts
+#[derive(Deserialize, Debug)]
+pub struct TradeEvent {
+    /// Trade id
+    pub i: String,
+    /// Symbol name
+    pub s: String,
+    /// Price
+    #[serde(deserialize_with = "from_str")]
+    pub p: f64,
+    /// Size
+    #[serde(deserialize_with = "from_str")]
+    pub v: f64,
+    /// Taker side, `Buy` or `Sell`
+    #[serde(rename = "S")]
+    pub side: String,
+    /// The timestamp in (ms) that the order is filled
+    #[serde(rename = "T")]
+    pub t: u64,
+    /// Cross sequence, derivatives only
+    pub seq: Option<u64>,
+}
+
+/// Ticker of any category. Spot and options always send the whole ticker, linear and
+/// inverse send a snapshot followed by deltas of the changed fields only
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TickerEvent {
+    pub symbol: String,
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub last_price: Option<f64>,
+    /// Volume over 24 hours, in USD contracts for inverse
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub volume_24h: Option<f64>,
+    /// Turnover over 24 hours, in the base currency for inverse
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub turnover_24h: Option<f64>,
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub mark_price: Option<f64>,
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub index_price: Option<f64>,
+    /// Perpetuals only
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub funding_rate: Option<f64>,
+}
+
+impl TickerEvent {
+    /// Applies a delta, whose missing fields are unchanged
+    pub fn merge(&mut self, delta: TickerEvent) {
+        self.last_price = delta.last_price.or(self.last_price);
+        self.volume_24h = delta.volume_24h.or(self.volume_24h);
+        self.turnover_24h = delta.turnover_24h.or(self.turnover_24h);
+        self.mark_price = delta.mark_price.or(self.mark_price);
+        self.index_price = delta.index_price.or(self.index_price);
+        self.funding_rate = delta.funding_rate.or(self.funding_rate);
+    }
+}
+
+/// Bybit sends `""` for fields that don't apply, e.g. the funding rate of futures
+fn optional_decimal<'de, D>(deserializer: D) -> result::Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: Option<String> = Deserialize::deserialize(deserializer)?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
 #[derive(Deserialize, Debug)]
-pub struct PublicResponse<Data> {
+pub struct PublicResponse<'a, Data> {
     /// Topic name
-    pub topic: String,
+    #[serde(borrow)]
+    pub topic: Cow<'a, str>,
     /// Data type: `snapshot`, `delta`
-    pub r#type: String,
+    #[serde(borrow)]
+    pub r#type: Cow<'a, str>,
     /// The timestamp in (ms) that the system generates the data
     pub ts: u64,
     /// Type to deserialise data from `Bybit` websocket stream
@@ -109,37 +247,95 @@ pub struct PublicResponse<Data> {
     /// The timestamp from the match engine when this orderbook data is produced
     /// It can be correlated with T from public trade channel
     pub cts: Option<u64>,
+    /// Cross sequence of derivative tickers
+    pub cs: Option<u64>,
+}
+
+impl<Data> PublicResponse<'_, Data> {
+    /// Copies the strings borrowed from the frame, `data` copying those of the payload
+    pub fn into_owned<D>(self, data: impl FnOnce(Data) -> D) -> PublicResponse<'static, D> {
+        PublicResponse {
+            topic: Cow::Owned(self.topic.into_owned()),
+            r#type: Cow::Owned(self.r#type.into_owned()),
+            ts: self.ts,
+            data: data(self.data),
+            cts: self.cts,
+            cs: self.cs,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
 struct Subscription {
     req_id: String,
     op: String,
     args: Vec<String>,
 }
 
+/// Bybit's markets, each streamed from an endpoint of its own
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    #[default]
+    SPOT,
+    /// USDT and USDC settled perpetuals and futures
+    LINEAR,
+    /// coin settled perpetuals and futures, sized in USD
+    INVERSE,
+    /// USDC settled options
+    OPTION,
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::SPOT => write!(f, "spot"),
+            Category::LINEAR => write!(f, "linear"),
+            Category::INVERSE => write!(f, "inverse"),
+            Category::OPTION => write!(f, "option"),
+        }
+    }
+}
+
+impl Category {
+    /// Public websocket endpoint of the category
+    pub fn url(&self) -> String {
+        format!("wss://stream.bybit.com/v5/public/{}", self)
+    }
+}
+
+/// Spot and derivatives offer 1, 50 and 200 levels, derivatives also 500. Options only
+/// offer 25 and 100
 #[allow(non_camel_case_types)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Depth {
     ONE,
+    TWENTY_FIVE,
     FIFTY,
+    ONE_HUNDRED,
     TWO_HUNDRED,
+    FIVE_HUNDRED,
 }
 
 #[allow(non_camel_case_types)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Channel {
     ORDERBOOK(Depth),
+    PUBLIC_TRADE,
+    TICKERS,
 }
 
 impl Display for Channel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Channel::PUBLIC_TRADE => write!(f, "publicTrade"),
+            Channel::TICKERS => write!(f, "tickers"),
             Channel::ORDERBOOK(depth) => match depth {
                 Depth::ONE => write!(f, "orderbook.1"),
+                Depth::TWENTY_FIVE => write!(f, "orderbook.25"),
                 Depth::FIFTY => write!(f, "orderbook.50"),
+                Depth::ONE_HUNDRED => write!(f, "orderbook.100"),
                 Depth::TWO_HUNDRED => write!(f, "orderbook.200"),
+                Depth::FIVE_HUNDRED => write!(f, "orderbook.500"),
             },
         }
     }
@@ -155,47 +351,114 @@ impl Display for Bybit {
 }
 
 impl Bybit {
+    /// Spot endpoint. See [`Category::url`] for derivatives
     pub const URL: &'static str = "wss://stream.bybit.com/v5/public/spot";
+    pub const REST_URL: &'static str = "https://api.bybit.com";
 
     pub fn new() -> Self {
         Self(0)
     }
-}
 
-impl Wss for Bybit {
-    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<String> {
+    /// Bybit's symbol of a topic. `BTC-USDT` pairs are joined into `BTCUSDT`, anything else
+    /// is taken as Bybit's own symbol, e.g. `BTCUSD`, `BTC-29DEC23` or `BTC-29DEC23-40000-C`
+    pub fn symbol(topic: &str) -> Symbol {
+        match topic.split_once('-') {
+            Some((base, quote))
+                if !quote.contains('-') && quote.chars().all(|c| c.is_ascii_alphabetic()) =>
+            {
+                format!("{}{}", base, quote).to_uppercase()
+            }
+            _ => topic.to_uppercase(),
+        }
+    }
+
+    /// Builds an `op` (`subscribe` or `unsubscribe`) request for `topics` of `channel`
+    fn request(&mut self, op: &str, channel: String, topics: Vec<String>) -> Result<Request> {
         let args = topics
-            .into_iter()
-            .filter_map(|t| {
-                let parts = t.split('-').collect::<Vec<&str>>();
-                if parts.len() == 2 {
-                    Some(parts.join(""))
-                } else {
-                    None
-                }
-            })
-            .map(|sym| format!("{}.{}", channel, sym.to_uppercase()))
+            .iter()
+            .map(|t| format!("{}.{}", channel, Self::symbol(t)))
             .collect::<Vec<String>>();
+        let req_id = self.0.to_string();
         let sub = Subscription {
-            req_id: self.0.to_string(),
-            op: "subscribe".to_string(),
+            req_id: req_id.clone(),
+            op: op.to_string(),
             args,
         };
         self.0 += 1;
 
         match serde_json::to_string(&sub) {
-            Ok(s) => Ok(s),
+            Ok(payload) => Ok(Request {
+                payload,
+                ack_ids: vec![req_id],
+            }),
             Err(e) => Err(TorrentError::BadParse(format!("serde parse error: {}", e))),
         }
     }
+
+    /// Bybit answers every request with its `req_id` and whether it succeeded
+    pub fn parse_ack(msg: &str) -> Option<Ack> {
+        if !msg.contains("\"op\"") {
+            return None;
+        }
+        let ack = serde_json::from_str::<SubscribeMessage>(msg).ok()?;
+        if ack.op != "subscribe" && ack.op != "unsubscribe" {
+            return None;
+        }
+        if ack.success {
+            Some(Ack::Success(ack.req_id))
+        } else {
+            Some(Ack::Failure(ack.req_id, ack.ret_msg))
+        }
+    }
+}
+
+impl Wss for Bybit {
+    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("subscribe", channel, topics)
+    }
+    fn unsubscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("unsubscribe", channel, topics)
+    }
     fn to_enum(&self) -> Exchange {
         Exchange::BYBIT
     }
+
+    fn ack_fn(&self) -> fn(&str) -> Option<Ack> {
+        Self::parse_ack
+    }
+
+    /// Bybit spot accepts at most 10 args per subscribe request. Connections are kept to a
+    /// manageable number of topics
+    fn limits(&self) -> Limits {
+        Limits {
+            topics_per_connection: 200,
+            topics_per_request: 10,
+            // no published figure, frequent requests get the ip banned
+            messages: Some(RateLimit::new(10, Duration::from_secs(1))),
+        }
+    }
+
+    /// Bybit recommends a `{"op":"ping"}` every 20s. Spot answers with `"ret_msg":"pong"`
+    /// while derivatives answer with `"op":"pong"`
+    fn ping_routine(&self) -> Option<PingRoutine> {
+        Some(PingRoutine::new(
+            Duration::from_secs(20),
+            || tungstenite::Message::text(r#"{"op":"ping"}"#),
+            |msg| msg.contains(r#""ret_msg":"pong""#) || msg.contains(r#""op":"pong""#),
+        ))
+    }
 }
 
-#[derive(Debug)]
 pub struct Manager {
     zenoh: zenoh::Session,
+    /// market of the books, which Bybit's symbols don't tell apart
+    category: Category,
+    /// last update id of every book by topic. Deltas of books without one, unsubscribed or
+    /// out of sync, are dropped until their next snapshot
+    update_ids: HashMap<String, u64>,
+    /// last full ticker of every symbol, which deltas are applied to
+    tickers: HashMap<Symbol, TickerEvent>,
+    client: Option<WebSocketClient>,
 }
 
 impl Default for Manager {
@@ -204,7 +467,13 @@ impl Default for Manager {
         let session = zenoh::open(config)
             .res()
             .unwrap_or_else(|e| panic!("Couldn't open zenoh session: {e}"));
-        Self { zenoh: session }
+        Self {
+            zenoh: session,
+            category: Category::default(),
+            update_ids: HashMap::default(),
+            tickers: HashMap::default(),
+            client: None,
+        }
     }
 }
 
@@ -212,70 +481,147 @@ impl Manager {
     pub fn new() -> Self {
         Default::default()
     }
-}
 
-impl MessageCallback<Message> for Manager {
-    fn message_callback(&mut self, msg: Result<Message>) -> Result<()> {
-        match msg? {
-            Message::Orderbook(update) => {
-                let is_snapshot = update.r#type == "snapshot";
-                let symbol = update.data.s;
-                let _ = self.transmit(symbol, update.data.b, update.data.a, is_snapshot);
-            }
-            Message::Subscribe(_) => {}
+    /// Books of `category`, connected to its [`Category::url`]. Defaults to spot
+    pub fn with_category(mut self, category: Category) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Resubscribes through `client` to resync books that missed an update. Without it,
+    /// they stay out of sync until the next reconnect
+    pub fn with_client(mut self, client: WebSocketClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Closes the zenoh session once the puts already made have been flushed
+    pub fn close(self) -> Result<()> {
+        self.zenoh
+            .close()
+            .res()
+            .map_err(|e| TorrentError::BadConnection(format!("failed to close zenoh: {}", e)))
+    }
+
+    /// Drops the book of `topic`, asks for a fresh snapshot and clears Bybit's levels for
+    /// `symbol` in the aggregator
+    fn resync(&mut self, topic: &str, symbol: Symbol, reason: String) -> Result<()> {
+        self.update_ids.remove(topic);
+        if let (Some(client), Some((channel, _))) = (&self.client, topic.rsplit_once('.')) {
+            client.resubscribe(channel.to_string(), vec![symbol.clone()])?;
         }
-        Ok(())
+        self.transmit(symbol, vec![], vec![], true)?;
+        Err(TorrentError::OutOfSync(format!(
+            "Bybit {}: {}",
+            topic, reason
+        )))
     }
-}
 
-impl Transmitor<Vec<LevelUpdate>> for Manager {
-    fn resolve_symbol(&self, symbol: &Symbol) -> Option<CcyPair> {
-        let regex =
-            regex::Regex::new(r"^(\w+)(BTC|TRY|ETH|BNB|USDT|PAX|TUSD|USDC|XRP|USDS)$").ok()?;
-        let capture = regex.captures(symbol)?;
-        let (base, quote) = (capture.get(1)?, capture.get(2)?);
+    fn transmit_trade(&self, trade: TradeEvent) -> Result<()> {
+        let ccy_pair = self.resolve_symbol(&trade.s).ok_or_else(|| {
+            TorrentError::BadParse(format!("{} is not supported for Bybit", trade.s))
+        })?;
+        let qty = match self.category {
+            Category::INVERSE => trade.v / trade.p,
+            _ => trade.v,
+        };
+        let trade = Trade {
+            id: trade.i,
+            price: (trade.p * ASSET_CONSTANT_MULTIPLIER) as u64,
+            qty: (qty * ASSET_CONSTANT_MULTIPLIER) as u64,
+            is_buy: trade.side == "Buy",
+            timestamp: trade.t * 1000,
+        };
 
-        return Some(CcyPair {
-            base: base.as_str().to_string(),
-            quote: quote.as_str().to_string(),
-            product: "spot".to_string(),
-        });
+        let event = make_trade_event(trade, ccy_pair, Exchange::BYBIT)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        let encoding = Encoding::APP_CUSTOM
+            .with_suffix("trade_event")
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        let tradefeed = zenoh::key_expr::keyexpr::new(TRADE_FEED)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        self.zenoh
+            .put(tradefeed, event.buff)
+            .encoding(encoding)
+            .res()
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))
     }
 
-    fn standardise_updates(&self, updates: Vec<LevelUpdate>) -> Vec<Level> {
-        updates
-            .into_iter()
-            .map(|update| {
-                let update = *update;
-                let update_0 = (update[0] * ASSET_CONSTANT_MULTIPLIER) as u64;
-                let update_1 = (update[1] * ASSET_CONSTANT_MULTIPLIER) as u64;
-                Level::new(update_0, update_1)
-            })
-            .collect()
+    /// Normalised ticker of `symbol`, once its last price is known
+    pub fn ticker(&self, symbol: &Symbol, timestamp: u64) -> Option<Ticker> {
+        let ticker = self.tickers.get(symbol)?;
+        // inverse volumes are in USD contracts, their turnover is in the base currency
+        let volume = match self.category {
+            Category::INVERSE => ticker.turnover_24h,
+            _ => ticker.volume_24h,
+        };
+        let scale = |v: f64| (v * ASSET_CONSTANT_MULTIPLIER) as u64;
+        Some(Ticker {
+            last_price: scale(ticker.last_price?),
+            volume_24h: volume.map_or(0, scale),
+            mark_price: ticker.mark_price.map(scale),
+            funding_rate: ticker.funding_rate,
+            timestamp: timestamp * 1000,
+        })
     }
 
-    fn transmit(
+    fn transmit_ticker(&self, symbol: Symbol, timestamp: u64) -> Result<()> {
+        let Some(ticker) = self.ticker(&symbol, timestamp) else {
+            return Ok(());
+        };
+        let ccy_pair = self.resolve_symbol(&symbol).ok_or_else(|| {
+            TorrentError::BadParse(format!("{symbol} is not supported for Bybit"))
+        })?;
+
+        let event = make_ticker_event(ticker, ccy_pair, Exchange::BYBIT)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        let encoding = Encoding::APP_CUSTOM
+            .with_suffix("ticker_event")
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        let tickerfeed = zenoh::key_expr::keyexpr::new(TICKER_FEED)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        self.zenoh
+            .put(tickerfeed, event.buff)
+            .encoding(encoding)
+            .res()
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))
+    }
+
+    /// Inverse books are sized in USD contracts, converted here into the base currency
+    fn to_base_units(&self, mut levels: Vec<LevelUpdate>) -> Vec<LevelUpdate> {
+        if self.category == Category::INVERSE {
+            for level in levels.iter_mut().filter(|l| l.0[0] > 0.0) {
+                level.0[1] /= level.0[0];
+            }
+        }
+        levels
+    }
+
+    /// Publishes a book event stamped with Bybit's cross sequence and matching engine time
+    fn transmit_sequenced(
         &self,
         symbol: Symbol,
         bids: Vec<LevelUpdate>,
         asks: Vec<LevelUpdate>,
         is_snapshot: bool,
+        origin: Origin,
     ) -> Result<()> {
-        let bids = self.standardise_updates(bids);
-        let asks = self.standardise_updates(asks);
-        let ccy_pair = self
-            .resolve_symbol(&symbol)
-            .unwrap_or_else(|| panic!("{symbol} is not supported for Bybit"));
+        let bids = self.standardise_updates(self.to_base_units(bids));
+        let asks = self.standardise_updates(self.to_base_units(asks));
+        let ccy_pair = self.resolve_symbol(&symbol).ok_or_else(|| {
+            TorrentError::BadParse(format!("{} is not supported for Bybit", symbol))
+        })?;
 
         let (event, encoding) = if is_snapshot {
-            let event = make_snapshot_event(bids, asks, ccy_pair, Exchange::BYBIT)
-                .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+            let event =
+                make_sequenced_snapshot_event(bids, asks, ccy_pair, Exchange::BYBIT, origin)
+                    .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
             let encoding = Encoding::APP_CUSTOM
                 .with_suffix("snapshot_event")
                 .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
             (event, encoding)
         } else {
-            let event = make_update_event(bids, asks, ccy_pair, Exchange::BYBIT)
+            let event = make_sequenced_update_event(bids, asks, ccy_pair, Exchange::BYBIT, origin)
                 .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
             let encoding = Encoding::APP_CUSTOM
                 .with_suffix("update_event")
@@ -292,3 +638,181 @@ impl Transmitor<Vec<LevelUpdate>> for Manager {
             .map_err(|e| TorrentError::BadZenoh(e.to_string()))
     }
 }
+
+impl MessageCallback<Message<'_>> for Manager {
+    fn message_callback(&mut self, msg: Result<Message<'_>>) -> Result<()> {
+        match msg? {
+            Message::Orderbook(update) => {
+                let data = update.data;
+                // `u` restarts at 1 with a snapshot when Bybit's service restarts
+                let is_snapshot = update.r#type == "snapshot" || data.u == 1;
+                if !is_snapshot {
+                    match self.update_ids.get(update.topic.as_ref()) {
+                        None => return Ok(()),
+                        Some(&last) if data.u != last + 1 => {
+                            let reason = format!("update {} after {}", data.u, last);
+                            return self.resync(&update.topic, data.s.into_owned(), reason);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                self.update_ids.insert(update.topic.into_owned(), data.u);
+                let origin = Origin {
+                    sequence: data.seq.unwrap_or(data.u),
+                    timestamp: update.cts.unwrap_or(update.ts) * 1000,
+                };
+                let symbol = data.s.into_owned();
+                self.transmit_sequenced(symbol, data.b, data.a, is_snapshot, origin)?;
+            }
+            Message::Trades(update) => {
+                for trade in update.data {
+                    self.transmit_trade(trade)?;
+                }
+            }
+            Message::Ticker(update) => {
+                let symbol = update.data.symbol.clone();
+                if update.r#type == "snapshot" {
+                    self.tickers.insert(symbol.clone(), update.data);
+                } else if let Some(ticker) = self.tickers.get_mut(&symbol) {
+                    ticker.merge(update.data);
+                } else {
+                    // a delta before its snapshot
+                    return Ok(());
+                }
+                self.transmit_ticker(symbol, update.cts.unwrap_or(update.ts))?;
+            }
+            Message::Subscribe(_) => {}
+        }
+        Ok(())
+    }
+
+    fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        if channel == Channel::TICKERS.to_string() {
+            for topic in topics {
+                self.tickers.remove(&Bybit::symbol(topic));
+            }
+            return Ok(());
+        }
+        if !channel.starts_with("orderbook") {
+            return Ok(());
+        }
+        for topic in topics {
+            let symbol = Bybit::symbol(topic);
+            self.update_ids.remove(&format!("{}.{}", channel, symbol));
+            // an empty snapshot clears Bybit's levels for `symbol` in the aggregator
+            self.transmit(symbol, vec![], vec![], true)?;
+        }
+        Ok(())
+    }
+}
+
+impl Transmitor<Vec<LevelUpdate>> for Manager {
+    /// Products are `spot`, `swap`, `futures-<expiry>` and `option-<expiry>-<strike>-<C|P>`,
+    /// so that derivatives aren't merged into spot books. Expiries are `YYMMDD` like Okx's
+    /// and Binance's
+    fn resolve_symbol(&self, symbol: &Symbol) -> Option<CcyPair> {
+        let pattern = match self.category {
+            Category::SPOT => r"^(\w+)(BTC|TRY|ETH|BNB|USDT|PAX|TUSD|USDC|XRP|USDS)$",
+            // BTCUSDT, BTCPERP for USDC, BTCUSDT-29DEC23 and BTC-29DEC23 for USDC
+            Category::LINEAR => r"^([A-Z0-9]+?)(USDT|USDC|PERP|)(?:-(\d+[A-Z]{3}\d{2}))?$",
+            // BTCUSD and BTCUSDH24
+            Category::INVERSE => r"^([A-Z0-9]+?)(USD)([FGHJKMNQUVXZ]\d{2})?$",
+            // BTC-29DEC23-40000-C and BTC-29DEC23-40000-C-USDT
+            Category::OPTION => r"^([A-Z0-9]+)-(\d+[A-Z]{3}\d{2})-(\d+-[CP])(?:-(USDT|USDC))?$",
+        };
+        let capture = regex::Regex::new(pattern).ok()?.captures(symbol)?;
+        let base = capture.get(1)?.as_str().to_string();
+
+        let (quote, product) = match self.category {
+            Category::SPOT => (capture.get(2)?.as_str(), "spot".to_string()),
+            Category::LINEAR => {
+                let quote = match capture.get(2)?.as_str() {
+                    "PERP" | "" => "USDC",
+                    quote => quote,
+                };
+                let product = match capture.get(3) {
+                    Some(expiry) => format!("futures-{}", expiry_date(expiry.as_str())?),
+                    // neither a quote nor an expiry, e.g. a spot pair's base
+                    None if capture.get(2)?.as_str().is_empty() => return None,
+                    None => "swap".to_string(),
+                };
+                (quote, product)
+            }
+            Category::INVERSE => {
+                let product = match capture.get(3) {
+                    Some(code) => format!("futures-{}", quarterly_expiry(code.as_str())?),
+                    None => "swap".to_string(),
+                };
+                ("USD", product)
+            }
+            Category::OPTION => {
+                let quote = capture.get(4).map_or("USDC", |q| q.as_str());
+                let expiry = expiry_date(capture.get(2)?.as_str())?;
+                (
+                    quote,
+                    format!("option-{}-{}", expiry, capture.get(3)?.as_str()),
+                )
+            }
+        };
+
+        Some(CcyPair {
+            base,
+            quote: quote.to_string(),
+            product,
+        })
+    }
+
+    fn standardise_updates(&self, updates: Vec<LevelUpdate>) -> Vec<Level> {
+        updates
+            .into_iter()
+            .map(|update| {
+                let update = *update;
+                let update_0 = (update[0] * ASSET_CONSTANT_MULTIPLIER) as u64;
+                let update_1 = (update[1] * ASSET_CONSTANT_MULTIPLIER) as u64;
+                Level::new(update_0, update_1)
+            })
+            .collect()
+    }
+
+    fn transmit(
+        &self,
+        symbol: Symbol,
+        bids: Vec<LevelUpdate>,
+        asks: Vec<LevelUpdate>,
+        is_snapshot: bool,
+    ) -> Result<()> {
+        self.transmit_sequenced(symbol, bids, asks, is_snapshot, Origin::default())
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// `29DEC23` as `231229`
+fn expiry_date(date: &str) -> Option<String> {
+    let (day, rest) = date.split_at(date.len().checked_sub(5)?);
+    let (month, year) = rest.split_at(3);
+    let month = MONTHS.iter().position(|m| *m == month)? + 1;
+    let day = day.parse::<u32>().ok()?;
+    Some(format!("{}{:02}{:02}", year, month, day))
+}
+
+/// Inverse futures expire on the last Friday of the month of their code, e.g. `H24` (March
+/// 2024) on `240329`
+fn quarterly_expiry(code: &str) -> Option<String> {
+    let month = "FGHJKMNQUVXZ".find(code.get(..1)?)? as u32 + 1;
+    let year = code.get(1..)?.parse::<u32>().ok()?;
+    let last_day = match month {
+        2 if year % 4 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    // weekday of the last day by Sakamoto's method, 0 being Sunday and 5 Friday
+    let offset = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4][month as usize - 1];
+    let y = 2000 + year - (month < 3) as u32;
+    let weekday = (y + y / 4 - y / 100 + y / 400 + offset + last_day) % 7;
+    let day = last_day - (weekday + 2) % 7;
+    Some(format!("{:02}{:02}{:02}", year, month, day))
+}
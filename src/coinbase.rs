@@ -1,8 +1,31 @@
-use crate::utils::{from_str, now, Exchange, Result, TorrentError};
-use crate::websocket::{MessageCallback, Wss};
+use crate::aggregator::Transmitor;
+use crate::arbitration::Sequenced;
+use crate::config::{CoinbaseAuth, CoinbaseCredentials};
+use crate::flatbuffer::{make_snapshot_event, make_update_event};
+use crate::orderbook::l2::Level;
+use crate::utils::{
+    from_str, now, CcyPair, Exchange, Result, Symbol, TorrentError, ASSET_CONSTANT_MULTIPLIER,
+    DATA_FEED,
+};
+use crate::websocket::{
+    Ack, Decode, Limits, MessageCallback, RateLimit, Request, WebSocketClient, Wss,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use hmac::{Hmac, Mac};
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::pkcs8::DecodePrivateKey;
+use p256::SecretKey;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use zenoh::prelude::sync::SyncResolve;
+use zenoh::prelude::Encoding;
 
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
@@ -12,8 +35,102 @@ pub enum Message {
     Subscribe(SubscribeMessage),
 }
 
+/// Coinbase's messages own their strings
+impl Decode for Message {
+    type Frame<'a> = Self;
+
+    fn into_owned(frame: Self) -> Self {
+        frame
+    }
+
+    fn into_frame<'a>(self) -> Self
+    where
+        Self: 'a,
+    {
+        self
+    }
+}
+
+impl Sequenced for Message {
+    /// `sequence_num` counts the messages of a single connection, so it can't match copies
+    /// across connections. The exchange's `event_time` of the latest update does, with the
+    /// [`Sequenced::digest`] of the updates telling apart batches stamped with the same time
+    fn sequence(&self) -> Option<(String, u64)> {
+        match self {
+            Message::Level2(m) => {
+                let event = m.events.first()?;
+                let event_time = event
+                    .updates
+                    .iter()
+                    .filter_map(|u| timestamp_nanos(&u.event_time))
+                    .max()?;
+                Some((event.product_id.clone(), event_time))
+            }
+            Message::Heartbeats(_) | Message::Subscribe(_) => None,
+        }
+    }
+
+    /// Snapshots' levels are all stamped with the epoch
+    fn is_snapshot(&self) -> bool {
+        matches!(self, Message::Level2(m) if m.events.first().is_some_and(|e| e.r#type == "snapshot"))
+    }
+
+    fn stream(_channel: &str, topic: &str) -> String {
+        topic.to_string()
+    }
+
+    fn digest(&self) -> Option<u64> {
+        let Message::Level2(m) = self else {
+            return None;
+        };
+        let mut hasher = DefaultHasher::new();
+        for event in &m.events {
+            event.r#type.hash(&mut hasher);
+            for update in &event.updates {
+                update.side.hash(&mut hasher);
+                update.event_time.hash(&mut hasher);
+                update.price_level.to_bits().hash(&mut hasher);
+                update.new_quantity.to_bits().hash(&mut hasher);
+            }
+        }
+        Some(hasher.finish())
+    }
+
+    fn connection_sequence(&self) -> Option<u64> {
+        match self {
+            Message::Level2(m) => Some(m.sequence_num),
+            Message::Heartbeats(m) => Some(m.sequence_num),
+            Message::Subscribe(m) => Some(m.sequence_num),
+        }
+    }
+}
+
+/// Nanoseconds since the epoch of an RFC 3339 UTC timestamp such as
+/// `2023-02-09T20:32:50.714964855Z`
+fn timestamp_nanos(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|v| v.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(|v| v.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    let nanos = format!("{:0<9}", fraction).get(..9)?.parse::<i64>().ok()?;
+
+    // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(secs * 1_000_000_000 + nanos).ok()
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Level2Update {
+    /// `bid` or `offer`
     pub side: String,
     pub event_time: String,
     #[serde(deserialize_with = "from_str")]
@@ -81,10 +198,17 @@ pub struct SubscribeMessage {
     pub channel: String,
     pub client_id: String,
     pub timestamp: String,
-    pub sequence_id: u64,
+    #[serde(alias = "sequence_id")]
+    pub sequence_num: u64,
     pub events: Vec<SubscribeEvent>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ErrorMessage {
+    pub r#type: String,
+    pub message: String,
+}
+
 #[derive(Serialize, Debug)]
 struct LegacySubscription {
     pub r#type: String,
@@ -95,6 +219,22 @@ struct LegacySubscription {
     pub signature: String,
 }
 
+#[derive(Serialize, Debug)]
+struct JwtHeader<'a> {
+    alg: &'a str,
+    kid: &'a str,
+    nonce: String,
+    typ: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    nbf: u64,
+    exp: u64,
+}
+
 #[derive(Serialize, Debug)]
 struct JwtSubscription {
     pub r#type: String,
@@ -124,7 +264,7 @@ impl Display for Channel {
 pub struct Coinbase {
     key_name: String,
     private_key: String,
-    is_legacy: bool,
+    auth: CoinbaseAuth,
 }
 
 impl Display for Coinbase {
@@ -135,12 +275,17 @@ impl Display for Coinbase {
 
 impl Coinbase {
     pub const URL: &'static str = "wss://advanced-trade-ws.coinbase.com";
+    pub const REST_URL: &'static str = "https://api.coinbase.com";
+
+    /// How long a JWT stays valid. Coinbase rejects tokens older than 2 minutes
+    const JWT_EXPIRY: u64 = 120;
 
-    pub fn new(key_name: String, private_key: String, is_legacy: bool) -> Self {
+    pub fn new(credentials: CoinbaseCredentials) -> Self {
         Self {
-            key_name,
-            private_key,
-            is_legacy,
+            key_name: credentials.key_name,
+            // keys pasted on a single line keep their newlines escaped
+            private_key: credentials.private_key.replace("\\n", "\n"),
+            auth: credentials.auth,
         }
     }
 
@@ -162,19 +307,46 @@ impl Coinbase {
         Ok(hex::encode(signature_bytes))
     }
 
-    fn jwt_sign(&self, _timestamp: &str) -> Result<String> {
-        let _private_key_bytes = self.private_key.as_bytes();
-        unimplemented!()
+    /// Signs a JWT with the EC private key (SEC1 or PKCS#8 PEM) valid from `timestamp`
+    /// for [`Coinbase::JWT_EXPIRY`] seconds. The random nonce makes every token unique
+    fn jwt_sign(&self, timestamp: u64) -> Result<String> {
+        let secret = SecretKey::from_sec1_pem(&self.private_key)
+            .or_else(|_| SecretKey::from_pkcs8_pem(&self.private_key))
+            .map_err(|e| TorrentError::BadParse(format!("Coinbase private key: {}", e)))?;
+
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let header = JwtHeader {
+            alg: "ES256",
+            kid: &self.key_name,
+            nonce: hex::encode(nonce),
+            typ: "JWT",
+        };
+        let claims = JwtClaims {
+            iss: "cdp",
+            sub: &self.key_name,
+            nbf: timestamp,
+            exp: timestamp + Self::JWT_EXPIRY,
+        };
+        let message = format!("{}.{}", base64_json(&header)?, base64_json(&claims)?);
+
+        let signature: Signature = SigningKey::from(secret).sign(message.as_bytes());
+        Ok(format!(
+            "{}.{}",
+            message,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        ))
     }
-}
 
-impl Wss for Coinbase {
-    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<String> {
-        let timestamp = now().to_string();
-        let sub = if self.is_legacy {
+    /// Builds a signed `kind` (`subscribe` or `unsubscribe`) request for `topics` of `channel`.
+    /// JWTs expire quickly, so each request, replays after a reconnect included, signs a new one
+    fn request(&self, kind: &str, channel: String, topics: Vec<String>) -> Result<Request> {
+        let now = now();
+        let timestamp = now.to_string();
+        let sub = if self.auth == CoinbaseAuth::Legacy {
             let signature = self.legacy_sign(timestamp.as_str(), channel.as_str(), &topics)?;
             let legacy_sub = LegacySubscription {
-                r#type: "subscribe".to_string(),
+                r#type: kind.to_string(),
                 product_ids: topics,
                 channel,
                 api_key: self.key_name.clone(),
@@ -183,9 +355,9 @@ impl Wss for Coinbase {
             };
             serde_json::to_string(&legacy_sub)
         } else {
-            let jwt = self.jwt_sign(timestamp.as_str())?;
+            let jwt = self.jwt_sign(now)?;
             let jwt_sub = JwtSubscription {
-                r#type: "subscribe".to_string(),
+                r#type: kind.to_string(),
                 product_ids: topics,
                 channel,
                 jwt,
@@ -195,31 +367,286 @@ impl Wss for Coinbase {
         };
 
         match sub {
-            Ok(s) => Ok(s),
+            // Coinbase answers requests in order without echoing anything identifying them
+            Ok(payload) => Ok(Request {
+                payload,
+                ack_ids: Vec::new(),
+            }),
             Err(e) => Err(TorrentError::BadParse(format!("serde parse error: {}", e))),
         }
     }
+
+    /// Coinbase confirms a request with a message on the `subscriptions` channel listing
+    /// every active subscription and rejects it with an `error` message
+    pub fn parse_ack(msg: &str) -> Option<Ack> {
+        if msg.contains(r#""channel":"subscriptions""#) {
+            return Some(Ack::Success(None));
+        }
+        if !msg.contains(r#""type":"error""#) {
+            return None;
+        }
+        let error = serde_json::from_str::<ErrorMessage>(msg).ok()?;
+        Some(Ack::Failure(None, error.message))
+    }
+}
+
+/// Base64url encoded JSON of a JWT's header or claims
+fn base64_json(part: &impl Serialize) -> Result<String> {
+    serde_json::to_vec(part)
+        .map(|json| URL_SAFE_NO_PAD.encode(json))
+        .map_err(|e| TorrentError::BadParse(format!("serde parse error: {}", e)))
+}
+
+impl Wss for Coinbase {
+    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("subscribe", channel, topics)
+    }
+    fn unsubscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("unsubscribe", channel, topics)
+    }
     fn to_enum(&self) -> Exchange {
         Exchange::COINBASE
     }
+
+    fn ack_fn(&self) -> fn(&str) -> Option<Ack> {
+        Self::parse_ack
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            // 8 messages per second per ip for unauthenticated connections
+            messages: Some(RateLimit::new(8, Duration::from_secs(1))),
+            ..Limits::default()
+        }
+    }
+
+    /// Coinbase doesn't accept client pings; subscribing to `heartbeats` keeps the
+    /// connection (and its idle timeout) alive instead
+    fn heartbeat_channel(&self) -> Option<String> {
+        Some(Channel::HEARTBEATS.to_string())
+    }
 }
 
-pub struct Manager;
+/// Keeps every level2 book of a connection in sync and publishes it on zenoh
+pub struct Manager {
+    zenoh: zenoh::Session,
+    /// `sequence_num` of the connection's latest message
+    sequence_num: Option<u64>,
+    /// products whose book was snapshotted with no message missed since. Updates of other
+    /// products are dropped until their next snapshot
+    synced: HashSet<Symbol>,
+    /// resubscribes out of sync products to get fresh snapshots
+    client: Option<WebSocketClient>,
+    /// whether `sequence_num` is checked here rather than by a redundant feed's legs
+    sequence_check: bool,
+}
+
+impl Default for Manager {
+    fn default() -> Self {
+        let config = zenoh::config::default();
+        let session = zenoh::open(config)
+            .res()
+            .unwrap_or_else(|e| panic!("Couldn't open zenoh session: {e}"));
+        Self {
+            zenoh: session,
+            sequence_num: None,
+            synced: HashSet::default(),
+            client: None,
+            sequence_check: true,
+        }
+    }
+}
+
+impl Manager {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Resubscribes through `client` to resync the books once a message was missed.
+    /// Without it, books stay out of sync until the next reconnect
+    pub fn with_client(mut self, client: WebSocketClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Whether `sequence_num` is checked by the manager, as it is by default. Behind a
+    /// [`RedundantFeed`](crate::arbitration::RedundantFeed), whose legs number their messages
+    /// independently, each leg checks it instead and reports gaps as
+    /// [`TorrentError::OutOfSync`], upon which the books are resynced
+    pub fn with_sequence_check(mut self, sequence_check: bool) -> Self {
+        self.sequence_check = sequence_check;
+        self
+    }
+
+    /// Closes the zenoh session once the puts already made have been flushed
+    pub fn close(self) -> Result<()> {
+        self.zenoh
+            .close()
+            .res()
+            .map_err(|e| TorrentError::BadConnection(format!("failed to close zenoh: {}", e)))
+    }
+
+    /// Checks that no message of the connection was missed: `sequence_num` increments
+    /// by one per message, across channels, and restarts from 0 on a new connection
+    fn check_sequence(&mut self, sequence_num: u64) -> Result<()> {
+        if !self.sequence_check {
+            return Ok(());
+        }
+        let expected = self.sequence_num.map_or(0, |last| last + 1);
+        self.sequence_num = Some(sequence_num);
+        if sequence_num == expected || sequence_num == 0 {
+            return Ok(());
+        }
+        self.resync(format!(
+            "expected sequence_num {} but got {}",
+            expected, sequence_num
+        ))
+    }
+
+    /// Marks every book out of sync, clears it downstream and asks for fresh snapshots
+    fn resync(&mut self, reason: String) -> Result<()> {
+        let products = self.synced.drain().collect::<Vec<Symbol>>();
+        if products.is_empty() {
+            return Ok(());
+        }
+        if let Some(client) = &self.client {
+            client.resubscribe(Channel::LEVEL2.to_string(), products.clone())?;
+        }
+        for product in &products {
+            self.transmit(product.clone(), vec![], vec![], true)?;
+        }
+        Err(TorrentError::OutOfSync(format!(
+            "Coinbase {}: {}",
+            products.join(", "),
+            reason
+        )))
+    }
+
+    fn process(&mut self, event: Level2Event) -> Result<()> {
+        let is_snapshot = match event.r#type.as_str() {
+            "snapshot" => {
+                self.synced.insert(event.product_id.clone());
+                true
+            }
+            "update" if self.synced.contains(&event.product_id) => false,
+            "update" => return Ok(()),
+            kind => {
+                return Err(TorrentError::BadParse(format!(
+                    "unknown level2 event type: {}",
+                    kind
+                )))
+            }
+        };
+        let (bids, asks) = event
+            .updates
+            .into_iter()
+            .partition::<Vec<Level2Update>, _>(|u| u.side == "bid");
+        self.transmit(event.product_id, bids, asks, is_snapshot)
+    }
+}
 
 impl MessageCallback<Message> for Manager {
     fn message_callback(&mut self, msg: Result<Message>) -> Result<()> {
-        let msg = msg?;
-        match msg {
-            Message::Subscribe(_m) => {
-                unimplemented!()
+        let msg = match msg {
+            Ok(msg) => msg,
+            // a redundant feed's leg missed a message
+            Err(TorrentError::OutOfSync(reason)) => return self.resync(reason),
+            Err(e) => {
+                if e.is_disconnect() {
+                    // the next connection numbers its messages from 0 again
+                    self.sequence_num = None;
+                }
+                return Err(e);
             }
-            Message::Heartbeats(_m) => {
-                unimplemented!()
-            }
-            Message::Level2(_m) => {
-                unimplemented!()
+        };
+        match msg {
+            // acknowledgements are resolved by the `WebSocketClient`
+            Message::Subscribe(m) => self.check_sequence(m.sequence_num),
+            Message::Heartbeats(m) => self.check_sequence(m.sequence_num),
+            Message::Level2(m) => {
+                // a snapshot in the same message is still good after a gap
+                let sequence = self.check_sequence(m.sequence_num);
+                for event in m.events {
+                    self.process(event)?;
+                }
+                sequence
             }
         }
+    }
+
+    fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        if channel != Channel::LEVEL2.to_string() {
+            return Ok(());
+        }
+        for product in topics {
+            self.synced.remove(product);
+            // an empty snapshot clears Coinbase's levels for `product` in the aggregator
+            self.transmit(product.clone(), vec![], vec![], true)?;
+        }
         Ok(())
     }
 }
+
+impl Transmitor<Vec<Level2Update>> for Manager {
+    fn resolve_symbol(&self, symbol: &Symbol) -> Option<CcyPair> {
+        let parts = symbol.split('-').collect::<Vec<&str>>();
+        if parts.len() == 2 {
+            Some(CcyPair {
+                base: parts[0].to_string(),
+                quote: parts[1].to_string(),
+                product: "spot".to_string(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn standardise_updates(&self, updates: Vec<Level2Update>) -> Vec<Level> {
+        updates
+            .into_iter()
+            .map(|update| {
+                let price = (update.price_level * ASSET_CONSTANT_MULTIPLIER) as u64;
+                let qty = (update.new_quantity * ASSET_CONSTANT_MULTIPLIER) as u64;
+                Level::new(price, qty)
+            })
+            .collect()
+    }
+
+    fn transmit(
+        &self,
+        symbol: Symbol,
+        bids: Vec<Level2Update>,
+        asks: Vec<Level2Update>,
+        is_snapshot: bool,
+    ) -> Result<()> {
+        let bids = self.standardise_updates(bids);
+        let asks = self.standardise_updates(asks);
+        let ccy_pair = self.resolve_symbol(&symbol).ok_or_else(|| {
+            TorrentError::BadParse(format!("{} is not supported for Coinbase", symbol))
+        })?;
+
+        let (event, encoding) = if is_snapshot {
+            let event = make_snapshot_event(bids, asks, ccy_pair, Exchange::COINBASE)
+                .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+            let encoding = Encoding::APP_CUSTOM
+                .with_suffix("snapshot_event")
+                .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+            (event, encoding)
+        } else {
+            let event = make_update_event(bids, asks, ccy_pair, Exchange::COINBASE)
+                .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+            let encoding = Encoding::APP_CUSTOM
+                .with_suffix("update_event")
+                .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+            (event, encoding)
+        };
+
+        let datafeed = zenoh::key_expr::keyexpr::new(DATA_FEED)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        self.zenoh
+            .put(datafeed, event.buff)
+            .encoding(encoding)
+            .res()
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))
+    }
+}
@@ -0,0 +1,175 @@
+use crate::binance::Binance;
+use crate::bybit::Bybit;
+use crate::coinbase::Coinbase;
+use crate::okx::Okx;
+use crate::utils::{Exchange, Result, TorrentError};
+use std::env;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Websocket and REST base urls of an exchange
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub websocket: String,
+    pub rest: String,
+}
+
+impl Endpoint {
+    pub fn new(websocket: impl Into<String>, rest: impl Into<String>) -> Self {
+        Self {
+            websocket: websocket.into(),
+            rest: rest.into(),
+        }
+    }
+
+    /// Overrides the urls with `<PREFIX>_WS_URL` and `<PREFIX>_REST_URL` when `var` has them
+    fn override_from(mut self, prefix: &str, var: &impl Fn(&str) -> Option<String>) -> Self {
+        if let Some(url) = var(&format!("{}_WS_URL", prefix)) {
+            self.websocket = url;
+        }
+        if let Some(url) = var(&format!("{}_REST_URL", prefix)) {
+            self.rest = url;
+        }
+        self
+    }
+}
+
+/// Endpoints of every supported exchange. Defaults to the public production endpoints
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoints {
+    pub binance: Endpoint,
+    pub okx: Endpoint,
+    pub bybit: Endpoint,
+    pub coinbase: Endpoint,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            binance: Endpoint::new(Binance::URL, Binance::REST_URL),
+            okx: Endpoint::new(Okx::URL, Okx::REST_URL),
+            bybit: Endpoint::new(Bybit::URL, Bybit::REST_URL),
+            coinbase: Endpoint::new(Coinbase::URL, Coinbase::REST_URL),
+        }
+    }
+}
+
+impl Endpoints {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Default endpoints overridden by `BINANCE_WS_URL`, `BINANCE_REST_URL`, `OKX_WS_URL`,
+    /// ... environment variables, e.g. to target a testnet, a regional host or a mock server
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Default endpoints overridden by the variables `var` looks up, named like
+    /// [`Endpoints::from_env`]'s
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+        Self {
+            binance: default.binance.override_from("BINANCE", &var),
+            okx: default.okx.override_from("OKX", &var),
+            bybit: default.bybit.override_from("BYBIT", &var),
+            coinbase: default.coinbase.override_from("COINBASE", &var),
+        }
+    }
+
+    pub fn with(mut self, exchange: Exchange, endpoint: Endpoint) -> Self {
+        *self.get_mut(&exchange) = endpoint;
+        self
+    }
+
+    pub fn get(&self, exchange: &Exchange) -> &Endpoint {
+        match exchange {
+            Exchange::BINANCE => &self.binance,
+            Exchange::OKX => &self.okx,
+            Exchange::BYBIT => &self.bybit,
+            Exchange::COINBASE => &self.coinbase,
+        }
+    }
+
+    fn get_mut(&mut self, exchange: &Exchange) -> &mut Endpoint {
+        match exchange {
+            Exchange::BINANCE => &mut self.binance,
+            Exchange::OKX => &mut self.okx,
+            Exchange::BYBIT => &mut self.bybit,
+            Exchange::COINBASE => &mut self.coinbase,
+        }
+    }
+}
+
+/// How Coinbase subscriptions are signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinbaseAuth {
+    /// HMAC signature of legacy API keys
+    Legacy,
+    /// ES256 JWT of Coinbase Cloud keys, re-signed for every request
+    Jwt,
+}
+
+impl Display for CoinbaseAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoinbaseAuth::Legacy => write!(f, "legacy"),
+            CoinbaseAuth::Jwt => write!(f, "jwt"),
+        }
+    }
+}
+
+impl FromStr for CoinbaseAuth {
+    type Err = TorrentError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "legacy" => Ok(CoinbaseAuth::Legacy),
+            "jwt" => Ok(CoinbaseAuth::Jwt),
+            _ => Err(TorrentError::BadParse(format!(
+                "unknown Coinbase auth `{}`, expected `legacy` or `jwt`",
+                s
+            ))),
+        }
+    }
+}
+
+/// Api key Coinbase subscriptions are signed with
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoinbaseCredentials {
+    pub key_name: String,
+    pub private_key: String,
+    pub auth: CoinbaseAuth,
+}
+
+impl CoinbaseCredentials {
+    pub fn new(
+        key_name: impl Into<String>,
+        private_key: impl Into<String>,
+        auth: CoinbaseAuth,
+    ) -> Self {
+        Self {
+            key_name: key_name.into(),
+            private_key: private_key.into(),
+            auth,
+        }
+    }
+
+    /// Credentials from `KEY_NAME` and `PRIVATE_KEY`, signed as `COINBASE_AUTH` says
+    /// (`jwt` or `legacy`). Without it, PEM private keys sign JWTs and others are legacy
+    /// secrets. `None` unless both the key name and private key are set, fails if
+    /// `COINBASE_AUTH` is neither
+    pub fn from_env() -> Result<Option<Self>> {
+        let key_name = env::var("KEY_NAME").unwrap_or_default();
+        let private_key = env::var("PRIVATE_KEY").unwrap_or_default();
+        if key_name.is_empty() || private_key.is_empty() {
+            return Ok(None);
+        }
+        let auth = match env::var("COINBASE_AUTH") {
+            Ok(auth) => auth.parse()?,
+            Err(_) if private_key.contains("-----BEGIN") => CoinbaseAuth::Jwt,
+            Err(_) => CoinbaseAuth::Legacy,
+        };
+        Ok(Some(Self::new(key_name, private_key, auth)))
+    }
+}
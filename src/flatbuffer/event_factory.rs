@@ -7,8 +7,16 @@ use super::snapshot::atrimo::snapshot_events::{
     SnapshotDataArgs, SnapshotEvent, SnapshotEventArgs, SnapshotEventMessage,
     SnapshotEventMessageArgs,
 };
+use super::ticker::atrimo::ticker_events::{
+    finish_ticker_event_message_buffer, TickerEvent, TickerEventArgs, TickerEventMessage,
+    TickerEventMessageArgs,
+};
+use super::trade::atrimo::trade_events::{
+    finish_trade_event_message_buffer, TradeEvent, TradeEventArgs, TradeEventMessage,
+    TradeEventMessageArgs,
+};
 use crate::orderbook::l2::Level;
-use crate::utils::{CcyPair, Exchange, FlatbufferEvent};
+use crate::utils::{CcyPair, Exchange, FlatbufferEvent, Origin, Ticker, Trade};
 use failure::ResultExt;
 use std::time::SystemTime;
 
@@ -17,6 +25,17 @@ pub fn make_update_event(
     asks: Vec<Level>,
     ccy_pair: CcyPair,
     exchange: Exchange,
+) -> Result<FlatbufferEvent, failure::Error> {
+    make_sequenced_update_event(bids, asks, ccy_pair, exchange, Origin::default())
+}
+
+/// Update event carrying the exchange's sequence number and timestamp
+pub fn make_sequenced_update_event(
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+    ccy_pair: CcyPair,
+    exchange: Exchange,
+    origin: Origin,
 ) -> Result<FlatbufferEvent, failure::Error> {
     let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(10240000);
     let exchange_name = builder.create_string(exchange.to_string().as_str());
@@ -56,6 +75,8 @@ pub fn make_update_event(
             instrument: Some(instrument_name),
             timestamp: timestamp as u64,
             update: Some(update_data),
+            sequence: origin.sequence,
+            exchange_timestamp: origin.timestamp,
         },
     );
     let update_event_message = UpdateEventMessage::create(
@@ -80,6 +101,17 @@ pub fn make_snapshot_event(
     asks: Vec<Level>,
     ccy_pair: CcyPair,
     exchange: Exchange,
+) -> Result<FlatbufferEvent, failure::Error> {
+    make_sequenced_snapshot_event(bids, asks, ccy_pair, exchange, Origin::default())
+}
+
+/// Snapshot event carrying the exchange's sequence number and timestamp
+pub fn make_sequenced_snapshot_event(
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+    ccy_pair: CcyPair,
+    exchange: Exchange,
+    origin: Origin,
 ) -> Result<FlatbufferEvent, failure::Error> {
     let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(10240000);
     let exchange_name = builder.create_string(exchange.to_string().as_str());
@@ -119,6 +151,8 @@ pub fn make_snapshot_event(
             instrument: Some(instrument_name),
             timestamp: timestamp as u64,
             snapshot: Some(snapshot_data),
+            sequence: origin.sequence,
+            exchange_timestamp: origin.timestamp,
         },
     );
 
@@ -139,6 +173,95 @@ pub fn make_snapshot_event(
     })
 }
 
+pub fn make_trade_event(
+    trade: Trade,
+    ccy_pair: CcyPair,
+    exchange: Exchange,
+) -> Result<FlatbufferEvent, failure::Error> {
+    let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(256);
+    let exchange_name = builder.create_string(exchange.to_string().as_str());
+    let instrument_name = builder.create_string(ccy_pair.to_string().as_str());
+    let trade_id = builder.create_string(trade.id.as_str());
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .with_context(|e| format!("duration since epoch error {e}"))?
+        .as_micros();
+
+    let trade_event = TradeEvent::create(
+        &mut builder,
+        &TradeEventArgs {
+            exchange: Some(exchange_name),
+            instrument: Some(instrument_name),
+            timestamp: timestamp as u64,
+            trade_id: Some(trade_id),
+            price: trade.price,
+            qty: trade.qty,
+            is_buy: trade.is_buy,
+            trade_timestamp: trade.timestamp,
+        },
+    );
+    let trade_event_message = TradeEventMessage::create(
+        &mut builder,
+        &TradeEventMessageArgs {
+            trade_event: Some(trade_event),
+            message_type: 2,
+        },
+    );
+
+    finish_trade_event_message_buffer(&mut builder, trade_event_message);
+    let buffer = builder.finished_data().to_vec();
+
+    Ok(FlatbufferEvent {
+        stream_id: 2,
+        buff: buffer,
+    })
+}
+
+pub fn make_ticker_event(
+    ticker: Ticker,
+    ccy_pair: CcyPair,
+    exchange: Exchange,
+) -> Result<FlatbufferEvent, failure::Error> {
+    let mut builder = flatbuffers::FlatBufferBuilder::with_capacity(256);
+    let exchange_name = builder.create_string(exchange.to_string().as_str());
+    let instrument_name = builder.create_string(ccy_pair.to_string().as_str());
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .with_context(|e| format!("duration since epoch error {e}"))?
+        .as_micros();
+
+    let ticker_event = TickerEvent::create(
+        &mut builder,
+        &TickerEventArgs {
+            exchange: Some(exchange_name),
+            instrument: Some(instrument_name),
+            timestamp: timestamp as u64,
+            last_price: ticker.last_price,
+            volume_24h: ticker.volume_24h,
+            mark_price: ticker.mark_price.unwrap_or_default(),
+            funding_rate: ticker.funding_rate.unwrap_or_default(),
+            exchange_timestamp: ticker.timestamp,
+        },
+    );
+    let ticker_event_message = TickerEventMessage::create(
+        &mut builder,
+        &TickerEventMessageArgs {
+            ticker_event: Some(ticker_event),
+            message_type: 3,
+        },
+    );
+
+    finish_ticker_event_message_buffer(&mut builder, ticker_event_message);
+    let buffer = builder.finished_data().to_vec();
+
+    Ok(FlatbufferEvent {
+        stream_id: 3,
+        buff: buffer,
+    })
+}
+
 pub fn make_snapshot_aggregator(
     bids: Vec<Level>,
     asks: Vec<Level>,
@@ -182,6 +305,7 @@ pub fn make_snapshot_aggregator(
             instrument: Some(instrument_name),
             timestamp: timestamp as u64,
             snapshot: Some(snapshot_data),
+            ..Default::default()
         },
     );
     let snapshot_event_message = SnapshotEventMessage::create(
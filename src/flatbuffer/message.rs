@@ -34,16 +34,17 @@ pub mod atrimo {
             since = "2.0.0",
             note = "Use associated constants instead. This will no longer be generated in 2021."
         )]
-        pub const ENUM_MAX_MESSAGE_TYPE: i8 = 2;
+        pub const ENUM_MAX_MESSAGE_TYPE: i8 = 3;
         #[deprecated(
             since = "2.0.0",
             note = "Use associated constants instead. This will no longer be generated in 2021."
         )]
         #[allow(non_camel_case_types)]
-        pub const ENUM_VALUES_MESSAGE_TYPE: [MessageType; 3] = [
+        pub const ENUM_VALUES_MESSAGE_TYPE: [MessageType; 4] = [
             MessageType::SNAPSHOT_EVENT,
             MessageType::UPDATE_EVENT,
             MessageType::TRADE_EVENT,
+            MessageType::TICKER_EVENT,
         ];
 
         #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -54,17 +55,23 @@ pub mod atrimo {
             pub const SNAPSHOT_EVENT: Self = Self(0);
             pub const UPDATE_EVENT: Self = Self(1);
             pub const TRADE_EVENT: Self = Self(2);
+            pub const TICKER_EVENT: Self = Self(3);
 
             pub const ENUM_MIN: i8 = 0;
-            pub const ENUM_MAX: i8 = 2;
-            pub const ENUM_VALUES: &'static [Self] =
-                &[Self::SNAPSHOT_EVENT, Self::UPDATE_EVENT, Self::TRADE_EVENT];
+            pub const ENUM_MAX: i8 = 3;
+            pub const ENUM_VALUES: &'static [Self] = &[
+                Self::SNAPSHOT_EVENT,
+                Self::UPDATE_EVENT,
+                Self::TRADE_EVENT,
+                Self::TICKER_EVENT,
+            ];
             /// Returns the variant's name or "" if unknown.
             pub fn variant_name(self) -> Option<&'static str> {
                 match self {
                     Self::SNAPSHOT_EVENT => Some("SNAPSHOT_EVENT"),
                     Self::UPDATE_EVENT => Some("UPDATE_EVENT"),
                     Self::TRADE_EVENT => Some("TRADE_EVENT"),
+                    Self::TICKER_EVENT => Some("TICKER_EVENT"),
                     _ => None,
                 }
             }
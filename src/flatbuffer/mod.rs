@@ -1,5 +1,8 @@
 pub mod event_factory;
-pub use event_factory::{make_snapshot_aggregator, make_snapshot_event, make_update_event};
+pub use event_factory::{
+    make_sequenced_snapshot_event, make_sequenced_update_event, make_snapshot_aggregator,
+    make_snapshot_event, make_ticker_event, make_trade_event, make_update_event,
+};
 pub mod orderbook;
 pub use orderbook::atrimo::update_events::{
     root_as_update_event_message, UpdateAskData, UpdateBidData,
@@ -8,5 +11,9 @@ pub mod snapshot;
 pub use snapshot::atrimo::snapshot_events::{
     root_as_snapshot_event_message, SnapshotAskData, SnapshotBidData,
 };
+pub mod ticker;
+pub use ticker::atrimo::ticker_events::root_as_ticker_event_message;
+pub mod trade;
+pub use trade::atrimo::trade_events::root_as_trade_event_message;
 mod message;
 mod pricing_info;
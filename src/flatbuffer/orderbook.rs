@@ -421,6 +421,8 @@ pub mod atrimo {
             pub const VT_INSTRUMENT: flatbuffers::VOffsetT = 6;
             pub const VT_TIMESTAMP: flatbuffers::VOffsetT = 8;
             pub const VT_UPDATE: flatbuffers::VOffsetT = 10;
+            pub const VT_SEQUENCE: flatbuffers::VOffsetT = 12;
+            pub const VT_EXCHANGE_TIMESTAMP: flatbuffers::VOffsetT = 14;
 
             #[inline]
             pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -432,6 +434,8 @@ pub mod atrimo {
                 args: &'args UpdateEventArgs<'args>,
             ) -> flatbuffers::WIPOffset<UpdateEvent<'bldr>> {
                 let mut builder = UpdateEventBuilder::new(_fbb);
+                builder.add_exchange_timestamp(args.exchange_timestamp);
+                builder.add_sequence(args.sequence);
                 builder.add_timestamp(args.timestamp);
                 if let Some(x) = args.update {
                     builder.add_update(x);
@@ -488,6 +492,28 @@ pub mod atrimo {
                     )
                 }
             }
+            #[inline]
+            pub fn sequence(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(UpdateEvent::VT_SEQUENCE, Some(0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn exchange_timestamp(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(UpdateEvent::VT_EXCHANGE_TIMESTAMP, Some(0))
+                        .unwrap()
+                }
+            }
         }
 
         impl flatbuffers::Verifiable for UpdateEvent<'_> {
@@ -514,6 +540,8 @@ pub mod atrimo {
                         Self::VT_UPDATE,
                         false,
                     )?
+                    .visit_field::<u64>("sequence", Self::VT_SEQUENCE, false)?
+                    .visit_field::<u64>("exchange_timestamp", Self::VT_EXCHANGE_TIMESTAMP, false)?
                     .finish();
                 Ok(())
             }
@@ -523,6 +551,8 @@ pub mod atrimo {
             pub instrument: Option<flatbuffers::WIPOffset<&'a str>>,
             pub timestamp: u64,
             pub update: Option<flatbuffers::WIPOffset<UpdateData<'a>>>,
+            pub sequence: u64,
+            pub exchange_timestamp: u64,
         }
         impl<'a> Default for UpdateEventArgs<'a> {
             #[inline]
@@ -532,6 +562,8 @@ pub mod atrimo {
                     instrument: None,
                     timestamp: 0,
                     update: None,
+                    sequence: 0,
+                    exchange_timestamp: 0,
                 }
             }
         }
@@ -569,6 +601,19 @@ pub mod atrimo {
                     );
             }
             #[inline]
+            pub fn add_sequence(&mut self, sequence: u64) {
+                self.fbb_
+                    .push_slot::<u64>(UpdateEvent::VT_SEQUENCE, sequence, 0);
+            }
+            #[inline]
+            pub fn add_exchange_timestamp(&mut self, exchange_timestamp: u64) {
+                self.fbb_.push_slot::<u64>(
+                    UpdateEvent::VT_EXCHANGE_TIMESTAMP,
+                    exchange_timestamp,
+                    0,
+                );
+            }
+            #[inline]
             pub fn new(
                 _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
             ) -> UpdateEventBuilder<'a, 'b> {
@@ -592,6 +637,8 @@ pub mod atrimo {
                 ds.field("instrument", &self.instrument());
                 ds.field("timestamp", &self.timestamp());
                 ds.field("update", &self.update());
+                ds.field("sequence", &self.sequence());
+                ds.field("exchange_timestamp", &self.exchange_timestamp());
                 ds.finish()
             }
         }
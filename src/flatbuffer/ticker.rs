@@ -0,0 +1,528 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+// @generated
+
+// use core::cmp::Ordering;
+// use core::mem;
+
+extern crate flatbuffers;
+// use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod atrimo {
+
+    use core::cmp::Ordering;
+    use core::mem;
+
+    extern crate flatbuffers;
+    use self::flatbuffers::{EndianScalar, Follow};
+    #[allow(unused_imports, dead_code)]
+    pub mod ticker_events {
+
+        use core::cmp::Ordering;
+        use core::mem;
+
+        extern crate flatbuffers;
+        use self::flatbuffers::{EndianScalar, Follow};
+
+        pub enum TickerEventOffset {}
+        #[derive(Copy, Clone, PartialEq)]
+
+        pub struct TickerEvent<'a> {
+            pub _tab: flatbuffers::Table<'a>,
+        }
+
+        impl<'a> flatbuffers::Follow<'a> for TickerEvent<'a> {
+            type Inner = TickerEvent<'a>;
+            #[inline]
+            unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+                Self {
+                    _tab: flatbuffers::Table::new(buf, loc),
+                }
+            }
+        }
+
+        impl<'a> TickerEvent<'a> {
+            pub const VT_EXCHANGE: flatbuffers::VOffsetT = 4;
+            pub const VT_INSTRUMENT: flatbuffers::VOffsetT = 6;
+            pub const VT_TIMESTAMP: flatbuffers::VOffsetT = 8;
+            pub const VT_LAST_PRICE: flatbuffers::VOffsetT = 10;
+            pub const VT_VOLUME_24H: flatbuffers::VOffsetT = 12;
+            pub const VT_MARK_PRICE: flatbuffers::VOffsetT = 14;
+            pub const VT_FUNDING_RATE: flatbuffers::VOffsetT = 16;
+            pub const VT_EXCHANGE_TIMESTAMP: flatbuffers::VOffsetT = 18;
+
+            #[inline]
+            pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+                TickerEvent { _tab: table }
+            }
+            #[allow(unused_mut)]
+            pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+                _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+                args: &'args TickerEventArgs<'args>,
+            ) -> flatbuffers::WIPOffset<TickerEvent<'bldr>> {
+                let mut builder = TickerEventBuilder::new(_fbb);
+                builder.add_exchange_timestamp(args.exchange_timestamp);
+                builder.add_funding_rate(args.funding_rate);
+                builder.add_mark_price(args.mark_price);
+                builder.add_volume_24h(args.volume_24h);
+                builder.add_last_price(args.last_price);
+                builder.add_timestamp(args.timestamp);
+                if let Some(x) = args.instrument {
+                    builder.add_instrument(x);
+                }
+                if let Some(x) = args.exchange {
+                    builder.add_exchange(x);
+                }
+                builder.finish()
+            }
+
+            #[inline]
+            pub fn exchange(&self) -> Option<&'a str> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<flatbuffers::ForwardsUOffset<&str>>(TickerEvent::VT_EXCHANGE, None)
+                }
+            }
+            #[inline]
+            pub fn instrument(&self) -> Option<&'a str> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<flatbuffers::ForwardsUOffset<&str>>(TickerEvent::VT_INSTRUMENT, None)
+                }
+            }
+            #[inline]
+            pub fn timestamp(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TickerEvent::VT_TIMESTAMP, Some(0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn last_price(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TickerEvent::VT_LAST_PRICE, Some(0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn volume_24h(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TickerEvent::VT_VOLUME_24H, Some(0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn mark_price(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TickerEvent::VT_MARK_PRICE, Some(0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn funding_rate(&self) -> f64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<f64>(TickerEvent::VT_FUNDING_RATE, Some(0.0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn exchange_timestamp(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TickerEvent::VT_EXCHANGE_TIMESTAMP, Some(0))
+                        .unwrap()
+                }
+            }
+        }
+
+        impl flatbuffers::Verifiable for TickerEvent<'_> {
+            #[inline]
+            fn run_verifier(
+                v: &mut flatbuffers::Verifier,
+                pos: usize,
+            ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+                use self::flatbuffers::Verifiable;
+                v.visit_table(pos)?
+                    .visit_field::<flatbuffers::ForwardsUOffset<&str>>(
+                        "exchange",
+                        Self::VT_EXCHANGE,
+                        false,
+                    )?
+                    .visit_field::<flatbuffers::ForwardsUOffset<&str>>(
+                        "instrument",
+                        Self::VT_INSTRUMENT,
+                        false,
+                    )?
+                    .visit_field::<u64>("timestamp", Self::VT_TIMESTAMP, false)?
+                    .visit_field::<u64>("last_price", Self::VT_LAST_PRICE, false)?
+                    .visit_field::<u64>("volume_24h", Self::VT_VOLUME_24H, false)?
+                    .visit_field::<u64>("mark_price", Self::VT_MARK_PRICE, false)?
+                    .visit_field::<f64>("funding_rate", Self::VT_FUNDING_RATE, false)?
+                    .visit_field::<u64>("exchange_timestamp", Self::VT_EXCHANGE_TIMESTAMP, false)?
+                    .finish();
+                Ok(())
+            }
+        }
+        pub struct TickerEventArgs<'a> {
+            pub exchange: Option<flatbuffers::WIPOffset<&'a str>>,
+            pub instrument: Option<flatbuffers::WIPOffset<&'a str>>,
+            pub timestamp: u64,
+            pub last_price: u64,
+            pub volume_24h: u64,
+            pub mark_price: u64,
+            pub funding_rate: f64,
+            pub exchange_timestamp: u64,
+        }
+        impl<'a> Default for TickerEventArgs<'a> {
+            #[inline]
+            fn default() -> Self {
+                TickerEventArgs {
+                    exchange: None,
+                    instrument: None,
+                    timestamp: 0,
+                    last_price: 0,
+                    volume_24h: 0,
+                    mark_price: 0,
+                    funding_rate: 0.0,
+                    exchange_timestamp: 0,
+                }
+            }
+        }
+
+        pub struct TickerEventBuilder<'a: 'b, 'b> {
+            fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+        }
+        impl<'a: 'b, 'b> TickerEventBuilder<'a, 'b> {
+            #[inline]
+            pub fn add_exchange(&mut self, exchange: flatbuffers::WIPOffset<&'b str>) {
+                self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(
+                    TickerEvent::VT_EXCHANGE,
+                    exchange,
+                );
+            }
+            #[inline]
+            pub fn add_instrument(&mut self, instrument: flatbuffers::WIPOffset<&'b str>) {
+                self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(
+                    TickerEvent::VT_INSTRUMENT,
+                    instrument,
+                );
+            }
+            #[inline]
+            pub fn add_timestamp(&mut self, timestamp: u64) {
+                self.fbb_
+                    .push_slot::<u64>(TickerEvent::VT_TIMESTAMP, timestamp, 0);
+            }
+            #[inline]
+            pub fn add_last_price(&mut self, last_price: u64) {
+                self.fbb_
+                    .push_slot::<u64>(TickerEvent::VT_LAST_PRICE, last_price, 0);
+            }
+            #[inline]
+            pub fn add_volume_24h(&mut self, volume_24h: u64) {
+                self.fbb_
+                    .push_slot::<u64>(TickerEvent::VT_VOLUME_24H, volume_24h, 0);
+            }
+            #[inline]
+            pub fn add_mark_price(&mut self, mark_price: u64) {
+                self.fbb_
+                    .push_slot::<u64>(TickerEvent::VT_MARK_PRICE, mark_price, 0);
+            }
+            #[inline]
+            pub fn add_funding_rate(&mut self, funding_rate: f64) {
+                self.fbb_
+                    .push_slot::<f64>(TickerEvent::VT_FUNDING_RATE, funding_rate, 0.0);
+            }
+            #[inline]
+            pub fn add_exchange_timestamp(&mut self, exchange_timestamp: u64) {
+                self.fbb_.push_slot::<u64>(
+                    TickerEvent::VT_EXCHANGE_TIMESTAMP,
+                    exchange_timestamp,
+                    0,
+                );
+            }
+            #[inline]
+            pub fn new(
+                _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            ) -> TickerEventBuilder<'a, 'b> {
+                let start = _fbb.start_table();
+                TickerEventBuilder {
+                    fbb_: _fbb,
+                    start_: start,
+                }
+            }
+            #[inline]
+            pub fn finish(self) -> flatbuffers::WIPOffset<TickerEvent<'a>> {
+                let o = self.fbb_.end_table(self.start_);
+                flatbuffers::WIPOffset::new(o.value())
+            }
+        }
+
+        impl core::fmt::Debug for TickerEvent<'_> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut ds = f.debug_struct("TickerEvent");
+                ds.field("exchange", &self.exchange());
+                ds.field("instrument", &self.instrument());
+                ds.field("timestamp", &self.timestamp());
+                ds.field("last_price", &self.last_price());
+                ds.field("volume_24h", &self.volume_24h());
+                ds.field("mark_price", &self.mark_price());
+                ds.field("funding_rate", &self.funding_rate());
+                ds.field("exchange_timestamp", &self.exchange_timestamp());
+                ds.finish()
+            }
+        }
+        pub enum TickerEventMessageOffset {}
+        #[derive(Copy, Clone, PartialEq)]
+
+        pub struct TickerEventMessage<'a> {
+            pub _tab: flatbuffers::Table<'a>,
+        }
+
+        impl<'a> flatbuffers::Follow<'a> for TickerEventMessage<'a> {
+            type Inner = TickerEventMessage<'a>;
+            #[inline]
+            unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+                Self {
+                    _tab: flatbuffers::Table::new(buf, loc),
+                }
+            }
+        }
+
+        impl<'a> TickerEventMessage<'a> {
+            pub const VT_TRADE_EVENT: flatbuffers::VOffsetT = 4;
+            pub const VT_MESSAGE_TYPE: flatbuffers::VOffsetT = 6;
+
+            #[inline]
+            pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+                TickerEventMessage { _tab: table }
+            }
+            #[allow(unused_mut)]
+            pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+                _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+                args: &'args TickerEventMessageArgs<'args>,
+            ) -> flatbuffers::WIPOffset<TickerEventMessage<'bldr>> {
+                let mut builder = TickerEventMessageBuilder::new(_fbb);
+                builder.add_message_type(args.message_type);
+                if let Some(x) = args.ticker_event {
+                    builder.add_ticker_event(x);
+                }
+                builder.finish()
+            }
+
+            #[inline]
+            pub fn ticker_event(&self) -> Option<TickerEvent<'a>> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab.get::<flatbuffers::ForwardsUOffset<TickerEvent>>(
+                        TickerEventMessage::VT_TRADE_EVENT,
+                        None,
+                    )
+                }
+            }
+            #[inline]
+            pub fn message_type(&self) -> i32 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<i32>(TickerEventMessage::VT_MESSAGE_TYPE, Some(3))
+                        .unwrap()
+                }
+            }
+        }
+
+        impl flatbuffers::Verifiable for TickerEventMessage<'_> {
+            #[inline]
+            fn run_verifier(
+                v: &mut flatbuffers::Verifier,
+                pos: usize,
+            ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+                use self::flatbuffers::Verifiable;
+                v.visit_table(pos)?
+                    .visit_field::<flatbuffers::ForwardsUOffset<TickerEvent>>(
+                        "ticker_event",
+                        Self::VT_TRADE_EVENT,
+                        false,
+                    )?
+                    .visit_field::<i32>("message_type", Self::VT_MESSAGE_TYPE, false)?
+                    .finish();
+                Ok(())
+            }
+        }
+        pub struct TickerEventMessageArgs<'a> {
+            pub ticker_event: Option<flatbuffers::WIPOffset<TickerEvent<'a>>>,
+            pub message_type: i32,
+        }
+        impl<'a> Default for TickerEventMessageArgs<'a> {
+            #[inline]
+            fn default() -> Self {
+                TickerEventMessageArgs {
+                    ticker_event: None,
+                    message_type: 3,
+                }
+            }
+        }
+
+        pub struct TickerEventMessageBuilder<'a: 'b, 'b> {
+            fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+        }
+        impl<'a: 'b, 'b> TickerEventMessageBuilder<'a, 'b> {
+            #[inline]
+            pub fn add_ticker_event(
+                &mut self,
+                ticker_event: flatbuffers::WIPOffset<TickerEvent<'b>>,
+            ) {
+                self.fbb_
+                    .push_slot_always::<flatbuffers::WIPOffset<TickerEvent>>(
+                        TickerEventMessage::VT_TRADE_EVENT,
+                        ticker_event,
+                    );
+            }
+            #[inline]
+            pub fn add_message_type(&mut self, message_type: i32) {
+                self.fbb_
+                    .push_slot::<i32>(TickerEventMessage::VT_MESSAGE_TYPE, message_type, 3);
+            }
+            #[inline]
+            pub fn new(
+                _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            ) -> TickerEventMessageBuilder<'a, 'b> {
+                let start = _fbb.start_table();
+                TickerEventMessageBuilder {
+                    fbb_: _fbb,
+                    start_: start,
+                }
+            }
+            #[inline]
+            pub fn finish(self) -> flatbuffers::WIPOffset<TickerEventMessage<'a>> {
+                let o = self.fbb_.end_table(self.start_);
+                flatbuffers::WIPOffset::new(o.value())
+            }
+        }
+
+        impl core::fmt::Debug for TickerEventMessage<'_> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut ds = f.debug_struct("TickerEventMessage");
+                ds.field("ticker_event", &self.ticker_event());
+                ds.field("message_type", &self.message_type());
+                ds.finish()
+            }
+        }
+        #[inline]
+        /// Verifies that a buffer of bytes contains a `TickerEventMessage`
+        /// and returns it.
+        /// Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `root_as_ticker_event_message_unchecked`.
+        pub fn root_as_ticker_event_message(
+            buf: &[u8],
+        ) -> Result<TickerEventMessage, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::root::<TickerEventMessage>(buf)
+        }
+        #[inline]
+        /// Verifies that a buffer of bytes contains a size prefixed
+        /// `TickerEventMessage` and returns it.
+        /// Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `size_prefixed_root_as_ticker_event_message_unchecked`.
+        pub fn size_prefixed_root_as_ticker_event_message(
+            buf: &[u8],
+        ) -> Result<TickerEventMessage, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::size_prefixed_root::<TickerEventMessage>(buf)
+        }
+        #[inline]
+        /// Verifies, with the given options, that a buffer of bytes
+        /// contains a `TickerEventMessage` and returns it.
+        /// Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `root_as_ticker_event_message_unchecked`.
+        pub fn root_as_ticker_event_message_with_opts<'b, 'o>(
+            opts: &'o flatbuffers::VerifierOptions,
+            buf: &'b [u8],
+        ) -> Result<TickerEventMessage<'b>, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::root_with_opts::<TickerEventMessage<'b>>(opts, buf)
+        }
+        #[inline]
+        /// Verifies, with the given verifier options, that a buffer of
+        /// bytes contains a size prefixed `TickerEventMessage` and returns
+        /// it. Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `root_as_ticker_event_message_unchecked`.
+        pub fn size_prefixed_root_as_ticker_event_message_with_opts<'b, 'o>(
+            opts: &'o flatbuffers::VerifierOptions,
+            buf: &'b [u8],
+        ) -> Result<TickerEventMessage<'b>, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::size_prefixed_root_with_opts::<TickerEventMessage<'b>>(opts, buf)
+        }
+        #[inline]
+        /// Assumes, without verification, that a buffer of bytes contains a TickerEventMessage and returns it.
+        /// # Safety
+        /// Callers must trust the given bytes do indeed contain a valid `TickerEventMessage`.
+        pub unsafe fn root_as_ticker_event_message_unchecked(buf: &[u8]) -> TickerEventMessage {
+            flatbuffers::root_unchecked::<TickerEventMessage>(buf)
+        }
+        #[inline]
+        /// Assumes, without verification, that a buffer of bytes contains a size prefixed TickerEventMessage and returns it.
+        /// # Safety
+        /// Callers must trust the given bytes do indeed contain a valid size prefixed `TickerEventMessage`.
+        pub unsafe fn size_prefixed_root_as_ticker_event_message_unchecked(
+            buf: &[u8],
+        ) -> TickerEventMessage {
+            flatbuffers::size_prefixed_root_unchecked::<TickerEventMessage>(buf)
+        }
+        #[inline]
+        pub fn finish_ticker_event_message_buffer<'a>(
+            fbb: &mut flatbuffers::FlatBufferBuilder<'a>,
+            root: flatbuffers::WIPOffset<TickerEventMessage<'a>>,
+        ) {
+            fbb.finish(root, None);
+        }
+
+        #[inline]
+        pub fn finish_size_prefixed_ticker_event_message_buffer<'a>(
+            fbb: &mut flatbuffers::FlatBufferBuilder<'a>,
+            root: flatbuffers::WIPOffset<TickerEventMessage<'a>>,
+        ) {
+            fbb.finish_size_prefixed(root, None);
+        }
+    } // pub mod TickerEvents
+} // pub mod Atrimo
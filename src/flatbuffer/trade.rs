@@ -0,0 +1,519 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+// @generated
+
+// use core::cmp::Ordering;
+// use core::mem;
+
+extern crate flatbuffers;
+// use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod atrimo {
+
+    use core::cmp::Ordering;
+    use core::mem;
+
+    extern crate flatbuffers;
+    use self::flatbuffers::{EndianScalar, Follow};
+    #[allow(unused_imports, dead_code)]
+    pub mod trade_events {
+
+        use core::cmp::Ordering;
+        use core::mem;
+
+        extern crate flatbuffers;
+        use self::flatbuffers::{EndianScalar, Follow};
+
+        pub enum TradeEventOffset {}
+        #[derive(Copy, Clone, PartialEq)]
+
+        pub struct TradeEvent<'a> {
+            pub _tab: flatbuffers::Table<'a>,
+        }
+
+        impl<'a> flatbuffers::Follow<'a> for TradeEvent<'a> {
+            type Inner = TradeEvent<'a>;
+            #[inline]
+            unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+                Self {
+                    _tab: flatbuffers::Table::new(buf, loc),
+                }
+            }
+        }
+
+        impl<'a> TradeEvent<'a> {
+            pub const VT_EXCHANGE: flatbuffers::VOffsetT = 4;
+            pub const VT_INSTRUMENT: flatbuffers::VOffsetT = 6;
+            pub const VT_TIMESTAMP: flatbuffers::VOffsetT = 8;
+            pub const VT_TRADE_ID: flatbuffers::VOffsetT = 10;
+            pub const VT_PRICE: flatbuffers::VOffsetT = 12;
+            pub const VT_QTY: flatbuffers::VOffsetT = 14;
+            pub const VT_IS_BUY: flatbuffers::VOffsetT = 16;
+            pub const VT_TRADE_TIMESTAMP: flatbuffers::VOffsetT = 18;
+
+            #[inline]
+            pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+                TradeEvent { _tab: table }
+            }
+            #[allow(unused_mut)]
+            pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+                _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+                args: &'args TradeEventArgs<'args>,
+            ) -> flatbuffers::WIPOffset<TradeEvent<'bldr>> {
+                let mut builder = TradeEventBuilder::new(_fbb);
+                builder.add_trade_timestamp(args.trade_timestamp);
+                builder.add_qty(args.qty);
+                builder.add_price(args.price);
+                builder.add_timestamp(args.timestamp);
+                if let Some(x) = args.trade_id {
+                    builder.add_trade_id(x);
+                }
+                if let Some(x) = args.instrument {
+                    builder.add_instrument(x);
+                }
+                if let Some(x) = args.exchange {
+                    builder.add_exchange(x);
+                }
+                builder.add_is_buy(args.is_buy);
+                builder.finish()
+            }
+
+            #[inline]
+            pub fn exchange(&self) -> Option<&'a str> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<flatbuffers::ForwardsUOffset<&str>>(TradeEvent::VT_EXCHANGE, None)
+                }
+            }
+            #[inline]
+            pub fn instrument(&self) -> Option<&'a str> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<flatbuffers::ForwardsUOffset<&str>>(TradeEvent::VT_INSTRUMENT, None)
+                }
+            }
+            #[inline]
+            pub fn timestamp(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TradeEvent::VT_TIMESTAMP, Some(0))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn trade_id(&self) -> Option<&'a str> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<flatbuffers::ForwardsUOffset<&str>>(TradeEvent::VT_TRADE_ID, None)
+                }
+            }
+            #[inline]
+            pub fn price(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe { self._tab.get::<u64>(TradeEvent::VT_PRICE, Some(0)).unwrap() }
+            }
+            #[inline]
+            pub fn qty(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe { self._tab.get::<u64>(TradeEvent::VT_QTY, Some(0)).unwrap() }
+            }
+            #[inline]
+            pub fn is_buy(&self) -> bool {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<bool>(TradeEvent::VT_IS_BUY, Some(false))
+                        .unwrap()
+                }
+            }
+            #[inline]
+            pub fn trade_timestamp(&self) -> u64 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<u64>(TradeEvent::VT_TRADE_TIMESTAMP, Some(0))
+                        .unwrap()
+                }
+            }
+        }
+
+        impl flatbuffers::Verifiable for TradeEvent<'_> {
+            #[inline]
+            fn run_verifier(
+                v: &mut flatbuffers::Verifier,
+                pos: usize,
+            ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+                use self::flatbuffers::Verifiable;
+                v.visit_table(pos)?
+                    .visit_field::<flatbuffers::ForwardsUOffset<&str>>(
+                        "exchange",
+                        Self::VT_EXCHANGE,
+                        false,
+                    )?
+                    .visit_field::<flatbuffers::ForwardsUOffset<&str>>(
+                        "instrument",
+                        Self::VT_INSTRUMENT,
+                        false,
+                    )?
+                    .visit_field::<u64>("timestamp", Self::VT_TIMESTAMP, false)?
+                    .visit_field::<flatbuffers::ForwardsUOffset<&str>>(
+                        "trade_id",
+                        Self::VT_TRADE_ID,
+                        false,
+                    )?
+                    .visit_field::<u64>("price", Self::VT_PRICE, false)?
+                    .visit_field::<u64>("qty", Self::VT_QTY, false)?
+                    .visit_field::<bool>("is_buy", Self::VT_IS_BUY, false)?
+                    .visit_field::<u64>("trade_timestamp", Self::VT_TRADE_TIMESTAMP, false)?
+                    .finish();
+                Ok(())
+            }
+        }
+        pub struct TradeEventArgs<'a> {
+            pub exchange: Option<flatbuffers::WIPOffset<&'a str>>,
+            pub instrument: Option<flatbuffers::WIPOffset<&'a str>>,
+            pub timestamp: u64,
+            pub trade_id: Option<flatbuffers::WIPOffset<&'a str>>,
+            pub price: u64,
+            pub qty: u64,
+            pub is_buy: bool,
+            pub trade_timestamp: u64,
+        }
+        impl<'a> Default for TradeEventArgs<'a> {
+            #[inline]
+            fn default() -> Self {
+                TradeEventArgs {
+                    exchange: None,
+                    instrument: None,
+                    timestamp: 0,
+                    trade_id: None,
+                    price: 0,
+                    qty: 0,
+                    is_buy: false,
+                    trade_timestamp: 0,
+                }
+            }
+        }
+
+        pub struct TradeEventBuilder<'a: 'b, 'b> {
+            fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+        }
+        impl<'a: 'b, 'b> TradeEventBuilder<'a, 'b> {
+            #[inline]
+            pub fn add_exchange(&mut self, exchange: flatbuffers::WIPOffset<&'b str>) {
+                self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(
+                    TradeEvent::VT_EXCHANGE,
+                    exchange,
+                );
+            }
+            #[inline]
+            pub fn add_instrument(&mut self, instrument: flatbuffers::WIPOffset<&'b str>) {
+                self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(
+                    TradeEvent::VT_INSTRUMENT,
+                    instrument,
+                );
+            }
+            #[inline]
+            pub fn add_timestamp(&mut self, timestamp: u64) {
+                self.fbb_
+                    .push_slot::<u64>(TradeEvent::VT_TIMESTAMP, timestamp, 0);
+            }
+            #[inline]
+            pub fn add_trade_id(&mut self, trade_id: flatbuffers::WIPOffset<&'b str>) {
+                self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(
+                    TradeEvent::VT_TRADE_ID,
+                    trade_id,
+                );
+            }
+            #[inline]
+            pub fn add_price(&mut self, price: u64) {
+                self.fbb_.push_slot::<u64>(TradeEvent::VT_PRICE, price, 0);
+            }
+            #[inline]
+            pub fn add_qty(&mut self, qty: u64) {
+                self.fbb_.push_slot::<u64>(TradeEvent::VT_QTY, qty, 0);
+            }
+            #[inline]
+            pub fn add_is_buy(&mut self, is_buy: bool) {
+                self.fbb_
+                    .push_slot::<bool>(TradeEvent::VT_IS_BUY, is_buy, false);
+            }
+            #[inline]
+            pub fn add_trade_timestamp(&mut self, trade_timestamp: u64) {
+                self.fbb_
+                    .push_slot::<u64>(TradeEvent::VT_TRADE_TIMESTAMP, trade_timestamp, 0);
+            }
+            #[inline]
+            pub fn new(
+                _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            ) -> TradeEventBuilder<'a, 'b> {
+                let start = _fbb.start_table();
+                TradeEventBuilder {
+                    fbb_: _fbb,
+                    start_: start,
+                }
+            }
+            #[inline]
+            pub fn finish(self) -> flatbuffers::WIPOffset<TradeEvent<'a>> {
+                let o = self.fbb_.end_table(self.start_);
+                flatbuffers::WIPOffset::new(o.value())
+            }
+        }
+
+        impl core::fmt::Debug for TradeEvent<'_> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut ds = f.debug_struct("TradeEvent");
+                ds.field("exchange", &self.exchange());
+                ds.field("instrument", &self.instrument());
+                ds.field("timestamp", &self.timestamp());
+                ds.field("trade_id", &self.trade_id());
+                ds.field("price", &self.price());
+                ds.field("qty", &self.qty());
+                ds.field("is_buy", &self.is_buy());
+                ds.field("trade_timestamp", &self.trade_timestamp());
+                ds.finish()
+            }
+        }
+        pub enum TradeEventMessageOffset {}
+        #[derive(Copy, Clone, PartialEq)]
+
+        pub struct TradeEventMessage<'a> {
+            pub _tab: flatbuffers::Table<'a>,
+        }
+
+        impl<'a> flatbuffers::Follow<'a> for TradeEventMessage<'a> {
+            type Inner = TradeEventMessage<'a>;
+            #[inline]
+            unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+                Self {
+                    _tab: flatbuffers::Table::new(buf, loc),
+                }
+            }
+        }
+
+        impl<'a> TradeEventMessage<'a> {
+            pub const VT_TRADE_EVENT: flatbuffers::VOffsetT = 4;
+            pub const VT_MESSAGE_TYPE: flatbuffers::VOffsetT = 6;
+
+            #[inline]
+            pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+                TradeEventMessage { _tab: table }
+            }
+            #[allow(unused_mut)]
+            pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+                _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+                args: &'args TradeEventMessageArgs<'args>,
+            ) -> flatbuffers::WIPOffset<TradeEventMessage<'bldr>> {
+                let mut builder = TradeEventMessageBuilder::new(_fbb);
+                builder.add_message_type(args.message_type);
+                if let Some(x) = args.trade_event {
+                    builder.add_trade_event(x);
+                }
+                builder.finish()
+            }
+
+            #[inline]
+            pub fn trade_event(&self) -> Option<TradeEvent<'a>> {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab.get::<flatbuffers::ForwardsUOffset<TradeEvent>>(
+                        TradeEventMessage::VT_TRADE_EVENT,
+                        None,
+                    )
+                }
+            }
+            #[inline]
+            pub fn message_type(&self) -> i32 {
+                // Safety:
+                // Created from valid Table for this object
+                // which contains a valid value in this slot
+                unsafe {
+                    self._tab
+                        .get::<i32>(TradeEventMessage::VT_MESSAGE_TYPE, Some(2))
+                        .unwrap()
+                }
+            }
+        }
+
+        impl flatbuffers::Verifiable for TradeEventMessage<'_> {
+            #[inline]
+            fn run_verifier(
+                v: &mut flatbuffers::Verifier,
+                pos: usize,
+            ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+                use self::flatbuffers::Verifiable;
+                v.visit_table(pos)?
+                    .visit_field::<flatbuffers::ForwardsUOffset<TradeEvent>>(
+                        "trade_event",
+                        Self::VT_TRADE_EVENT,
+                        false,
+                    )?
+                    .visit_field::<i32>("message_type", Self::VT_MESSAGE_TYPE, false)?
+                    .finish();
+                Ok(())
+            }
+        }
+        pub struct TradeEventMessageArgs<'a> {
+            pub trade_event: Option<flatbuffers::WIPOffset<TradeEvent<'a>>>,
+            pub message_type: i32,
+        }
+        impl<'a> Default for TradeEventMessageArgs<'a> {
+            #[inline]
+            fn default() -> Self {
+                TradeEventMessageArgs {
+                    trade_event: None,
+                    message_type: 2,
+                }
+            }
+        }
+
+        pub struct TradeEventMessageBuilder<'a: 'b, 'b> {
+            fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+        }
+        impl<'a: 'b, 'b> TradeEventMessageBuilder<'a, 'b> {
+            #[inline]
+            pub fn add_trade_event(&mut self, trade_event: flatbuffers::WIPOffset<TradeEvent<'b>>) {
+                self.fbb_
+                    .push_slot_always::<flatbuffers::WIPOffset<TradeEvent>>(
+                        TradeEventMessage::VT_TRADE_EVENT,
+                        trade_event,
+                    );
+            }
+            #[inline]
+            pub fn add_message_type(&mut self, message_type: i32) {
+                self.fbb_
+                    .push_slot::<i32>(TradeEventMessage::VT_MESSAGE_TYPE, message_type, 2);
+            }
+            #[inline]
+            pub fn new(
+                _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+            ) -> TradeEventMessageBuilder<'a, 'b> {
+                let start = _fbb.start_table();
+                TradeEventMessageBuilder {
+                    fbb_: _fbb,
+                    start_: start,
+                }
+            }
+            #[inline]
+            pub fn finish(self) -> flatbuffers::WIPOffset<TradeEventMessage<'a>> {
+                let o = self.fbb_.end_table(self.start_);
+                flatbuffers::WIPOffset::new(o.value())
+            }
+        }
+
+        impl core::fmt::Debug for TradeEventMessage<'_> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut ds = f.debug_struct("TradeEventMessage");
+                ds.field("trade_event", &self.trade_event());
+                ds.field("message_type", &self.message_type());
+                ds.finish()
+            }
+        }
+        #[inline]
+        /// Verifies that a buffer of bytes contains a `TradeEventMessage`
+        /// and returns it.
+        /// Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `root_as_trade_event_message_unchecked`.
+        pub fn root_as_trade_event_message(
+            buf: &[u8],
+        ) -> Result<TradeEventMessage, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::root::<TradeEventMessage>(buf)
+        }
+        #[inline]
+        /// Verifies that a buffer of bytes contains a size prefixed
+        /// `TradeEventMessage` and returns it.
+        /// Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `size_prefixed_root_as_trade_event_message_unchecked`.
+        pub fn size_prefixed_root_as_trade_event_message(
+            buf: &[u8],
+        ) -> Result<TradeEventMessage, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::size_prefixed_root::<TradeEventMessage>(buf)
+        }
+        #[inline]
+        /// Verifies, with the given options, that a buffer of bytes
+        /// contains a `TradeEventMessage` and returns it.
+        /// Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `root_as_trade_event_message_unchecked`.
+        pub fn root_as_trade_event_message_with_opts<'b, 'o>(
+            opts: &'o flatbuffers::VerifierOptions,
+            buf: &'b [u8],
+        ) -> Result<TradeEventMessage<'b>, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::root_with_opts::<TradeEventMessage<'b>>(opts, buf)
+        }
+        #[inline]
+        /// Verifies, with the given verifier options, that a buffer of
+        /// bytes contains a size prefixed `TradeEventMessage` and returns
+        /// it. Note that verification is still experimental and may not
+        /// catch every error, or be maximally performant. For the
+        /// previous, unchecked, behavior use
+        /// `root_as_trade_event_message_unchecked`.
+        pub fn size_prefixed_root_as_trade_event_message_with_opts<'b, 'o>(
+            opts: &'o flatbuffers::VerifierOptions,
+            buf: &'b [u8],
+        ) -> Result<TradeEventMessage<'b>, flatbuffers::InvalidFlatbuffer> {
+            flatbuffers::size_prefixed_root_with_opts::<TradeEventMessage<'b>>(opts, buf)
+        }
+        #[inline]
+        /// Assumes, without verification, that a buffer of bytes contains a TradeEventMessage and returns it.
+        /// # Safety
+        /// Callers must trust the given bytes do indeed contain a valid `TradeEventMessage`.
+        pub unsafe fn root_as_trade_event_message_unchecked(buf: &[u8]) -> TradeEventMessage {
+            flatbuffers::root_unchecked::<TradeEventMessage>(buf)
+        }
+        #[inline]
+        /// Assumes, without verification, that a buffer of bytes contains a size prefixed TradeEventMessage and returns it.
+        /// # Safety
+        /// Callers must trust the given bytes do indeed contain a valid size prefixed `TradeEventMessage`.
+        pub unsafe fn size_prefixed_root_as_trade_event_message_unchecked(
+            buf: &[u8],
+        ) -> TradeEventMessage {
+            flatbuffers::size_prefixed_root_unchecked::<TradeEventMessage>(buf)
+        }
+        #[inline]
+        pub fn finish_trade_event_message_buffer<'a>(
+            fbb: &mut flatbuffers::FlatBufferBuilder<'a>,
+            root: flatbuffers::WIPOffset<TradeEventMessage<'a>>,
+        ) {
+            fbb.finish(root, None);
+        }
+
+        #[inline]
+        pub fn finish_size_prefixed_trade_event_message_buffer<'a>(
+            fbb: &mut flatbuffers::FlatBufferBuilder<'a>,
+            root: flatbuffers::WIPOffset<TradeEventMessage<'a>>,
+        ) {
+            fbb.finish_size_prefixed(root, None);
+        }
+    } // pub mod TradeEvents
+} // pub mod Atrimo
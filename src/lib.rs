@@ -1,11 +1,16 @@
 pub mod aggregator;
+pub mod arbitration;
 pub mod binance;
-pub mod coinbase;
 pub mod bybit;
+pub mod coinbase;
+pub mod config;
 pub mod flatbuffer;
+pub mod lifecycle;
 pub mod okx;
 pub mod orderbook;
+pub mod pool;
 pub mod rest;
+pub mod shutdown;
 pub mod spsc;
 pub mod utils;
 pub mod websocket;
@@ -0,0 +1,124 @@
+use crate::utils::Exchange;
+use std::fmt::{self, Display};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+
+/// Transitions kept for subscribers that fall behind before the oldest are dropped
+const TRANSITIONS_CAPACITY: usize = 64;
+
+/// Where a connection is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// not connected, or the connection was lost and nothing is reconnecting it yet
+    Disconnected,
+    /// handshaking with the exchange
+    Connecting,
+    /// the exchange acknowledged a subscription but no data arrived yet
+    Subscribed,
+    /// data is flowing
+    Streaming,
+    /// nothing arrived for half the idle timeout. Becomes `Streaming` again as soon as
+    /// data arrives, `Disconnected` once the idle timeout runs out
+    Stale,
+    /// waiting to reconnect or replaying subscriptions on a fresh connection
+    Reconnecting,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Disconnected => write!(f, "disconnected"),
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Subscribed => write!(f, "subscribed"),
+            ConnectionState::Streaming => write!(f, "streaming"),
+            ConnectionState::Stale => write!(f, "stale"),
+            ConnectionState::Reconnecting => write!(f, "reconnecting"),
+        }
+    }
+}
+
+/// A change of a connection's state
+#[derive(Debug, Clone)]
+pub struct Transition {
+    /// `None` until the client is connected to an exchange
+    pub exchange: Option<Exchange>,
+    pub from: ConnectionState,
+    pub to: ConnectionState,
+    pub at: SystemTime,
+    /// what caused the transition, e.g. the error that closed the connection
+    pub reason: Option<String>,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(exchange) = &self.exchange {
+            write!(f, "{}: ", exchange)?;
+        }
+        write!(f, "{} -> {}", self.from, self.to)?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// State machine of one connection, shared by its client handles and reader
+pub(crate) struct Lifecycle {
+    current: Mutex<(Option<Exchange>, ConnectionState)>,
+    transitions: broadcast::Sender<Transition>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        let (transitions, _) = broadcast::channel(TRANSITIONS_CAPACITY);
+        Self {
+            current: Mutex::new((None, ConnectionState::Disconnected)),
+            transitions,
+        }
+    }
+}
+
+impl Lifecycle {
+    pub(crate) fn state(&self) -> ConnectionState {
+        self.current.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Transition> {
+        self.transitions.subscribe()
+    }
+
+    pub(crate) fn set_exchange(&self, exchange: Exchange) {
+        self.current.lock().unwrap_or_else(|e| e.into_inner()).0 = Some(exchange);
+    }
+
+    /// Moves to `to` and broadcasts the transition. Staying in the same state is a no-op
+    pub(crate) fn transition(&self, to: ConnectionState, reason: Option<String>) {
+        self.transition_if(|_| true, to, reason)
+    }
+
+    /// Moves to `to` only if the current state satisfies `allowed`
+    pub(crate) fn transition_if(
+        &self,
+        allowed: impl Fn(ConnectionState) -> bool,
+        to: ConnectionState,
+        reason: Option<String>,
+    ) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let (exchange, from) = *current;
+        if from == to || !allowed(from) {
+            return;
+        }
+        current.1 = to;
+        let transition = Transition {
+            exchange,
+            from,
+            to,
+            at: SystemTime::now(),
+            reason,
+        };
+        log::info!("{}", transition);
+        // no subscribers is fine
+        let _ = self.transitions.send(transition);
+    }
+}
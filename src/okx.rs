@@ -1,33 +1,123 @@
 use crate::aggregator::Transmitor;
-use crate::flatbuffer::{make_snapshot_event, make_update_event};
+use crate::arbitration::Sequenced;
+use crate::flatbuffer::{make_snapshot_event, make_trade_event, make_update_event};
 use crate::orderbook::l2::Level;
+use crate::rest::RestClient;
 use crate::utils::{
-    CcyPair, Exchange, Result, Symbol, TorrentError, ASSET_CONSTANT_MULTIPLIER, DATA_FEED,
+    from_str, CcyPair, Decimal, Exchange, Result, ScaledDecimal, Symbol, TorrentError, Trade,
+    ASSET_CONSTANT_MULTIPLIER, DATA_FEED, TRADE_FEED,
+};
+use crate::websocket::{
+    Ack, Decode, Limits, MessageCallback, PingRoutine, RateLimit, Request, WebSocketClient, Wss,
 };
-use crate::websocket::{MessageCallback, Wss};
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Display};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Write};
 use std::ops::Deref;
 use std::result;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite;
 use zenoh::prelude::sync::SyncResolve;
 use zenoh::prelude::Encoding;
 
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
-pub enum Message {
-    Subscribe(SubscribeMessage),
+pub enum Message<'a> {
+    #[serde(borrow)]
+    Subscribe(SubscribeMessage<'a>),
     Failure(FailureMessage),
-    Books(BooksMessage),
+    #[serde(borrow)]
+    Books(BooksMessage<'a>),
+    #[serde(borrow)]
+    Trades(TradesMessage<'a>),
+}
+
+impl Decode for Message<'_> {
+    type Frame<'a> = Message<'a>;
+
+    fn into_owned(frame: Message<'_>) -> Self {
+        match frame {
+            Message::Subscribe(m) => Message::Subscribe(SubscribeMessage {
+                event: m.event,
+                arg: m.arg.into_owned(),
+                conn_id: m.conn_id,
+            }),
+            Message::Failure(m) => Message::Failure(m),
+            Message::Books(m) => Message::Books(m.into_owned()),
+            Message::Trades(m) => Message::Trades(TradesMessage {
+                arg: m.arg.into_owned(),
+                data: m.data,
+            }),
+        }
+    }
+
+    fn into_frame<'a>(self) -> Message<'a>
+    where
+        Self: 'a,
+    {
+        self
+    }
+}
+
+impl Sequenced for Message<'_> {
+    fn sequence(&self) -> Option<(String, u64)> {
+        match self {
+            Message::Books(m) => {
+                let seq_id = m.data.iter().map(|d| d.seq_id).max()?;
+                Some((m.arg.stream_id(), seq_id as u64))
+            }
+            Message::Trades(m) => {
+                let trade_id = m
+                    .data
+                    .iter()
+                    .filter_map(|t| t.trade_id.parse().ok())
+                    .max()?;
+                Some((m.arg.stream_id(), trade_id))
+            }
+            Message::Subscribe(_) | Message::Failure(_) => None,
+        }
+    }
+
+    fn is_snapshot(&self) -> bool {
+        matches!(self, Message::Books(m) if m.action == "snapshot")
+    }
+
+    fn stream(channel: &str, topic: &str) -> String {
+        format!("{}:{}", channel, topic)
+    }
 }
 
+/// Price, quantity, deprecated liquidated orders and order count of a level
 #[derive(Debug)]
-pub struct LevelUpdate([f64; 4]);
+pub struct LevelUpdate {
+    values: [f64; 4],
+    /// decimals the price and quantity were sent with, needed for checksums
+    scales: [u8; 2],
+}
+
+impl LevelUpdate {
+    pub fn price(&self) -> ScaledDecimal {
+        ScaledDecimal {
+            value: self.values[0],
+            scale: self.scales[0],
+        }
+    }
+
+    pub fn qty(&self) -> ScaledDecimal {
+        ScaledDecimal {
+            value: self.values[1],
+            scale: self.scales[1],
+        }
+    }
+}
 
 impl Deref for LevelUpdate {
     type Target = [f64; 4];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.values
     }
 }
 
@@ -53,38 +143,48 @@ impl<'de> serde::de::Visitor<'de> for LevelUpdateVisitor {
     where
         A: serde::de::SeqAccess<'de>,
     {
-        let mut arr = [0.0; 4];
+        let mut values = [0.0; 4];
+        let mut scales = [0; 2];
         let mut i = 0;
 
-        while let Some(v) = seq.next_element::<String>()? {
-            // v is a valid string but not parsable to f64
-            let non_f64 = serde::de::Unexpected::Str(v.as_str());
-            let element = v
-                .parse()
-                .map_err(|_| serde::de::Error::invalid_value(non_f64, &self))?;
-            arr[i] = element;
+        while i < 2 {
+            let Some(ScaledDecimal { value, scale }) = seq.next_element()? else {
+                break;
+            };
+            values[i] = value;
+            scales[i] = scale;
+            i += 1;
+        }
+        while let Some(Decimal(v)) = seq.next_element()? {
+            values[i] = v;
             i += 1;
         }
-        Ok(LevelUpdate(arr))
+        Ok(LevelUpdate { values, scales })
     }
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct BooksUpdate {
+pub struct BooksUpdate<'a> {
     pub asks: Vec<LevelUpdate>,
     pub bids: Vec<LevelUpdate>,
-    pub ts: String,
+    #[serde(borrow)]
+    pub ts: Cow<'a, str>,
+    /// not sent by `books5` and `bbo-tbt`
+    #[serde(default)]
     pub checksum: i64,
+    /// not sent by `books5` and `bbo-tbt`
+    #[serde(default)]
     pub prev_seq_id: i64,
     pub seq_id: i64,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct SubscribeMessage {
+pub struct SubscribeMessage<'a> {
     pub event: String,
-    pub arg: Arg,
+    #[serde(borrow)]
+    pub arg: Arg<'a>,
     pub conn_id: String,
 }
 
@@ -97,40 +197,114 @@ pub struct FailureMessage {
     pub conn_id: String,
 }
 
+/// Order book push. Its strings are borrowed from the frame unless they had to be unescaped
 #[derive(Deserialize, Debug)]
-pub struct BooksMessage {
-    pub action: String,
-    pub arg: Arg,
-    pub data: Vec<BooksUpdate>,
+pub struct BooksMessage<'a> {
+    /// `snapshot` or `update`. Empty for `books5` and `bbo-tbt`, whose pushes are all full
+    /// books
+    #[serde(default, borrow)]
+    pub action: Cow<'a, str>,
+    #[serde(borrow)]
+    pub arg: Arg<'a>,
+    #[serde(borrow)]
+    pub data: Vec<BooksUpdate<'a>>,
+}
+
+impl BooksMessage<'_> {
+    /// Copies the strings borrowed from the frame
+    pub fn into_owned(self) -> BooksMessage<'static> {
+        BooksMessage {
+            action: Cow::Owned(self.action.into_owned()),
+            arg: self.arg.into_owned(),
+            data: self
+                .data
+                .into_iter()
+                .map(|d| BooksUpdate {
+                    asks: d.asks,
+                    bids: d.bids,
+                    ts: Cow::Owned(d.ts.into_owned()),
+                    checksum: d.checksum,
+                    prev_seq_id: d.prev_seq_id,
+                    seq_id: d.seq_id,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Public trade. Okx may aggregate several fills of a taker order into one
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesUpdate {
+    pub inst_id: String,
+    pub trade_id: String,
+    #[serde(deserialize_with = "from_str")]
+    pub px: f64,
+    /// in contracts for derivatives
+    #[serde(deserialize_with = "from_str")]
+    pub sz: f64,
+    /// taker side, `buy` or `sell`
+    pub side: String,
+    #[serde(deserialize_with = "from_str")]
+    pub ts: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TradesMessage<'a> {
+    #[serde(borrow)]
+    pub arg: Arg<'a>,
+    pub data: Vec<TradesUpdate>,
 }
 
 #[derive(Serialize, Debug)]
 struct Subscription {
     pub op: String,
-    pub args: Vec<Arg>,
+    pub args: Vec<Arg<'static>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct Arg {
-    channel: String,
-    inst_id: String,
-    #[serde(default = "InstType::str_default")]
-    inst_type: String,
+pub struct Arg<'a> {
+    #[serde(borrow)]
+    channel: Cow<'a, str>,
+    #[serde(borrow)]
+    inst_id: Cow<'a, str>,
+    /// only echoed back by Okx for some channels
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    inst_type: Option<Cow<'a, str>>,
 }
 
-impl Arg {
-    fn new(channel: String, inst_id: String, inst_type: String) -> Self {
-        Self {
-            channel,
-            inst_id,
-            inst_type: inst_type.to_uppercase(),
+impl Arg<'_> {
+    fn new(channel: String, inst_id: String, inst_type: String) -> Arg<'static> {
+        Arg {
+            channel: Cow::Owned(channel),
+            inst_id: Cow::Owned(inst_id),
+            inst_type: Some(Cow::Owned(inst_type.to_uppercase())),
         }
     }
+
+    fn into_owned(self) -> Arg<'static> {
+        Arg {
+            channel: Cow::Owned(self.channel.into_owned()),
+            inst_id: Cow::Owned(self.inst_id.into_owned()),
+            inst_type: self.inst_type.map(|t| Cow::Owned(t.into_owned())),
+        }
+    }
+
+    /// Stream of the arg's pushes
+    fn stream_id(&self) -> String {
+        format!("{}:{}", self.channel, self.inst_id)
+    }
+
+    /// Okx acknowledges every arg of a request separately, echoing op, channel and instId.
+    /// The op tells apart the acks of a resubscription's unsubscribe and subscribe
+    fn ack_id(&self, op: &str) -> String {
+        format!("{}:{}", op, self.stream_id())
+    }
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InstType {
     #[default]
     SPOT,
@@ -141,13 +315,6 @@ pub enum InstType {
     ANY,
 }
 
-impl InstType {
-    fn str_default() -> String {
-        let variant: InstType = Default::default();
-        variant.to_string()
-    }
-}
-
 impl Display for InstType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -161,17 +328,150 @@ impl Display for InstType {
     }
 }
 
+impl FromStr for InstType {
+    type Err = TorrentError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "SPOT" => Ok(InstType::SPOT),
+            "MARGIN" => Ok(InstType::MARGIN),
+            "SWAP" => Ok(InstType::SWAP),
+            "FUTURES" => Ok(InstType::FUTURES),
+            "OPTION" => Ok(InstType::OPTION),
+            "ANY" => Ok(InstType::ANY),
+            _ => Err(TorrentError::BadParse(format!(
+                "unknown Okx instType {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl InstType {
+    /// Guesses the type of an instId: `BTC-USDT`, `BTC-USDT-SWAP`, `BTC-USD-240329` or
+    /// `BTC-USD-240329-60000-C`. Margin instIds look like spot ones and are guessed as spot
+    pub fn from_inst_id(inst_id: &str) -> Self {
+        match inst_id.split('-').collect::<Vec<&str>>().as_slice() {
+            [_, _] => InstType::SPOT,
+            [_, _, "SWAP"] => InstType::SWAP,
+            [_, _, _] => InstType::FUTURES,
+            [_, _, _, _, _] => InstType::OPTION,
+            _ => InstType::ANY,
+        }
+    }
+
+    /// Whether quantities are in base currency units rather than contracts
+    pub fn is_spot(&self) -> bool {
+        matches!(self, InstType::SPOT | InstType::MARGIN)
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct InstrumentsRequest {
+    inst_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inst_family: Option<String>,
+}
+
+/// Envelope of every Okx REST response. `code` is `0` on success
+#[derive(Deserialize, Debug)]
+pub struct RestResponse<T> {
+    pub code: String,
+    pub msg: String,
+    pub data: Vec<T>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RequestError {
+    code: String,
+    msg: String,
+}
+
+impl Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "code: {}, msg: {}", self.code, self.msg)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Instrument {
+    pub inst_id: String,
+    pub inst_type: String,
+    /// contract value, empty for spot and margin
+    #[serde(default, deserialize_with = "from_str")]
+    pub ct_val: f64,
+    /// contract multiplier, empty for spot and margin
+    #[serde(default, deserialize_with = "from_str")]
+    pub ct_mult: f64,
+    /// currency of `ct_val`: the base currency of linear contracts and options, the quote
+    /// currency of inverse ones
+    #[serde(default)]
+    pub ct_val_ccy: String,
+}
+
+impl Instrument {
+    /// Converts `qty` contracts at `price` into base currency units
+    pub fn base_qty(&self, price: f64, qty: f64) -> f64 {
+        let ct_mult = if self.ct_mult == 0.0 {
+            1.0
+        } else {
+            self.ct_mult
+        };
+        let size = qty * self.ct_val * ct_mult;
+        let is_inverse = self.inst_id.split('-').nth(1) == Some(self.ct_val_ccy.as_str());
+        if is_inverse {
+            size / price
+        } else {
+            size
+        }
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Channel {
     TRADES,
+    /// 400 levels, snapshot then updates every 100ms
     BOOKS,
+    /// 5 levels, the full book every 100ms
+    BOOKS5,
+    /// best bid and offer on every change
+    BBO_TBT,
+    /// 400 levels, snapshot then updates on every change. Needs a logged in VIP account
+    BOOKS_L2_TBT,
+    /// 50 levels, snapshot then updates on every change. Needs a logged in VIP account
+    BOOKS50_L2_TBT,
+}
+
+impl Channel {
+    /// Order book channels by name
+    pub fn book(channel: &str) -> Option<Self> {
+        match channel {
+            "books" => Some(Channel::BOOKS),
+            "books5" => Some(Channel::BOOKS5),
+            "bbo-tbt" => Some(Channel::BBO_TBT),
+            "books-l2-tbt" => Some(Channel::BOOKS_L2_TBT),
+            "books50-l2-tbt" => Some(Channel::BOOKS50_L2_TBT),
+            _ => None,
+        }
+    }
+
+    /// Whether every push replaces the whole book, without a checksum to verify
+    pub fn replaces_book(&self) -> bool {
+        matches!(self, Channel::BOOKS5 | Channel::BBO_TBT)
+    }
 }
 
 impl Display for Channel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Channel::BOOKS => write!(f, "books"),
+            Channel::BOOKS5 => write!(f, "books5"),
+            Channel::BBO_TBT => write!(f, "bbo-tbt"),
+            Channel::BOOKS_L2_TBT => write!(f, "books-l2-tbt"),
+            Channel::BOOKS50_L2_TBT => write!(f, "books50-l2-tbt"),
             Channel::TRADES => write!(f, "trades"),
         }
     }
@@ -188,37 +488,190 @@ impl Display for Okx {
 
 impl Okx {
     pub const URL: &'static str = "wss://ws.okx.com:8443/ws/v5/public";
+    pub const REST_URL: &'static str = "https://www.okx.com";
+    pub const INSTRUMENTS: &'static str = "/api/v5/public/instruments";
 
     pub fn new() -> Self {
         Self {}
     }
-}
 
-impl Wss for Okx {
-    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<String> {
+    /// Builds an `op` (`subscribe` or `unsubscribe`) request for `topics` of `channel`
+    fn request(&self, op: &str, channel: String, topics: Vec<String>) -> Result<Request> {
         let args = topics
             .into_iter()
-            .map(|t| Arg::new(channel.clone(), t, "SPOT".to_string()))
-            .collect::<Vec<Arg>>();
+            .map(|t| {
+                let inst_type = InstType::from_inst_id(&t).to_string();
+                Arg::new(channel.clone(), t, inst_type)
+            })
+            .collect::<Vec<Arg<'static>>>();
+        let ack_ids = args.iter().map(|arg| arg.ack_id(op)).collect();
         let sub = Subscription {
-            op: "subscribe".to_string(),
+            op: op.to_string(),
             args,
         };
 
         match serde_json::to_string(&sub) {
-            Ok(s) => Ok(s),
+            Ok(payload) => Ok(Request { payload, ack_ids }),
             Err(e) => Err(TorrentError::BadParse(format!("serde parse error: {}", e))),
         }
     }
 
+    /// Lists the instruments of `inst_type`, whose contract values
+    /// [`Manager::with_instruments`] converts derivative quantities with. Options also need
+    /// their `inst_family`, e.g. `BTC-USD`
+    pub async fn instruments(
+        rest_url: &str,
+        inst_type: InstType,
+        inst_family: Option<&str>,
+    ) -> Result<Vec<Instrument>> {
+        let rest_client = RestClient::new(rest_url);
+        let request = InstrumentsRequest {
+            inst_type: inst_type.to_string(),
+            inst_family: inst_family.map(String::from),
+        };
+        let response = rest_client
+            .get::<RestResponse<Instrument>, RequestError, _>(Self::INSTRUMENTS, Some(request))
+            .await?;
+        if response.code != "0" {
+            return Err(TorrentError::BadRequest(format!(
+                "code: {}, msg: {}",
+                response.code, response.msg
+            )));
+        }
+        Ok(response.data)
+    }
+
+    /// Okx confirms each arg with a `subscribe`/`unsubscribe` event and rejects a request
+    /// with an `error` event that doesn't say which arg failed
+    pub fn parse_ack(msg: &str) -> Option<Ack> {
+        if !msg.contains("\"event\"") {
+            return None;
+        }
+        match serde_json::from_str::<Message>(msg).ok()? {
+            Message::Subscribe(m) => Some(Ack::Success(Some(m.arg.ack_id(&m.event)))),
+            Message::Failure(m) => Some(Ack::Failure(None, format!("{}: {}", m.code, m.msg))),
+            Message::Books(_) | Message::Trades(_) => None,
+        }
+    }
+}
+
+impl Wss for Okx {
+    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("subscribe", channel, topics)
+    }
+
+    fn unsubscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request> {
+        self.request("unsubscribe", channel, topics)
+    }
+
     fn to_enum(&self) -> Exchange {
         Exchange::OKX
     }
+
+    fn ack_fn(&self) -> fn(&str) -> Option<Ack> {
+        Self::parse_ack
+    }
+
+    /// Okx caps a request at 64KB and a connection at 480 subscribe requests per hour, so
+    /// args are batched and a connection is kept to a manageable number of books
+    fn limits(&self) -> Limits {
+        Limits {
+            topics_per_connection: 200,
+            topics_per_request: 100,
+            // 3 subscribe/unsubscribe/login requests per second per connection
+            messages: Some(RateLimit::new(3, Duration::from_secs(1))),
+        }
+    }
+
+    /// Okx drops connections that stay silent for 30s. It answers a plain-text `ping`
+    /// with a plain-text `pong`
+    fn ping_routine(&self) -> Option<PingRoutine> {
+        Some(PingRoutine::new(
+            Duration::from_secs(25),
+            || tungstenite::Message::text("ping"),
+            |msg| msg == "pong",
+        ))
+    }
+}
+
+/// Local copy of a book, kept to verify the sequence ids and checksum of every push
+#[derive(Debug, Default)]
+struct Book {
+    /// price and quantity as sent, keyed by the price in asset units
+    bids: BTreeMap<u64, (ScaledDecimal, ScaledDecimal)>,
+    asks: BTreeMap<u64, (ScaledDecimal, ScaledDecimal)>,
+    seq_id: i64,
+}
+
+impl Book {
+    /// Levels a side the checksum is computed over
+    const CHECKSUM_DEPTH: usize = 25;
+
+    /// Applies a snapshot or update, failing with `OutOfSync` if a push was missed or the
+    /// resulting book doesn't match Okx's
+    fn apply(&mut self, update: &BooksUpdate<'_>, is_snapshot: bool) -> Result<()> {
+        if !is_snapshot && update.prev_seq_id != self.seq_id {
+            return Err(TorrentError::OutOfSync(format!(
+                "expected prevSeqId {} but got {}",
+                self.seq_id, update.prev_seq_id
+            )));
+        }
+        self.seq_id = update.seq_id;
+        for (side, levels) in [
+            (&mut self.bids, &update.bids),
+            (&mut self.asks, &update.asks),
+        ] {
+            for level in levels {
+                let price = (level.price().value * ASSET_CONSTANT_MULTIPLIER) as u64;
+                if level.qty().value == 0.0 {
+                    side.remove(&price);
+                } else {
+                    side.insert(price, (level.price(), level.qty()));
+                }
+            }
+        }
+
+        let checksum = self.checksum();
+        if checksum as i64 != update.checksum {
+            return Err(TorrentError::OutOfSync(format!(
+                "expected checksum {} but got {}",
+                checksum, update.checksum
+            )));
+        }
+        Ok(())
+    }
+
+    /// Signed CRC32 of `bid price:bid qty:ask price:ask qty:...` over the best levels,
+    /// with prices and quantities written exactly as Okx sent them
+    fn checksum(&self) -> i32 {
+        let mut bids = self.bids.values().rev().take(Self::CHECKSUM_DEPTH);
+        let mut asks = self.asks.values().take(Self::CHECKSUM_DEPTH);
+        let mut text = String::new();
+        loop {
+            let (bid, ask) = (bids.next(), asks.next());
+            if bid.is_none() && ask.is_none() {
+                break;
+            }
+            for (price, qty) in bid.into_iter().chain(ask) {
+                if !text.is_empty() {
+                    text.push(':');
+                }
+                let _ = write!(text, "{}:{}", price, qty);
+            }
+        }
+        crc32fast::hash(text.as_bytes()) as i32
+    }
 }
 
-#[derive(Debug)]
 pub struct Manager {
     zenoh: zenoh::Session,
+    /// local copies of the subscribed books by channel and symbol. Updates of books without
+    /// one, unsubscribed or out of sync, are dropped until their next snapshot
+    books: HashMap<(String, Symbol), Book>,
+    /// resubscribes out of sync books to get fresh snapshots
+    client: Option<WebSocketClient>,
+    /// derivatives by instId, whose quantities are converted from contracts to base units
+    instruments: HashMap<Symbol, Instrument>,
 }
 
 impl Default for Manager {
@@ -227,7 +680,12 @@ impl Default for Manager {
         let session = zenoh::open(config)
             .res()
             .unwrap_or_else(|e| panic!("Couldn't open zenoh session: {e}"));
-        Self { zenoh: session }
+        Self {
+            zenoh: session,
+            books: HashMap::default(),
+            client: None,
+            instruments: HashMap::default(),
+        }
     }
 }
 
@@ -235,50 +693,189 @@ impl Manager {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Resubscribes through `client` to resync books that went out of sync. Without it,
+    /// they stay out of sync until the next reconnect
+    pub fn with_client(mut self, client: WebSocketClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Converts the quantities of derivative books from contracts into base currency units,
+    /// so that they aggregate with spot books. See [`Okx::instruments`]
+    pub fn with_instruments(mut self, instruments: Vec<Instrument>) -> Self {
+        self.instruments
+            .extend(instruments.into_iter().map(|i| (i.inst_id.clone(), i)));
+        self
+    }
+
+    /// Type of `inst_id` as listed by its instrument, guessed from the id when it wasn't given
+    /// to [`Manager::with_instruments`]
+    pub fn inst_type(&self, inst_id: &str) -> InstType {
+        self.instruments
+            .get(inst_id)
+            .and_then(|i| i.inst_type.parse().ok())
+            .unwrap_or_else(|| InstType::from_inst_id(inst_id))
+    }
+
+    /// Closes the zenoh session once the puts already made have been flushed
+    pub fn close(self) -> Result<()> {
+        self.zenoh
+            .close()
+            .res()
+            .map_err(|e| TorrentError::BadConnection(format!("failed to close zenoh: {}", e)))
+    }
+
+    /// Quantities of derivative books are in contracts. Fails for derivatives whose
+    /// instrument wasn't given to [`Manager::with_instruments`]
+    fn to_base_units(&self, symbol: &Symbol, levels: &mut [LevelUpdate]) -> Result<()> {
+        if levels.is_empty() || self.inst_type(symbol).is_spot() {
+            return Ok(());
+        }
+        let instrument = self.instruments.get(symbol).ok_or_else(|| {
+            TorrentError::BadParse(format!("no contract value for Okx {}", symbol))
+        })?;
+        for level in levels {
+            level.values[1] = instrument.base_qty(level.values[0], level.values[1]);
+        }
+        Ok(())
+    }
+
+    fn transmit_trade(&self, trade: TradesUpdate) -> Result<()> {
+        let ccy_pair = self.resolve_symbol(&trade.inst_id).ok_or_else(|| {
+            TorrentError::BadParse(format!("{} is not supported for Okx", trade.inst_id))
+        })?;
+        let qty = match self.instruments.get(&trade.inst_id) {
+            _ if self.inst_type(&trade.inst_id).is_spot() => trade.sz,
+            Some(instrument) => instrument.base_qty(trade.px, trade.sz),
+            None => {
+                return Err(TorrentError::BadParse(format!(
+                    "no contract value for Okx {}",
+                    trade.inst_id
+                )))
+            }
+        };
+        let trade = Trade {
+            id: trade.trade_id,
+            price: (trade.px * ASSET_CONSTANT_MULTIPLIER) as u64,
+            qty: (qty * ASSET_CONSTANT_MULTIPLIER) as u64,
+            is_buy: trade.side == "buy",
+            timestamp: trade.ts * 1000,
+        };
+
+        let event = make_trade_event(trade, ccy_pair, Exchange::OKX)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        let encoding = Encoding::APP_CUSTOM
+            .with_suffix("trade_event")
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        let tradefeed = zenoh::key_expr::keyexpr::new(TRADE_FEED)
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))?;
+        self.zenoh
+            .put(tradefeed, event.buff)
+            .encoding(encoding)
+            .res()
+            .map_err(|e| TorrentError::BadZenoh(e.to_string()))
+    }
+
+    /// Drops the book of `symbol`, asks `channel` for a fresh snapshot and clears Okx's
+    /// levels for it in the aggregator
+    fn resync(&mut self, channel: String, symbol: Symbol, reason: TorrentError) -> Result<()> {
+        self.books.remove(&(channel.clone(), symbol.clone()));
+        if let Some(client) = &self.client {
+            client.resubscribe(channel, vec![symbol.clone()])?;
+        }
+        self.transmit(symbol.clone(), vec![], vec![], true)?;
+        Err(TorrentError::OutOfSync(format!(
+            "Okx {}: {}",
+            symbol, reason
+        )))
+    }
 }
 
-impl MessageCallback<Message> for Manager {
-    fn message_callback(&mut self, msg: Result<Message>) -> Result<()> {
+impl MessageCallback<Message<'_>> for Manager {
+    fn message_callback(&mut self, msg: Result<Message<'_>>) -> Result<()> {
         match msg? {
             Message::Failure(m) => {
+                // the rejected request's acknowledgement already carries the error
                 if m.msg.contains("Invalid request") {
                     return Err(TorrentError::BadRequest(format!("{:?}", m)));
-                } else if m.msg.contains("Connection refused") {
-                    // TODO: Sleep then reconnect
-                    panic!("{:?}", m);
                 }
-                // TODO: handle it better
-                panic!("{:?}", m);
+                return Err(TorrentError::BadSubscription(format!(
+                    "{}: {}",
+                    m.code, m.msg
+                )));
             }
             Message::Books(update) => {
-                let mut is_snapshot = false;
-                if update.action == "snapshot" {
-                    is_snapshot = true;
+                let Some(channel) = Channel::book(&update.arg.channel) else {
+                    return Err(TorrentError::BadParse(format!(
+                        "unknown Okx book channel: {}",
+                        update.arg.channel
+                    )));
+                };
+                let symbol = update.arg.inst_id.into_owned();
+                if channel.replaces_book() {
+                    for data in update.data {
+                        self.transmit(symbol.clone(), data.bids, data.asks, true)?;
+                    }
+                    return Ok(());
                 }
-                let symbol = update.arg.inst_id;
+
+                let is_snapshot = update.action == "snapshot";
+                let key = (update.arg.channel.into_owned(), symbol.clone());
                 for data in update.data {
+                    if is_snapshot {
+                        self.books.insert(key.clone(), Book::default());
+                    }
+                    let Some(book) = self.books.get_mut(&key) else {
+                        return Ok(());
+                    };
+                    if let Err(e) = book.apply(&data, is_snapshot) {
+                        return self.resync(key.0, symbol, e);
+                    }
                     // cloning is ok since `update.data.len() == 1` 99% of the time
-                    let _ = self.transmit(symbol.clone(), data.bids, data.asks, is_snapshot);
+                    self.transmit(symbol.clone(), data.bids, data.asks, is_snapshot)?;
+                }
+            }
+            Message::Trades(update) => {
+                for trade in update.data {
+                    self.transmit_trade(trade)?;
                 }
             }
             _ => {}
         }
         Ok(())
     }
+
+    fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        if Channel::book(channel).is_none() {
+            return Ok(());
+        }
+        for symbol in topics {
+            self.books.remove(&(channel.to_string(), symbol.clone()));
+            // an empty snapshot clears Okx's levels for `symbol` in the aggregator
+            self.transmit(symbol.clone(), vec![], vec![], true)?;
+        }
+        Ok(())
+    }
 }
 
 impl Transmitor<Vec<LevelUpdate>> for Manager {
+    /// Spot books are `spot`, perpetual swaps `swap`, futures `futures-<expiry>` and options
+    /// `option-<expiry>-<strike>-<C|P>`
     fn resolve_symbol(&self, symbol: &Symbol) -> Option<CcyPair> {
         let parts = symbol.split('-').collect::<Vec<&str>>();
-        if parts.len() == 2 {
-            Some(CcyPair {
-                base: parts[0].to_string(),
-                quote: parts[1].to_string(),
-                product: "spot".to_string(),
-            })
-        } else {
-            None
-        }
+        let product = match self.inst_type(symbol) {
+            InstType::SPOT | InstType::MARGIN => "spot".to_string(),
+            InstType::SWAP => "swap".to_string(),
+            InstType::FUTURES => format!("futures-{}", parts[2]),
+            InstType::OPTION => format!("option-{}", parts[2..].join("-")),
+            InstType::ANY => return None,
+        };
+        Some(CcyPair {
+            base: parts[0].to_string(),
+            quote: parts[1].to_string(),
+            product,
+        })
     }
 
     fn standardise_updates(&self, updates: Vec<LevelUpdate>) -> Vec<Level> {
@@ -296,15 +893,17 @@ impl Transmitor<Vec<LevelUpdate>> for Manager {
     fn transmit(
         &self,
         symbol: Symbol,
-        bids: Vec<LevelUpdate>,
-        asks: Vec<LevelUpdate>,
+        mut bids: Vec<LevelUpdate>,
+        mut asks: Vec<LevelUpdate>,
         is_snapshot: bool,
     ) -> Result<()> {
+        self.to_base_units(&symbol, &mut bids)?;
+        self.to_base_units(&symbol, &mut asks)?;
         let bids = self.standardise_updates(bids);
         let asks = self.standardise_updates(asks);
-        let ccy_pair = self
-            .resolve_symbol(&symbol)
-            .unwrap_or_else(|| panic!("{symbol} is not supported for Okx"));
+        let ccy_pair = self.resolve_symbol(&symbol).ok_or_else(|| {
+            TorrentError::BadParse(format!("{} is not supported for Okx", symbol))
+        })?;
 
         let (event, encoding) = if is_snapshot {
             let event = make_snapshot_event(bids, asks, ccy_pair, Exchange::OKX)
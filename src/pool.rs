@@ -0,0 +1,218 @@
+use crate::utils::{Exchange, Result, TorrentError};
+use crate::websocket::{
+    Acknowledgement, AsyncMessageCallback, Decode, Event, MessageCallback, WebSocketClient,
+};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Messages queued from the connections for the listener. Once full, their reader loops
+/// wait for the listener to catch up
+pub(crate) const FORWARD_QUEUE: usize = 1024;
+
+/// Forwards a connection's messages to the pool's pipeline
+pub(crate) struct Forwarder<T>(pub(crate) mpsc::Sender<Event<T>>);
+
+impl<T> Forwarder<T> {
+    pub(crate) async fn forward(&self, msg: Result<T>) -> Result<()> {
+        self.0
+            .send(Event::Message(msg))
+            .await
+            .map_err(|_| TorrentError::BadConnection("pool listener stopped".to_string()))
+    }
+}
+
+impl<T: Decode> AsyncMessageCallback<T> for Forwarder<T> {
+    async fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
+        self.forward(msg).await
+    }
+
+    async fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.0
+            .send(Event::Unsubscribed(channel.to_string(), topics.to_vec()))
+            .await
+            .map_err(|_| TorrentError::BadConnection("pool listener stopped".to_string()))
+    }
+}
+
+struct Shard {
+    client: WebSocketClient,
+    listener: JoinHandle<()>,
+}
+
+impl Shard {
+    fn topics(&self) -> usize {
+        self.client.subscriptions().values().map(Vec::len).sum()
+    }
+
+    fn is_subscribed(&self, channel: &str, topic: &String) -> bool {
+        self.client
+            .subscriptions()
+            .get(channel)
+            .is_some_and(|topics| topics.contains(topic))
+    }
+}
+
+struct Inner<T> {
+    exchange: Exchange,
+    /// settings (endpoints, timeouts) every new connection is created with
+    template: WebSocketClient,
+    shards: tokio::sync::Mutex<Vec<Shard>>,
+    events_tx: mpsc::Sender<Event<T>>,
+    events_rx: Mutex<Option<mpsc::Receiver<Event<T>>>>,
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        for shard in self.shards.get_mut().iter() {
+            shard.listener.abort();
+        }
+    }
+}
+
+/// Spreads subscriptions to one exchange over as many connections as its per-connection
+/// limits require. Every connection reconnects on its own while their messages are merged
+/// into a single [`MessageCallback`]. Clones share the same connections.
+///
+/// Depth streams synced with REST snapshots aren't pooled: subscribe to them with
+/// [`WebSocketClient::depth_subscribe`], whose connection carries as many Binance streams as
+/// its market allows
+pub struct WebSocketPool<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for WebSocketPool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> WebSocketPool<T>
+where
+    T: Decode + Send + 'static,
+{
+    pub fn new(exchange: Exchange) -> Self {
+        Self::with_client(exchange, WebSocketClient::new())
+    }
+
+    /// Pool whose connections take their settings from `client`
+    pub fn with_client(exchange: Exchange, client: WebSocketClient) -> Self {
+        let (events_tx, events_rx) = mpsc::channel(FORWARD_QUEUE);
+        Self {
+            inner: Arc::new(Inner {
+                exchange,
+                template: client,
+                shards: tokio::sync::Mutex::new(Vec::new()),
+                events_tx,
+                events_rx: Mutex::new(Some(events_rx)),
+            }),
+        }
+    }
+
+    pub async fn connections(&self) -> usize {
+        self.inner.shards.lock().await.len()
+    }
+
+    /// Subscribes to `topics` of `channel`, filling the spare capacity of open connections
+    /// before opening new ones. Topics the pool is already subscribed to are skipped
+    pub async fn subscribe(&self, channel: String, topics: Vec<String>) -> Result<Acknowledgement> {
+        let mut shards = self.inner.shards.lock().await;
+        let mut seen = HashSet::new();
+        let mut topics = topics
+            .into_iter()
+            .filter(|t| seen.insert(t.clone()))
+            .filter(|t| !shards.iter().any(|s| s.is_subscribed(&channel, t)))
+            .collect::<Vec<String>>();
+
+        let mut acks = Vec::new();
+        let mut idx = 0;
+        while !topics.is_empty() {
+            if idx == shards.len() {
+                shards.push(self.open_shard().await?);
+            }
+            let shard = &shards[idx];
+            idx += 1;
+
+            let capacity = match shard.client.limits() {
+                Some(limits) => limits.topics_per_connection.saturating_sub(shard.topics()),
+                None => continue,
+            };
+            if capacity == 0 {
+                continue;
+            }
+            let rest = topics.split_off(capacity.min(topics.len()));
+            let batch = std::mem::replace(&mut topics, rest);
+            acks.push(shard.client.subscribe(channel.clone(), batch).await?);
+        }
+        Ok(Acknowledgement::join(acks))
+    }
+
+    /// Unsubscribes from `topics` of `channel` on whichever connections carry them
+    pub async fn unsubscribe(
+        &self,
+        channel: String,
+        topics: Vec<String>,
+    ) -> Result<Acknowledgement> {
+        let shards = self.inner.shards.lock().await;
+        let mut acks = Vec::new();
+        for shard in shards.iter() {
+            let batch = topics
+                .iter()
+                .filter(|t| shard.is_subscribed(&channel, t))
+                .cloned()
+                .collect::<Vec<String>>();
+            if !batch.is_empty() {
+                acks.push(shard.client.unsubscribe(channel.clone(), batch).await?);
+            }
+        }
+        Ok(Acknowledgement::join(acks))
+    }
+
+    /// Feeds the messages of every connection, current and future, to `callback_manager`.
+    /// Can only be called once per pool
+    pub async fn listen_with<M>(&self, callback_manager: M) -> Result<()>
+    where
+        M: MessageCallback<T>,
+    {
+        let events_rx = self
+            .inner
+            .events_rx
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let Some(mut events_rx) = events_rx else {
+            return Err(TorrentError::BadRequest(
+                "pool is already being listened to".to_string(),
+            ));
+        };
+
+        let mut manager = callback_manager;
+        while let Some(event) = events_rx.recv().await {
+            let result = match event {
+                Event::Message(msg) => manager.message_callback(msg.map(T::into_frame)),
+                Event::Subscribed(..) => Ok(()),
+                Event::Unsubscribed(channel, topics) => manager.on_unsubscribe(&channel, &topics),
+            };
+            if let Err(e) = result {
+                log::warn!("{}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Connects a new connection and starts forwarding its messages to the pipeline
+    async fn open_shard(&self) -> Result<Shard> {
+        let client = self.inner.template.detached();
+        let reader = client.connect(self.inner.exchange).await?;
+        let forwarder = Forwarder(self.inner.events_tx.clone());
+        let listener = tokio::spawn(
+            client
+                .clone()
+                .listen_with_reconnect_async(reader, forwarder),
+        );
+        Ok(Shard { client, listener })
+    }
+}
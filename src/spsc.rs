@@ -3,7 +3,7 @@ use std::sync::Arc;
 use std::vec::Vec;
 
 use crossbeam_utils::CachePadded;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
 
 #[derive(Debug, PartialEq)]
 pub enum QueueError {
@@ -183,6 +183,17 @@ impl<T> Consumer<T> {
         self.head = head;
         Ok(value)
     }
+
+    /// Returns true once the producer has been dropped. Elements it pushed before can still
+    /// be popped, so a consumer draining the queue stops at the first empty `pop` after this
+    pub fn is_closed(&self) -> bool {
+        if Arc::strong_count(&self.inner) > 1 {
+            return false;
+        }
+        // synchronise with the producer's drop so that its last pushes are visible
+        fence(Ordering::Acquire);
+        true
+    }
 }
 
 pub struct SPSCQueue;
@@ -5,12 +5,14 @@ use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const DATA_FEED: &str = "atrimo/datafeeds";
+pub const TRADE_FEED: &str = "atrimo/trades";
+pub const TICKER_FEED: &str = "atrimo/tickers";
 pub const ASSET_CONSTANT_MULTIPLIER: f64 = 1e10;
 pub type Result<T> = result::Result<T, TorrentError>;
 pub type Symbol = String;
 
 #[allow(non_camel_case_types)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Exchange {
     OKX,
     COINBASE,
@@ -38,6 +40,16 @@ pub enum TorrentError {
     BadParse(String),
     Unknown(String),
     BadZenoh(String),
+    BadSubscription(String),
+    Timeout(String),
+    /// the connection was closed, with the close code and reason
+    Closed(u16, String),
+    /// the underlying socket (io, tls) failed
+    Transport(String),
+    /// the peer violated the websocket protocol
+    Protocol(String),
+    /// a book missed updates (sequence gap, checksum mismatch) and waits for a resync
+    OutOfSync(String),
 }
 
 impl Display for TorrentError {
@@ -49,10 +61,31 @@ impl Display for TorrentError {
             TorrentError::BadParse(v) => write!(f, "prasing error: {}", v),
             TorrentError::Unknown(v) => write!(f, "unknown error: {}", v),
             TorrentError::BadZenoh(v) => write!(f, "zenoh error: {}", v),
+            TorrentError::BadSubscription(v) => write!(f, "subscription rejected: {}", v),
+            TorrentError::Timeout(v) => write!(f, "timed out: {}", v),
+            TorrentError::Closed(code, reason) => {
+                write!(f, "connection closed with code {}: {}", code, reason)
+            }
+            TorrentError::Transport(v) => write!(f, "transport error: {}", v),
+            TorrentError::Protocol(v) => write!(f, "websocket protocol error: {}", v),
+            TorrentError::OutOfSync(v) => write!(f, "out of sync: {}", v),
         }
     }
 }
 
+impl TorrentError {
+    /// Whether the error ended the connection, which is then replaced by a new one
+    pub fn is_disconnect(&self) -> bool {
+        matches!(
+            self,
+            TorrentError::Closed(..)
+                | TorrentError::Timeout(_)
+                | TorrentError::Transport(_)
+                | TorrentError::Protocol(_)
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct CcyPair {
     pub base: String,
@@ -66,6 +99,44 @@ impl Display for CcyPair {
     }
 }
 
+/// Public trade, normalised across exchanges
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub id: String,
+    /// scaled by `ASSET_CONSTANT_MULTIPLIER`, like book levels
+    pub price: u64,
+    /// in base currency units, scaled by `ASSET_CONSTANT_MULTIPLIER`
+    pub qty: u64,
+    /// whether the taker bought
+    pub is_buy: bool,
+    /// when the exchange matched the trade, in microseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// Market summary of an instrument, normalised across exchanges
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ticker {
+    /// scaled by `ASSET_CONSTANT_MULTIPLIER`, like book levels
+    pub last_price: u64,
+    /// base currency traded over the last 24 hours, scaled by `ASSET_CONSTANT_MULTIPLIER`
+    pub volume_24h: u64,
+    /// derivatives only, scaled by `ASSET_CONSTANT_MULTIPLIER`
+    pub mark_price: Option<u64>,
+    /// perpetuals only
+    pub funding_rate: Option<f64>,
+    /// when the exchange produced the ticker, in microseconds since the epoch
+    pub timestamp: u64,
+}
+
+/// Where a book event sits in its exchange's stream. Zero when the exchange doesn't say
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Origin {
+    /// the exchange's sequence number of the book
+    pub sequence: u64,
+    /// when the exchange produced the data, in microseconds since the epoch
+    pub timestamp: u64,
+}
+
 #[derive(Debug)]
 pub struct FlatbufferEvent {
     pub stream_id: u8,
@@ -103,6 +174,84 @@ where
     Ok(S::from_str(&s).unwrap_or_default())
 }
 
+/// Price or quantity sent as a decimal string. Parsed in place, without allocating a
+/// `String` per value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decimal(pub f64);
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(DecimalVisitor)
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> serde::de::Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse()
+            .map(Decimal)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+    }
+}
+
+/// Decimal string parsed in place that remembers how many decimals it was written with,
+/// so that it displays exactly as it was sent, e.g. `0.50`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaledDecimal {
+    pub value: f64,
+    pub scale: u8,
+}
+
+impl Display for ScaledDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.*}", self.scale as usize, self.value)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScaledDecimal {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(ScaledDecimalVisitor)
+    }
+}
+
+struct ScaledDecimalVisitor;
+
+impl<'de> serde::de::Visitor<'de> for ScaledDecimalVisitor {
+    type Value = ScaledDecimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let Decimal(value) = DecimalVisitor.visit_str(v)?;
+        let scale = v.split_once('.').map_or(0, |(_, decimals)| decimals.len());
+        Ok(ScaledDecimal {
+            value,
+            scale: scale as u8,
+        })
+    }
+}
+
 #[macro_export]
 macro_rules! dbg {
     ($fmt:expr $(, $($arg:tt)*)?) => {
@@ -1,76 +1,771 @@
-use crate::binance::{Binance, Channel, Spot, API};
+use crate::binance::{Binance, Channel, Market};
 use crate::bybit::Bybit;
 use crate::coinbase::Coinbase;
+use crate::config::{CoinbaseCredentials, Endpoints};
+use crate::lifecycle::{ConnectionState, Lifecycle, Transition};
 use crate::okx::Okx;
 use crate::rest::RestClient;
 use crate::utils::{Exchange, Result, Symbol, TorrentError};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
 use futures_util::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
 };
-use serde::{de::DeserializeOwned, Serialize};
-use std::collections::HashMap;
-use std::env;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Display};
+use std::future::{Future, IntoFuture};
+use std::io::Read;
 use std::marker;
-use tokio::{net::TcpStream, sync::mpsc};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::{
+    net::TcpStream,
+    sync::{broadcast, mpsc, oneshot},
+    task::JoinHandle,
+    time::Instant,
+};
 use tokio_tungstenite::{
     connect_async,
-    tungstenite::{self},
+    tungstenite::{
+        self,
+        protocol::{frame::coding::CloseCode, CloseFrame},
+    },
     MaybeTlsStream, WebSocketStream,
 };
 
 type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
-type SocketReader = SplitStream<Socket>;
+type SocketStream = SplitStream<Socket>;
 type SocketWriter = SplitSink<Socket, tungstenite::Message>;
 
-pub trait MessageCallback<T> {
-    fn message_callback(&mut self, msg: Result<T>) -> Result<()>;
+/// Read half of an exchange connection. Carries the idle timeout after which the connection
+/// is declared dead
+pub struct SocketReader {
+    stream: SocketStream,
+    commands_rx: mpsc::UnboundedReceiver<Command>,
+    pending: Arc<Mutex<PendingAcks>>,
+    idle_timeout: Duration,
+    pong_fn: fn(&str) -> bool,
+    ack_fn: fn(&str) -> Option<Ack>,
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl From<tungstenite::Error> for TorrentError {
+    fn from(e: tungstenite::Error) -> Self {
+        use tungstenite::error::ProtocolError;
+        match e {
+            // 1006: the connection was dropped without a close frame
+            tungstenite::Error::ConnectionClosed
+            | tungstenite::Error::AlreadyClosed
+            | tungstenite::Error::Protocol(ProtocolError::ResetWithoutClosingHandshake) => {
+                TorrentError::Closed(1006, e.to_string())
+            }
+            tungstenite::Error::Io(_) | tungstenite::Error::Tls(_) => {
+                TorrentError::Transport(e.to_string())
+            }
+            e => TorrentError::Protocol(e.to_string()),
+        }
+    }
+}
+
+/// Decodes a binary frame into text. Exchanges compress their payloads with gzip, zlib or
+/// raw deflate; uncompressed frames are taken as utf-8 as is
+pub fn decode_binary(bytes: &[u8]) -> Result<String> {
+    let mut text = String::new();
+    let decoded = match bytes {
+        [0x1f, 0x8b, ..] => GzDecoder::new(bytes).read_to_string(&mut text),
+        // zlib header: deflate method with a valid check value
+        [cmf, flg, ..] if cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0 => {
+            ZlibDecoder::new(bytes).read_to_string(&mut text)
+        }
+        _ => match std::str::from_utf8(bytes) {
+            Ok(s) => return Ok(s.to_string()),
+            Err(_) => DeflateDecoder::new(bytes).read_to_string(&mut text),
+        },
+    };
+    match decoded {
+        Ok(_) => Ok(text),
+        Err(e) => Err(TorrentError::BadParse(format!(
+            "Unable to decode binary frame: {}",
+            e
+        ))),
+    }
+}
+
+/// Locks `mutex`, ignoring poisoning: the guarded state stays consistent even if a holder
+/// panicked
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Message decoded from a websocket frame. Its [`Decode::Frame`] may borrow strings from
+/// the frame, which lives until the callback handling it returns, instead of allocating them
+pub trait Decode: Sized {
+    type Frame<'a>: Deserialize<'a> + Send;
+    /// Copies whatever `frame` borrows, e.g. to hand it over to another task
+    fn into_owned(frame: Self::Frame<'_>) -> Self;
+    /// The message as a frame, e.g. to hand a message received from another task to a
+    /// [`MessageCallback`]
+    fn into_frame<'a>(self) -> Self::Frame<'a>
+    where
+        Self: 'a;
+}
+
+/// Generic JSON, owning its strings
+impl Decode for serde_json::Value {
+    type Frame<'a> = Self;
+
+    fn into_owned(frame: Self) -> Self {
+        frame
+    }
+
+    fn into_frame<'a>(self) -> Self
+    where
+        Self: 'a,
+    {
+        self
+    }
+}
+
+pub trait MessageCallback<T: Decode> {
+    fn message_callback(&mut self, msg: Result<T::Frame<'_>>) -> Result<()>;
+    /// Called once `topics` of `channel` have been unsubscribed from, so that any state
+    /// derived from them (e.g. the exchange's levels in the aggregator) can be purged
+    fn on_unsubscribe(&mut self, _channel: &str, _topics: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// [`MessageCallback`] whose calls are awaited by the reader loop, so a manager can await
+/// publishing or hand work off instead of blocking the reader. The loop doesn't read the
+/// next frame until the previous call resolves, which is how backpressure reaches the socket
+pub trait AsyncMessageCallback<T: Decode> {
+    fn message_callback(&mut self, msg: Result<T>) -> impl Future<Output = Result<()>>;
+    /// Handles a message still borrowing from its frame if that needs no awaiting, in which
+    /// case `None` is returned. Otherwise hands back a copy for
+    /// [`AsyncMessageCallback::message_callback`]
+    fn frame_callback(&mut self, msg: Result<T::Frame<'_>>) -> Option<Result<T>> {
+        Some(msg.map(T::into_owned))
+    }
+    fn on_unsubscribe(
+        &mut self,
+        _channel: &str,
+        _topics: &[String],
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+    /// Called once `topics` of `channel` have been subscribed to through
+    /// [`WebSocketClient::subscribe`]
+    fn on_subscribe(
+        &mut self,
+        _channel: &str,
+        _topics: &[String],
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+}
+
+/// Runs a [`MessageCallback`] inline in the reader loop
+pub(crate) struct Inline<M>(pub(crate) M);
+
+impl<T: Decode, M: MessageCallback<T>> AsyncMessageCallback<T> for Inline<M> {
+    async fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
+        self.0.message_callback(msg.map(T::into_frame))
+    }
+
+    fn frame_callback(&mut self, msg: Result<T::Frame<'_>>) -> Option<Result<T>> {
+        let _ = self.0.message_callback(msg);
+        None
+    }
+
+    async fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.0.on_unsubscribe(channel, topics)
+    }
+}
+
+/// What a reader loop hands over to a callback running elsewhere
+pub(crate) enum Event<T> {
+    Message(Result<T>),
+    Subscribed(String, Vec<String>),
+    Unsubscribed(String, Vec<String>),
+}
+
+/// Runs a blocking [`MessageCallback`] (e.g. a `Manager` publishing on zenoh) on a thread of
+/// its own. Messages reach it through a bounded channel: once `capacity` messages are
+/// queued, the reader loop waits for the manager to catch up
+pub struct Pipeline<T> {
+    tx: mpsc::Sender<Event<T>>,
+}
+
+impl<T> Clone for Pipeline<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
 }
 
-pub trait Wss: Display {
-    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<String>;
+impl<T: Decode + Send + 'static> Pipeline<T> {
+    /// Starts the worker running `callback_manager`. It stops, handing the manager back,
+    /// once every clone of the pipeline is dropped
+    pub fn spawn<M>(callback_manager: M, capacity: usize) -> (Self, JoinHandle<M>)
+    where
+        M: MessageCallback<T> + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel(capacity);
+        let worker = tokio::task::spawn_blocking(move || {
+            let mut manager = callback_manager;
+            while let Some(event) = rx.blocking_recv() {
+                let result = match event {
+                    Event::Message(msg) => manager.message_callback(msg.map(T::into_frame)),
+                    Event::Subscribed(..) => Ok(()),
+                    Event::Unsubscribed(channel, topics) => {
+                        manager.on_unsubscribe(&channel, &topics)
+                    }
+                };
+                if let Err(e) = result {
+                    log::warn!("{}", e);
+                }
+            }
+            manager
+        });
+        (Self { tx }, worker)
+    }
+}
+
+impl<T: Decode> AsyncMessageCallback<T> for Pipeline<T> {
+    async fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
+        self.tx
+            .send(Event::Message(msg))
+            .await
+            .map_err(|_| TorrentError::BadConnection("pipeline worker stopped".to_string()))
+    }
+
+    async fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        self.tx
+            .send(Event::Unsubscribed(channel.to_string(), topics.to_vec()))
+            .await
+            .map_err(|_| TorrentError::BadConnection("pipeline worker stopped".to_string()))
+    }
+}
+
+/// A request to be written to an exchange's socket
+#[derive(Debug)]
+pub struct Request {
+    pub payload: String,
+    /// ids under which the exchange acknowledges the request. Empty if the exchange
+    /// acknowledges requests in order without echoing anything identifying them
+    pub ack_ids: Vec<String>,
+}
+
+/// An exchange's response to a [`Request`]
+#[derive(Debug, PartialEq)]
+pub enum Ack {
+    /// the request with this ack id (or, if `None`, the oldest pending request) succeeded
+    Success(Option<String>),
+    /// the request with this ack id (or, if `None`, the oldest pending request) was rejected
+    Failure(Option<String>, String),
+}
+
+/// Caps an exchange puts on a single connection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// topics (streams) one connection may be subscribed to
+    pub topics_per_connection: usize,
+    /// topics one subscribe/unsubscribe request may carry
+    pub topics_per_request: usize,
+    /// pace of outbound messages (requests, pings and pongs). `None` if unlimited
+    pub messages: Option<RateLimit>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            topics_per_connection: usize::MAX,
+            topics_per_request: usize::MAX,
+            messages: None,
+        }
+    }
+}
+
+/// Token bucket allowance: bursts of up to `burst` messages, refilled at `burst` messages
+/// per `per`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, per: Duration) -> Self {
+        Self { burst, per }
+    }
+}
+
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Waits until a token is available and takes it
+    async fn take(&mut self) {
+        let burst = self.limit.burst.max(1) as f64;
+        loop {
+            let now = Instant::now();
+            let elapsed = now.duration_since(self.refilled).as_secs_f64();
+            self.tokens = (self.tokens + elapsed / self.limit.per.as_secs_f64() * burst).min(burst);
+            self.refilled = now;
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+                return;
+            }
+            let missing = (1.0 - self.tokens) / burst;
+            tokio::time::sleep(self.limit.per.mul_f64(missing)).await;
+        }
+    }
+}
+
+/// Delays outbound messages of a connection have seen waiting for the rate limiter
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueueStats {
+    /// messages waiting to be written
+    pub queued: usize,
+    /// time the most recently written message spent queued
+    pub last_delay: Duration,
+    /// longest time any message spent queued
+    pub max_delay: Duration,
+}
+
+/// Queues messages for a connection's writer task, stamped with the time they were queued
+#[derive(Clone)]
+struct Outbox {
+    tx: mpsc::UnboundedSender<(Instant, tungstenite::Message)>,
+    stats: Arc<Mutex<QueueStats>>,
+}
+
+impl Outbox {
+    fn send(&self, msg: tungstenite::Message) -> Result<()> {
+        lock(&self.stats).queued += 1;
+        if self.tx.send((Instant::now(), msg)).is_err() {
+            lock(&self.stats).queued -= 1;
+            return Err(TorrentError::BadConnection(
+                "socket writer stopped".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub trait Wss: Display + Send {
+    fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request>;
+    fn unsubscribe(&mut self, channel: String, topics: Vec<String>) -> Result<Request>;
     fn to_enum(&self) -> Exchange;
+    /// Recognises the exchange's responses to subscribe/unsubscribe requests
+    fn ack_fn(&self) -> fn(&str) -> Option<Ack> {
+        |_| None
+    }
+    fn limits(&self) -> Limits {
+        Limits::default()
+    }
     fn ping_routine(&self) -> Option<PingRoutine> {
         None
     }
+    /// Channel that makes the exchange push heartbeats on its own. Subscribed to on connect
+    fn heartbeat_channel(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Jittered exponential backoff used to pace reconnection attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Returns the delay to wait before the next attempt.
+    /// The ceiling doubles on every call (`base * 2^attempt`, capped at `max`) and the
+    /// returned delay is drawn uniformly from `[ceiling / 2, ceiling]`
+    pub fn next_delay(&mut self) -> Duration {
+        let ceiling = self
+            .base
+            .saturating_mul(2_u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        let half = ceiling / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+
+    /// Starts over from `base` once a connection has been re-established
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
 }
 
+/// Receive the outcome of each request an (un)subscription was split into
+type AckReceivers = Vec<oneshot::Receiver<Result<()>>>;
+
+/// Requests of a connection that are still waiting to be acknowledged
 #[derive(Default)]
-pub struct WebSocketClient {
+struct PendingAcks {
+    next_key: u64,
+    /// ack id -> key of the request expecting it
+    ids: HashMap<String, u64>,
+    /// key -> (acks still expected, responder), in the order the requests were sent
+    requests: BTreeMap<u64, (usize, oneshot::Sender<Result<()>>)>,
+}
+
+impl PendingAcks {
+    fn insert(&mut self, ack_ids: Vec<String>) -> (u64, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        let key = self.next_key;
+        self.next_key += 1;
+        self.requests.insert(key, (ack_ids.len().max(1), tx));
+        for id in ack_ids {
+            self.ids.insert(id, key);
+        }
+        (key, rx)
+    }
+
+    fn resolve(&mut self, ack: Ack) {
+        match ack {
+            Ack::Success(id) => {
+                let Some(key) = self.key(id) else {
+                    return;
+                };
+                if let Some((expected, _)) = self.requests.get_mut(&key) {
+                    *expected -= 1;
+                    if *expected == 0 {
+                        self.complete(key, Ok(()));
+                    }
+                }
+            }
+            Ack::Failure(id, msg) => {
+                if let Some(key) = self.key(id) {
+                    self.complete(key, Err(TorrentError::BadSubscription(msg)));
+                }
+            }
+        }
+    }
+
+    /// Maps an ack id to its request. `None` stands for the oldest pending request
+    fn key(&mut self, id: Option<String>) -> Option<u64> {
+        match id {
+            Some(id) => self.ids.remove(&id),
+            None => self.requests.keys().next().copied(),
+        }
+    }
+
+    fn complete(&mut self, key: u64, result: Result<()>) {
+        self.ids.retain(|_, k| *k != key);
+        if let Some((_, responder)) = self.requests.remove(&key) {
+            let _ = responder.send(result);
+        }
+    }
+}
+
+/// Resolves once the exchange acknowledges a request. Fails if the exchange rejects it,
+/// the connection drops first or no acknowledgement arrives within the ack timeout
+pub struct Acknowledgement {
+    /// one per request the (un)subscription was split into
+    rxs: AckReceivers,
+    timeout: Duration,
+}
+
+impl Acknowledgement {
+    /// Resolves once all of `acks` do
+    pub fn join(acks: Vec<Acknowledgement>) -> Self {
+        let timeout = acks.iter().map(|a| a.timeout).max().unwrap_or_default();
+        Self {
+            rxs: acks.into_iter().flat_map(|a| a.rxs).collect(),
+            timeout,
+        }
+    }
+}
+
+impl IntoFuture for Acknowledgement {
+    type Output = Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            let all = async {
+                for rx in self.rxs {
+                    match rx.await {
+                        Ok(result) => result?,
+                        Err(_) => {
+                            return Err(TorrentError::BadConnection(
+                                "connection closed before the request was acknowledged".to_string(),
+                            ))
+                        }
+                    }
+                }
+                Ok(())
+            };
+            match tokio::time::timeout(self.timeout, all).await {
+                Ok(result) => result,
+                Err(_) => Err(TorrentError::Timeout(format!(
+                    "no acknowledgement within {:?}",
+                    self.timeout
+                ))),
+            }
+        })
+    }
+}
+
+/// Instructions from a [`WebSocketClient`] to the read loop of its current connection
+enum Command {
+    /// `topics` of `channel` were subscribed to
+    Subscribed(String, Vec<String>),
+    /// `topics` of `channel` were unsubscribed from
+    Unsubscribed(String, Vec<String>),
+}
+
+/// Connection state shared by every clone of a [`WebSocketClient`]
+#[derive(Default)]
+struct Connection {
     exchange: Option<Box<dyn Wss>>,
-    socket_w: Option<SocketWriter>,
+    /// every write to the socket goes through this channel to the connection's writer task
+    socket_w: Option<Outbox>,
+    /// queue delays of the connection's outbound messages, kept across reconnects
+    queue_stats: Arc<Mutex<QueueStats>>,
+    /// reaches the read loop of the current connection
+    commands: Option<mpsc::UnboundedSender<Command>>,
+    /// requests of the current connection waiting to be acknowledged
+    pending: Arc<Mutex<PendingAcks>>,
+    /// active topics per channel, replayed on reconnect
+    subscriptions: HashMap<String, Vec<String>>,
+    backoff: Backoff,
+    /// set by [`WebSocketClient::shutdown`], stops listeners from reconnecting
+    closing: bool,
+    /// writer and ping tasks of the current connection
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl Connection {
+    /// Writes `request` to the socket and registers it as waiting for acknowledgement
+    fn send(&self, request: Request) -> Result<oneshot::Receiver<Result<()>>> {
+        let (key, rx) = lock(&self.pending).insert(request.ack_ids);
+        if let Err(e) = self.write(request.payload) {
+            lock(&self.pending).complete(key, Err(TorrentError::BadConnection(e.to_string())));
+            return Err(e);
+        }
+        Ok(rx)
+    }
+
+    fn write(&self, msg: String) -> Result<()> {
+        let ex = self
+            .exchange
+            .as_ref()
+            .ok_or_else(|| TorrentError::BadParse("Not connected to exchange".to_string()))?;
+        match &self.socket_w {
+            Some(socket) => socket.send(tungstenite::Message::text(msg)).map_err(|e| {
+                TorrentError::BadConnection(format!("couldn't write to socket: {}", e))
+            }),
+            None => Err(TorrentError::BadParse(format!(
+                "Not connected to {} socket",
+                ex
+            ))),
+        }
+    }
+
+    fn send_subscription(&mut self, channel: String, topics: Vec<String>) -> Result<AckReceivers> {
+        self.send_chunked(channel, topics, |ex, channel, topics| {
+            ex.subscribe(channel, topics)
+        })
+    }
+
+    fn send_unsubscription(
+        &mut self,
+        channel: String,
+        topics: Vec<String>,
+    ) -> Result<AckReceivers> {
+        self.send_chunked(channel, topics, |ex, channel, topics| {
+            ex.unsubscribe(channel, topics)
+        })
+    }
+
+    /// Splits `topics` into as many requests as the exchange's per-request limit requires
+    fn send_chunked(
+        &mut self,
+        channel: String,
+        topics: Vec<String>,
+        build: fn(&mut dyn Wss, String, Vec<String>) -> Result<Request>,
+    ) -> Result<AckReceivers> {
+        let Some(ex) = &mut self.exchange else {
+            return Err(TorrentError::BadParse(
+                "Not connected to exchange".to_string(),
+            ));
+        };
+        let per_request = ex.limits().topics_per_request.max(1);
+        let mut requests = Vec::new();
+        if topics.is_empty() {
+            requests.push(build(ex.as_mut(), channel, topics)?);
+        } else {
+            for chunk in topics.chunks(per_request) {
+                requests.push(build(ex.as_mut(), channel.clone(), chunk.to_vec())?);
+            }
+        }
+        requests.into_iter().map(|req| self.send(req)).collect()
+    }
+}
+
+/// Handle to a websocket connection to one exchange. Clones share the same connection, so
+/// one clone can be moved into a listening task while another keeps (un)subscribing
+#[derive(Clone)]
+pub struct WebSocketClient {
+    conn: Arc<Mutex<Connection>>,
+    /// how long the connection may stay silent (no data, no pong) before it is declared dead
+    idle_timeout: Duration,
+    /// how long (un)subscriptions may wait for the exchange's acknowledgement
+    ack_timeout: Duration,
+    endpoints: Endpoints,
+    lifecycle: Arc<Lifecycle>,
+    /// overrides the exchange's own outbound rate limit
+    rate_limit: Option<RateLimit>,
+    /// signs Coinbase subscriptions. Falls back to [`CoinbaseCredentials::from_env`]
+    coinbase: Option<CoinbaseCredentials>,
+    /// Binance market connected to, whose limits apply and depth is snapshotted from
+    binance: Market,
+}
+
+impl Default for WebSocketClient {
+    fn default() -> Self {
+        Self {
+            conn: Arc::new(Mutex::new(Connection::default())),
+            idle_timeout: Duration::from_secs(30),
+            ack_timeout: Duration::from_secs(10),
+            endpoints: Endpoints::from_env(),
+            lifecycle: Arc::new(Lifecycle::default()),
+            rate_limit: None,
+            coinbase: None,
+            binance: Market::default(),
+        }
+    }
 }
 
 impl WebSocketClient {
     pub fn new() -> Self {
-        Self {
-            exchange: None,
-            socket_w: None,
+        Default::default()
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn with_ack_timeout(mut self, ack_timeout: Duration) -> Self {
+        self.ack_timeout = ack_timeout;
+        self
+    }
+
+    /// Replaces the endpoints, which otherwise come from [`Endpoints::from_env`]
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Paces outbound messages at `rate_limit` instead of the exchange's default
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Signs Coinbase subscriptions with `credentials` instead of the environment's
+    pub fn with_coinbase_credentials(mut self, credentials: CoinbaseCredentials) -> Self {
+        self.coinbase = Some(credentials);
+        self
+    }
+
+    /// Binance market connected to, spot by default. Sets the connection's limits and where
+    /// [`WebSocketClient::depth_subscribe`] snapshots. Its endpoints are set with
+    /// [`WebSocketClient::with_endpoints`]
+    pub fn with_binance_market(mut self, market: Market) -> Self {
+        self.binance = market;
+        self
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        lock(&self.conn)
+    }
+
+    fn acknowledgement(&self, rxs: AckReceivers) -> Acknowledgement {
+        Acknowledgement {
+            rxs,
+            timeout: self.ack_timeout,
         }
     }
 
-    pub async fn connect(&mut self, exchange: Exchange) -> Result<SocketReader> {
-        if self.exchange.is_some() && self.socket_w.is_some() {
-            return Err(TorrentError::BadConnection(format!(
-                "Already connected to: {}",
-                exchange
-            )));
+    /// A client with the same settings but its own connection
+    pub(crate) fn detached(&self) -> Self {
+        Self {
+            conn: Arc::new(Mutex::new(Connection::default())),
+            lifecycle: Arc::new(Lifecycle::default()),
+            ..self.clone()
         }
+    }
 
-        let url = match exchange {
-            Exchange::COINBASE => Coinbase::URL,
-            Exchange::OKX => Okx::URL,
-            Exchange::BINANCE => Binance::URL,
-            Exchange::BYBIT => Bybit::URL,
-        };
+    /// Current state of the connection
+    pub fn state(&self) -> ConnectionState {
+        self.lifecycle.state()
+    }
 
-        let reader = match connect_async(url).await {
-            Ok((socket, _)) => {
-                let (writer, reader) = socket.split();
-                self.socket_w = Some(writer);
-                reader
-            }
+    /// Receives every state transition of the connection from now on. A receiver that
+    /// falls too far behind skips the oldest transitions
+    pub fn transitions(&self) -> broadcast::Receiver<Transition> {
+        self.lifecycle.subscribe()
+    }
+
+    /// Queue delays outbound messages have seen waiting for the rate limiter
+    pub fn outbound_queue(&self) -> QueueStats {
+        *lock(&self.conn().queue_stats)
+    }
+
+    /// Connection limits of the connected exchange
+    pub(crate) fn limits(&self) -> Option<Limits> {
+        self.conn().exchange.as_ref().map(|ex| ex.limits())
+    }
+
+    /// Opens a new socket to `url` and spawns its writer task, plus a ping task if the
+    /// exchange requires client-side pings. Tasks of a previous connection are aborted
+    async fn handshake(&self, url: &str) -> Result<SocketReader> {
+        let (writer, stream) = match connect_async(url).await {
+            Ok((socket, _)) => socket.split(),
             Err(e) => {
                 return Err(TorrentError::BadConnection(format!(
                     "Unable to handshake: {}",
@@ -79,242 +774,799 @@ impl WebSocketClient {
             }
         };
 
-        match exchange {
-            Exchange::COINBASE => {
-                let key_name = env::var("KEY_NAME").unwrap_or_default();
-                let private_key = env::var("PRIVATE_KEY").unwrap_or_default();
-                if key_name.is_empty() || private_key.is_empty() {
-                    panic!("Coinbase credentails uninitalised. See `.env.example`");
-                }
-                let coinbase = Coinbase::new(key_name, private_key, true);
-                self.exchange = Some(Box::new(coinbase));
-            }
-            Exchange::OKX => {
-                let okx = Okx::new();
-                self.exchange = Some(Box::new(okx));
-            }
-            Exchange::BINANCE => {
-                let binance = Binance::new();
-                self.exchange = Some(Box::new(binance));
+        let mut conn = self.conn();
+        for task in conn.tasks.drain(..) {
+            task.abort();
+        }
+
+        let (tx, socket_w_rx) = mpsc::unbounded_channel();
+        // messages queued for the previous connection were dropped with it
+        lock(&conn.queue_stats).queued = 0;
+        let socket_w_tx = Outbox {
+            tx,
+            stats: conn.queue_stats.clone(),
+        };
+        let rate_limit = self
+            .rate_limit
+            .or_else(|| conn.exchange.as_ref().and_then(|ex| ex.limits().messages));
+        let writer = distribute_msgs(writer, socket_w_rx, rate_limit, conn.queue_stats.clone());
+        conn.tasks.push(tokio::spawn(writer));
+
+        let mut pong_fn: fn(&str) -> bool = |_| false;
+        if let Some(ping_routine) = conn.exchange.as_ref().and_then(|ex| ex.ping_routine()) {
+            pong_fn = ping_routine.pong_fn;
+            conn.tasks.push(tokio::spawn(schedule_pings(
+                socket_w_tx.clone(),
+                ping_routine,
+            )));
+        }
+        conn.socket_w = Some(socket_w_tx);
+
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        conn.commands = Some(commands_tx);
+
+        // requests still pending on the previous connection fail once it is dropped
+        conn.pending = Arc::new(Mutex::new(PendingAcks::default()));
+        let ack_fn = match &conn.exchange {
+            Some(ex) => ex.ack_fn(),
+            None => |_: &str| None,
+        };
+
+        Ok(SocketReader {
+            stream,
+            commands_rx,
+            pending: conn.pending.clone(),
+            idle_timeout: self.idle_timeout,
+            pong_fn,
+            ack_fn,
+            lifecycle: self.lifecycle.clone(),
+        })
+    }
+
+    pub async fn connect(&self, exchange: Exchange) -> Result<SocketReader> {
+        {
+            let mut conn = self.conn();
+            if conn.exchange.is_some() && conn.socket_w.is_some() {
+                return Err(TorrentError::BadConnection(format!(
+                    "Already connected to: {}",
+                    exchange
+                )));
             }
-            Exchange::BYBIT => {
-                let bybit = Bybit::new();
-                self.exchange = Some(Box::new(bybit));
+
+            match &exchange {
+                Exchange::COINBASE => {
+                    let credentials = match &self.coinbase {
+                        Some(credentials) => Some(credentials.clone()),
+                        None => CoinbaseCredentials::from_env()
+                            .map_err(|e| TorrentError::BadRequest(e.to_string()))?,
+                    };
+                    let Some(credentials) = credentials else {
+                        return Err(TorrentError::BadRequest(
+                            "Coinbase credentials uninitialised. See `.env.example`".to_string(),
+                        ));
+                    };
+                    let coinbase = Coinbase::new(credentials);
+                    conn.exchange = Some(Box::new(coinbase));
+                }
+                Exchange::OKX => {
+                    let okx = Okx::new();
+                    conn.exchange = Some(Box::new(okx));
+                }
+                Exchange::BINANCE => {
+                    let binance = Binance::new().with_market(self.binance);
+                    conn.exchange = Some(Box::new(binance));
+                }
+                Exchange::BYBIT => {
+                    let bybit = Bybit::new();
+                    conn.exchange = Some(Box::new(bybit));
+                }
+            };
+        }
+
+        self.lifecycle.set_exchange(exchange);
+        self.lifecycle.transition(ConnectionState::Connecting, None);
+        let reader = match self
+            .handshake(&self.endpoints.get(&exchange).websocket)
+            .await
+        {
+            Ok(reader) => reader,
+            Err(e) => {
+                self.lifecycle
+                    .transition(ConnectionState::Disconnected, Some(e.to_string()));
+                return Err(e);
             }
         };
 
-        /*
-        if let Some(exchange) = self.exchange {
-            if let Some(ping_routine) = exchange.ping_routine() {
-                let socket_w = self
-                    .socket_w
-                    .expect("connected exchange must have writer socket");
+        let heartbeat = self
+            .conn()
+            .exchange
+            .as_ref()
+            .and_then(|ex| ex.heartbeat_channel());
+        if let Some(channel) = heartbeat {
+            // nothing is reading the socket yet, so the acknowledgement isn't awaited
+            self.subscribe(channel, Vec::new()).await?;
+        }
 
-                tokio::spawn(schedule_pings(ping_routine));
+        Ok(reader)
+    }
+
+    /// Sends a subscription for `topics` of `channel`. The returned [`Acknowledgement`]
+    /// resolves once the exchange confirms or rejects it; it can only resolve while the
+    /// connection is being listened to
+    pub async fn subscribe(&self, channel: String, topics: Vec<String>) -> Result<Acknowledgement> {
+        let mut conn = self.conn();
+        let rxs = conn.send_subscription(channel.clone(), topics.clone())?;
+        let active = conn.subscriptions.entry(channel.clone()).or_default();
+        for topic in &topics {
+            if !active.contains(topic) {
+                active.push(topic.clone());
             }
         }
-        */
-        Ok(reader)
+        if let Some(commands) = &conn.commands {
+            let _ = commands.send(Command::Subscribed(channel, topics));
+        }
+        Ok(self.acknowledgement(rxs))
     }
 
-    pub async fn subscribe(&mut self, channel: String, topics: Vec<String>) -> Result<()> {
-        match &mut self.exchange {
-            Some(ex) => match &mut self.socket_w {
-                Some(ref mut socket) => {
-                    let sub_req = ex.subscribe(channel, topics)?;
-                    match socket.send(tungstenite::Message::text(sub_req)).await {
-                        Ok(_) => Ok(()),
-                        Err(e) => Err(TorrentError::BadConnection(format!(
-                            "couldn't write to socket: {}",
-                            e
-                        ))),
+    /// Unsubscribes from `topics` of `channel`. They are dropped from the active
+    /// subscriptions (so they aren't replayed on reconnect) and the listening manager is
+    /// told to purge them through [`MessageCallback::on_unsubscribe`]
+    pub async fn unsubscribe(
+        &self,
+        channel: String,
+        topics: Vec<String>,
+    ) -> Result<Acknowledgement> {
+        let mut conn = self.conn();
+        let rxs = conn.send_unsubscription(channel.clone(), topics.clone())?;
+        if let Some(active) = conn.subscriptions.get_mut(&channel) {
+            active.retain(|t| !topics.contains(t));
+            if active.is_empty() {
+                conn.subscriptions.remove(&channel);
+            }
+        }
+        if let Some(commands) = &conn.commands {
+            let _ = commands.send(Command::Unsubscribed(channel, topics));
+        }
+        Ok(self.acknowledgement(rxs))
+    }
+
+    /// Unsubscribes from and subscribes to `topics` of `channel` again, e.g. to receive
+    /// a fresh snapshot of a book that went out of sync. The active subscriptions stay as
+    /// they are and the listening manager isn't told about the unsubscription. Synchronous,
+    /// so that managers running on a thread of their own can call it
+    pub fn resubscribe(&self, channel: String, topics: Vec<String>) -> Result<Acknowledgement> {
+        let mut conn = self.conn();
+        let mut rxs = conn.send_unsubscription(channel.clone(), topics.clone())?;
+        rxs.extend(conn.send_subscription(channel, topics)?);
+        Ok(self.acknowledgement(rxs))
+    }
+
+    /// Returns the active topics of every subscribed channel
+    pub fn subscriptions(&self) -> HashMap<String, Vec<String>> {
+        self.conn().subscriptions.clone()
+    }
+
+    /// Re-establishes the connection to the already connected exchange, retrying with a
+    /// jittered exponential backoff until the handshake succeeds and every active
+    /// subscription has been replayed
+    pub async fn reconnect(&self) -> Result<SocketReader> {
+        let url = match &self.conn().exchange {
+            Some(ex) => self.endpoints.get(&ex.to_enum()).websocket.clone(),
+            None => {
+                return Err(TorrentError::BadParse(
+                    "Not connected to exchange".to_string(),
+                ))
+            }
+        };
+
+        loop {
+            if self.conn().closing {
+                return Err(TorrentError::BadConnection(
+                    "client is shutting down".to_string(),
+                ));
+            }
+            let delay = self.conn().backoff.next_delay();
+            log::warn!("reconnecting to {} in {:?}", url, delay);
+            self.lifecycle.transition(
+                ConnectionState::Reconnecting,
+                Some(format!("reconnecting in {:?}", delay)),
+            );
+            tokio::time::sleep(delay).await;
+
+            let reader = match self.handshake(&url).await {
+                Ok(reader) => reader,
+                Err(e) => {
+                    log::error!("{}", e);
+                    continue;
+                }
+            };
+
+            let mut conn = self.conn();
+            let mut replayed = true;
+            for (channel, topics) in conn.subscriptions.clone() {
+                match conn.send_subscription(channel.clone(), topics) {
+                    Ok(rxs) => {
+                        let ack = self.acknowledgement(rxs);
+                        tokio::spawn(async move {
+                            if let Err(e) = ack.await {
+                                log::error!("failed to resubscribe to {}: {}", channel, e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        log::error!("{}", e);
+                        replayed = false;
+                        break;
                     }
                 }
-                None => Err(TorrentError::BadParse(format!(
-                    "Not connected to {} socket",
-                    ex
-                ))),
-            },
-            None => Err(TorrentError::BadParse(
-                "Not connected to exchange".to_string(),
-            )),
+            }
+
+            if replayed {
+                log::info!("reconnected to {}", url);
+                conn.backoff.reset();
+                return Ok(reader);
+            }
         }
     }
 
-    pub async fn listen_with<T, M>(reader: SocketReader, callback_manager: M)
+    /// Reads from `reader` until the stream ends, errors or stays idle for longer than the
+    /// client's idle timeout, then hands `callback_manager` back so that it can be reused on
+    /// a fresh connection
+    pub async fn listen_with<T, M>(reader: SocketReader, callback_manager: M) -> M
     where
         M: MessageCallback<T>,
-        T: DeserializeOwned,
+        T: Decode,
+    {
+        Self::listen_with_async(reader, Inline(callback_manager))
+            .await
+            .0
+    }
+
+    /// Same as [`WebSocketClient::listen_with`] but awaits each callback
+    pub async fn listen_with_async<T, M>(reader: SocketReader, callback_manager: M) -> M
+    where
+        M: AsyncMessageCallback<T>,
+        T: Decode,
     {
         let mut manager: M = callback_manager;
+        let SocketReader {
+            mut stream,
+            mut commands_rx,
+            pending,
+            idle_timeout,
+            pong_fn,
+            ack_fn,
+            lifecycle,
+        } = reader;
+
+        // the connection is reported stale after half the idle timeout and dropped after
+        // the other half
+        let stale_after = idle_timeout / 2;
+        let mut stale = false;
+        let closed = loop {
+            let wait = if stale {
+                idle_timeout - stale_after
+            } else {
+                stale_after
+            };
+            let m = tokio::select! {
+                m = tokio::time::timeout(wait, stream.next()) => match m {
+                    Ok(Some(m)) => m,
+                    Ok(None) => {
+                        break TorrentError::Closed(
+                            1006,
+                            "stream ended without a close frame".to_string(),
+                        );
+                    }
+                    Err(_) if !stale => {
+                        stale = true;
+                        lifecycle.transition(
+                            ConnectionState::Stale,
+                            Some(format!("nothing received for {:?}", stale_after)),
+                        );
+                        continue;
+                    }
+                    Err(_) => {
+                        break TorrentError::Timeout(format!(
+                            "no data or pong received for {:?}",
+                            idle_timeout
+                        ));
+                    }
+                },
+                Some(command) = commands_rx.recv() => {
+                    let result = match command {
+                        Command::Subscribed(channel, topics) => {
+                            manager.on_subscribe(&channel, &topics).await
+                        }
+                        Command::Unsubscribed(channel, topics) => {
+                            manager.on_unsubscribe(&channel, &topics).await
+                        }
+                    };
+                    if let Err(e) = result {
+                        log::error!("{}", e);
+                    }
+                    continue;
+                }
+            };
+            stale = false;
 
-        let read_fut = reader.for_each(|m| {
             let data: String = match m {
-                Ok(v) => v.to_string(),
-                Err(e) => format!("websocket sent the error: {}", e),
+                Ok(tungstenite::Message::Text(text)) => text,
+                Ok(tungstenite::Message::Binary(bytes)) => match decode_binary(&bytes) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        let _ = manager.message_callback(Err(e)).await;
+                        continue;
+                    }
+                },
+                // tungstenite answers pings itself
+                Ok(tungstenite::Message::Ping(_)) => continue,
+                Ok(tungstenite::Message::Pong(_)) => continue,
+                Ok(tungstenite::Message::Close(frame)) => {
+                    // 1005: no status code was present
+                    let (code, reason) = frame
+                        .map(|f| (u16::from(f.code), f.reason.into_owned()))
+                        .unwrap_or((1005, String::new()));
+                    break TorrentError::Closed(code, reason);
+                }
+                // raw frames are only produced when writing
+                Ok(tungstenite::Message::Frame(_)) => continue,
+                Err(e) => break e.into(),
             };
 
-            let _ = match serde_json::from_str(&data) {
-                Ok(msg) => manager.message_callback(Ok(msg)),
-                Err(e) => manager.message_callback(Err(TorrentError::BadParse(format!(
-                    "Unable to parse msg because {} {}",
-                    e, data
-                )))),
+            if pong_fn(&data) {
+                continue;
+            }
+
+            match ack_fn(&data) {
+                Some(ack) => {
+                    if let Ack::Success(_) = ack {
+                        lifecycle.transition_if(
+                            |from| {
+                                matches!(
+                                    from,
+                                    ConnectionState::Connecting | ConnectionState::Reconnecting
+                                )
+                            },
+                            ConnectionState::Subscribed,
+                            None,
+                        );
+                    }
+                    lock(&pending).resolve(ack);
+                }
+                None => lifecycle.transition(ConnectionState::Streaming, None),
+            }
+
+            // the message borrows from `data` until the callback is done with it
+            let msg = serde_json::from_str::<T::Frame<'_>>(&data).map_err(|e| {
+                TorrentError::BadParse(format!("Unable to parse msg because {} {}", e, data))
+            });
+            if let Some(msg) = manager.frame_callback(msg) {
+                let _ = manager.message_callback(msg).await;
+            }
+        };
+
+        lifecycle.transition(ConnectionState::Disconnected, Some(closed.to_string()));
+        let _ = manager.message_callback(Err(closed)).await;
+
+        manager
+    }
+
+    /// Same as [`WebSocketClient::listen_with`] but supervises the connection: whenever the
+    /// stream ends, the client reconnects and replays its subscriptions. Never returns
+    pub async fn listen_with_reconnect<T, M>(self, reader: SocketReader, callback_manager: M)
+    where
+        M: MessageCallback<T>,
+        T: Decode,
+    {
+        self.listen_with_reconnect_async(reader, Inline(callback_manager))
+            .await
+    }
+
+    /// Same as [`WebSocketClient::listen_with_reconnect`] but awaits each callback
+    pub async fn listen_with_reconnect_async<T, M>(self, reader: SocketReader, callback_manager: M)
+    where
+        M: AsyncMessageCallback<T>,
+        T: Decode,
+    {
+        let mut reader = reader;
+        let mut manager = callback_manager;
+        loop {
+            manager = Self::listen_with_async(reader, manager).await;
+            if self.conn().closing {
+                return;
+            }
+            log::warn!("websocket stream ended");
+            reader = match self.reconnect().await {
+                Ok(reader) => reader,
+                Err(e) => {
+                    log::error!("{}", e);
+                    return;
+                }
             };
+        }
+    }
 
-            async {}
-        });
+    /// Unsubscribes from every active subscription and closes the connection with a Close
+    /// frame. Listeners supervising the connection return instead of reconnecting once the
+    /// exchange answers the Close frame (or the idle timeout runs out)
+    pub async fn shutdown(&self) -> Result<()> {
+        let mut acks = Vec::new();
+        for (channel, topics) in self.subscriptions() {
+            match self.unsubscribe(channel, topics).await {
+                Ok(ack) => acks.push(ack),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        if let Err(e) = Acknowledgement::join(acks).await {
+            log::warn!("unsubscribing on shutdown failed: {}", e);
+        }
 
-        read_fut.await
+        let mut conn = self.conn();
+        conn.closing = true;
+        match &conn.socket_w {
+            Some(socket) => socket.send(tungstenite::Message::Close(Some(CloseFrame {
+                code: CloseCode::Normal,
+                reason: "shutdown".into(),
+            }))),
+            None => Ok(()),
+        }
     }
 
+    /// Subscribes to the depth stream of `topics` and spawns a task that feeds it, together
+    /// with REST snapshots, into `callback_manager`. On disconnect the task reconnects,
+    /// replays the subscriptions and re-fetches snapshots for the still active topics. After
+    /// [`WebSocketClient::shutdown`] the task hands `callback_manager` back, once it has
+    /// handled every message received
     pub async fn depth_subscribe<M, E, T, Snapshot>(
-        &mut self,
+        self,
         reader: SocketReader,
         topics: Vec<String>,
         callback_manager: M,
-    ) -> Result<tokio::task::JoinHandle<()>>
+    ) -> Result<tokio::task::JoinHandle<Result<M>>>
     where
         M: DepthCallback<T, Snapshot> + Send + 'static,
         E: Display + DeserializeOwned + 'static,
-        T: Debug + DeserializeOwned + 'static + marker::Send,
+        T: Debug + Decode + 'static + marker::Send,
         Snapshot: Debug + DeserializeOwned + 'static + marker::Send,
     {
-        let listener = match &mut self.exchange {
-            Some(ex) => match ex.to_enum() {
-                Exchange::BINANCE => {
-                    let channel = Channel::DEPTH.to_string();
-                    let endpoint = API::Spot(Spot::Depth);
-                    match self.subscribe(channel, topics.clone()).await {
-                        Ok(_) => (),
-                        Err(err) => return Err(err),
-                    }
-                    let mut params: HashMap<Symbol, [(String, String); 2]> = HashMap::new();
-                    for topic in topics {
-                        let topic = topic.split('-').collect::<Vec<&str>>();
-                        if topic.len() != 2 {
-                            eprintln!("{} does't conform to X-Y format", topic.join(""));
-                            continue;
-                        }
-                        let topic = topic.join("");
-                        let param = [
-                            ("symbol".to_string(), topic.clone()),
-                            ("limit".to_string(), "5000".to_string()),
-                        ];
-                        params.insert(topic, param);
-                    }
-                    tokio::spawn(DepthManager::<M, Snapshot, T>::request_snapshot::<
-                        [(String, String); 2],
-                        E,
-                    >(
-                        reader, endpoint, params, callback_manager
-                    ))
+        let exchange = self.depth_exchange()?;
+        let ack = self.subscribe(Channel::DEPTH.to_string(), topics).await?;
+        tokio::spawn(async move {
+            if let Err(e) = ack.await {
+                log::error!("depth subscription failed: {}", e);
+            }
+        });
+        let listener = tokio::spawn(async move {
+            let mut reader = reader;
+            let mut manager = self.depth_manager::<M, Snapshot, T>(exchange, callback_manager);
+            loop {
+                match self
+                    .request_depth_snapshots::<_, E, _, _>(&mut manager)
+                    .await
+                {
+                    Ok(_) => manager = Self::listen_with_async(reader, manager).await,
+                    Err(e) => log::error!("{}", e),
                 }
-                _ => {
-                    return Err(TorrentError::BadRequest(format!(
-                        "Use `subscribe` method for depth orderbook subscription to {}",
-                        ex
-                    )))
+                if self.conn().closing {
+                    break;
                 }
-            },
-            None => {
-                return Err(TorrentError::BadParse(
-                    "Not connected to exchange".to_string(),
-                ))
+                reader = match self.reconnect().await {
+                    Ok(reader) => reader,
+                    Err(e) => {
+                        log::error!("{}", e);
+                        break;
+                    }
+                };
             }
-        };
+            manager.into_inner().await
+        });
         Ok(listener)
     }
+
+    /// Exchange of the connection, if its depth is subscribed to with REST snapshots
+    pub(crate) fn depth_exchange(&self) -> Result<Exchange> {
+        match &self.conn().exchange {
+            Some(ex) if ex.to_enum() == Exchange::BINANCE => Ok(Exchange::BINANCE),
+            Some(ex) => Err(TorrentError::BadRequest(format!(
+                "Use `subscribe` method for depth orderbook subscription to {}",
+                ex.to_enum()
+            ))),
+            None => Err(TorrentError::BadParse(
+                "Not connected to exchange".to_string(),
+            )),
+        }
+    }
+
+    /// Depth manager fed with snapshots from the REST api of `exchange`
+    pub(crate) fn depth_manager<M, Snapshot, T>(
+        &self,
+        exchange: Exchange,
+        callback_manager: M,
+    ) -> DepthManager<M, Snapshot, T>
+    where
+        M: DepthCallback<T, Snapshot> + Send + 'static,
+        Snapshot: DeserializeOwned + Debug + Send + 'static,
+        T: Send + 'static,
+    {
+        let rest_url = self.endpoints.get(&exchange).rest.clone();
+        DepthManager::new(callback_manager, rest_url).with_market(self.binance)
+    }
+
+    /// Requests snapshots of the connection's depth subscriptions for `manager`
+    pub(crate) async fn request_depth_snapshots<M, E, T, Snapshot>(
+        &self,
+        manager: &mut DepthManager<M, Snapshot, T>,
+    ) -> Result<()>
+    where
+        M: DepthCallback<T, Snapshot> + Send + 'static,
+        E: Display + DeserializeOwned,
+        Snapshot: DeserializeOwned + Debug + Send + 'static,
+        T: Send + 'static,
+    {
+        let topics = self
+            .subscriptions()
+            .remove(&Channel::DEPTH.to_string())
+            .unwrap_or_default();
+        let params = Self::depth_params(self.binance, topics);
+        let endpoint = String::from(self.binance.depth_api());
+        manager.request_snapshot::<_, E>(&endpoint, &params).await
+    }
+
+    fn depth_params(market: Market, topics: Vec<String>) -> HashMap<Symbol, [(String, String); 2]> {
+        let mut params: HashMap<Symbol, [(String, String); 2]> = HashMap::new();
+        for topic in topics {
+            let Some(symbol) = Binance::symbol(&topic) else {
+                log::warn!("{} does't conform to X-Y format", topic);
+                continue;
+            };
+            params.insert(symbol.clone(), market.depth_param(symbol));
+        }
+        params
+    }
 }
 
 pub trait DepthCallback<T, Snapshot> {
-    const REST_URL: &'static str;
-
     fn depth_callback(&mut self, msg: Result<T>, snapshots_mp: Option<HashMap<Symbol, Snapshot>>);
+    /// Called once depth `topics` have been unsubscribed from
+    fn on_unsubscribe(&mut self, _topics: &[String]) {}
+    /// Hands over the handle through which books that went out of sync ask for a fresh
+    /// snapshot
+    fn on_snapshot_requests(&mut self, _requests: SnapshotRequests) {}
 }
 
-pub struct DepthManager<M, Snapshot, T>
-where
-    M: DepthCallback<T, Snapshot>,
-    Snapshot: DeserializeOwned,
-{
-    user_manager: M,
+/// Asks the [`DepthManager`] for a fresh REST snapshot of a symbol. The snapshot is fetched
+/// without holding the reader loop up and handed over with a later message, like the first
+#[derive(Debug, Clone)]
+pub struct SnapshotRequests(mpsc::UnboundedSender<Symbol>);
+
+impl SnapshotRequests {
+    pub fn request(&self, symbol: Symbol) -> Result<()> {
+        self.0
+            .send(symbol)
+            .map_err(|_| TorrentError::BadConnection("depth manager stopped".to_string()))
+    }
+}
+
+/// Attempts at fetching a re-requested snapshot. A book whose snapshot couldn't be fetched
+/// stays out of sync until the next reconnect
+const SNAPSHOT_ATTEMPTS: usize = 3;
+
+enum DepthEvent<T, Snapshot> {
+    Message(Result<T>, Option<HashMap<Symbol, Snapshot>>),
+    Unsubscribed(Vec<String>),
+}
+
+/// Number of depth messages queued for the user's manager before the reader loop waits
+const DEPTH_QUEUE: usize = 1024;
+
+/// Pairs depth messages with REST snapshots and hands both to a [`DepthCallback`] running on
+/// a thread of its own, so a slow manager doesn't stall the reader loop
+pub struct DepthManager<M, Snapshot, T> {
+    tx: mpsc::Sender<DepthEvent<T, Snapshot>>,
+    worker: JoinHandle<M>,
+    /// base url of the exchange's REST api snapshots are requested from
+    rest_url: String,
+    /// route of the last snapshots, which re-requested ones are fetched from too
+    endpoint: String,
+    /// market re-requested snapshots are queried for
+    market: Market,
     snapshots_mp: Option<HashMap<Symbol, Snapshot>>,
-    _marker: marker::PhantomData<T>,
+    requests_rx: mpsc::UnboundedReceiver<Symbol>,
+    /// re-requested snapshots, fetched on tasks of their own
+    fetched_tx: mpsc::UnboundedSender<(Symbol, Snapshot)>,
+    fetched_rx: mpsc::UnboundedReceiver<(Symbol, Snapshot)>,
 }
 
 impl<M, Snapshot, T> DepthManager<M, Snapshot, T>
 where
-    M: DepthCallback<T, Snapshot>,
-    Snapshot: DeserializeOwned + Debug,
+    M: DepthCallback<T, Snapshot> + Send + 'static,
+    Snapshot: DeserializeOwned + Debug + Send + 'static,
+    T: Send + 'static,
 {
+    pub fn new(mut callback_manager: M, rest_url: String) -> Self {
+        let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+        callback_manager.on_snapshot_requests(SnapshotRequests(requests_tx));
+        let (fetched_tx, fetched_rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::channel(DEPTH_QUEUE);
+        let worker = tokio::task::spawn_blocking(move || {
+            let mut manager = callback_manager;
+            while let Some(event) = rx.blocking_recv() {
+                match event {
+                    DepthEvent::Message(msg, snapshots_mp) => {
+                        manager.depth_callback(msg, snapshots_mp)
+                    }
+                    DepthEvent::Unsubscribed(topics) => manager.on_unsubscribe(&topics),
+                }
+            }
+            manager
+        });
+        Self {
+            tx,
+            worker,
+            rest_url,
+            endpoint: String::new(),
+            market: Market::default(),
+            snapshots_mp: None,
+            requests_rx,
+            fetched_tx,
+            fetched_rx,
+        }
+    }
+
+    pub fn with_market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Stops the worker once it has handled every queued message and hands the user's
+    /// manager back
+    pub async fn into_inner(self) -> Result<M> {
+        drop(self.tx);
+        self.worker
+            .await
+            .map_err(|e| TorrentError::BadConnection(format!("depth manager failed: {}", e)))
+    }
+
+    /// Fetches a fresh depth snapshot for every symbol in `params`. The snapshots are
+    /// handed to the user's manager together with the next websocket message
     pub async fn request_snapshot<S, E>(
-        reader: SocketReader,
-        endpoint: impl Into<String>,
-        params: HashMap<Symbol, S>,
-        callback_manager: M,
-    ) where
-        M: DepthCallback<T, Snapshot>,
-        T: Debug + DeserializeOwned,
+        &mut self,
+        endpoint: &str,
+        params: &HashMap<Symbol, S>,
+    ) -> Result<()>
+    where
         S: Debug + Serialize,
         E: Display + DeserializeOwned,
     {
-        let rest_client = RestClient::new(M::REST_URL);
-        let endpoint = endpoint.into();
+        self.endpoint = endpoint.to_string();
+        let rest_client = RestClient::new(&self.rest_url);
         let mut snapshots_mp: HashMap<Symbol, Snapshot> = HashMap::new();
         for (symbol, param) in params {
             let depth_snapshot = match rest_client
-                .get::<Snapshot, E, S>(&endpoint, Some(param))
+                .get::<Snapshot, E, &S>(endpoint, Some(param))
                 .await
             {
                 Ok(depth_snapshot) => depth_snapshot,
                 Err(e) => {
-                    eprintln!("{}", TorrentError::BadRequest(e.to_string()));
+                    log::warn!("{}", TorrentError::BadRequest(e.to_string()));
                     continue;
                 }
             };
-            snapshots_mp.insert(symbol, depth_snapshot);
+            snapshots_mp.insert(symbol.clone(), depth_snapshot);
         }
         if snapshots_mp.is_empty() {
-            eprintln!("All snapshot requests were unsuccessful");
-            return;
+            return Err(TorrentError::BadRequest(
+                "All snapshot requests were unsuccessful".to_string(),
+            ));
         }
-        let manager = Self {
-            user_manager: callback_manager,
-            snapshots_mp: Some(snapshots_mp),
-            _marker: marker::PhantomData,
-        };
-        WebSocketClient::listen_with(reader, manager).await;
+        self.snapshots_mp = Some(snapshots_mp);
+        Ok(())
+    }
+
+    /// Fetches the snapshot of `symbol` on a task of its own, retrying a few times
+    fn fetch_snapshot(&self, symbol: Symbol) {
+        let rest_url = self.rest_url.clone();
+        let endpoint = self.endpoint.clone();
+        let fetched_tx = self.fetched_tx.clone();
+        let param = self.market.depth_param(symbol.clone());
+        tokio::spawn(async move {
+            let rest_client = RestClient::new(&rest_url);
+            for attempt in 1..=SNAPSHOT_ATTEMPTS {
+                // the error body is only displayed, whatever its shape
+                match rest_client
+                    .get::<Snapshot, serde_json::Value, _>(endpoint.as_str(), Some(&param))
+                    .await
+                {
+                    Ok(snapshot) => {
+                        let _ = fetched_tx.send((symbol, snapshot));
+                        return;
+                    }
+                    Err(e) => log::warn!("snapshot of {} failed ({}): {}", symbol, attempt, e),
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        });
     }
 }
 
-impl<M, Snapshot, T> MessageCallback<T> for DepthManager<M, Snapshot, T>
+impl<M, Snapshot, T> AsyncMessageCallback<T> for DepthManager<M, Snapshot, T>
 where
-    M: DepthCallback<T, Snapshot>,
-    Snapshot: DeserializeOwned,
-    T: Debug,
+    M: DepthCallback<T, Snapshot> + Send + 'static,
+    Snapshot: DeserializeOwned + Debug + Send + 'static,
+    T: Decode + Send + 'static,
 {
-    fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
-        if self.snapshots_mp.is_some() {
-            let snapshots_mp = self.snapshots_mp.take();
-            self.user_manager.depth_callback(msg, snapshots_mp);
-            return Ok(());
+    async fn message_callback(&mut self, msg: Result<T>) -> Result<()> {
+        while let Ok(symbol) = self.requests_rx.try_recv() {
+            self.fetch_snapshot(symbol);
         }
-        self.user_manager.depth_callback(msg, None);
-        Ok(())
+        while let Ok((symbol, snapshot)) = self.fetched_rx.try_recv() {
+            self.snapshots_mp
+                .get_or_insert_with(HashMap::new)
+                .insert(symbol, snapshot);
+        }
+        let snapshots_mp = self.snapshots_mp.take();
+        self.tx
+            .send(DepthEvent::Message(msg, snapshots_mp))
+            .await
+            .map_err(|_| TorrentError::BadConnection("depth manager stopped".to_string()))
+    }
+
+    async fn on_unsubscribe(&mut self, _channel: &str, topics: &[String]) -> Result<()> {
+        self.tx
+            .send(DepthEvent::Unsubscribed(topics.to_vec()))
+            .await
+            .map_err(|_| TorrentError::BadConnection("depth manager stopped".to_string()))
     }
 }
 
+/// Describes how an exchange expects to be pinged: `ping_fn` builds the payload sent every
+/// `period` and `pong_fn` recognises the exchange's text reply so it isn't parsed as data
 pub struct PingRoutine {
     interval: tokio::time::Interval,
     ping_fn: fn() -> tungstenite::Message,
+    pong_fn: fn(&str) -> bool,
+}
+
+impl PingRoutine {
+    pub fn new(
+        period: Duration,
+        ping_fn: fn() -> tungstenite::Message,
+        pong_fn: fn(&str) -> bool,
+    ) -> Self {
+        let start = tokio::time::Instant::now() + period;
+        Self {
+            interval: tokio::time::interval_at(start, period),
+            ping_fn,
+            pong_fn,
+        }
+    }
 }
 
 async fn distribute_msgs(
     mut socket_w: SocketWriter,
-    mut socket_w_rx: mpsc::UnboundedReceiver<tungstenite::Message>,
+    mut socket_w_rx: mpsc::UnboundedReceiver<(Instant, tungstenite::Message)>,
+    rate_limit: Option<RateLimit>,
+    stats: Arc<Mutex<QueueStats>>,
 ) {
-    while let Some(msg) = socket_w_rx.recv().await {
+    let mut bucket = rate_limit.map(TokenBucket::new);
+    while let Some((queued_at, msg)) = socket_w_rx.recv().await {
+        if let Some(bucket) = &mut bucket {
+            bucket.take().await;
+        }
+        let delay = queued_at.elapsed();
+        {
+            let mut stats = lock(&stats);
+            stats.queued = stats.queued.saturating_sub(1);
+            stats.last_delay = delay;
+            stats.max_delay = stats.max_delay.max(delay);
+        }
+        if delay >= Duration::from_secs(1) {
+            log::warn!(
+                "outbound message was queued for {:?} by the rate limiter",
+                delay
+            );
+        }
         if let Err(e) = socket_w.send(msg).await {
             if matches!(
                 e,
@@ -327,16 +1579,17 @@ async fn distribute_msgs(
             ) {
                 break;
             }
-            eprintln!("failed to write to exchange wss");
+            log::error!("failed to write to exchange wss");
         }
     }
 }
 
 async fn schedule_pings(
-    socket_w_tx: mpsc::UnboundedSender<tungstenite::Message>,
+    socket_w_tx: Outbox,
     PingRoutine {
         mut interval,
         ping_fn,
+        ..
     }: PingRoutine,
 ) {
     loop {
@@ -0,0 +1,438 @@
+mod mock;
+
+use async_wss::arbitration::{Arbiter, RedundantFeed, Sequenced};
+use async_wss::binance::{
+    Channel as BinanceChannel, DepthSnapshot, Message as BinanceMessage, RequestError,
+};
+use async_wss::bybit::{Channel as BybitChannel, Depth, Message as BybitMessage};
+use async_wss::coinbase::{
+    Channel as CoinbaseChannel, Manager as CoinbaseManager, Message as CoinbaseMessage,
+};
+use async_wss::config::{CoinbaseAuth, CoinbaseCredentials, Endpoints};
+use async_wss::okx::Message as OkxMessage;
+use async_wss::utils::{Exchange, Result, Symbol};
+use async_wss::websocket::{Decode, DepthCallback, MessageCallback, WebSocketClient};
+use mock::{Dialect, MockExchange, Step};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+struct Collector<T>(mpsc::UnboundedSender<Result<T>>);
+
+impl<T: Decode> MessageCallback<T> for Collector<T> {
+    fn message_callback(&mut self, msg: Result<T::Frame<'_>>) -> Result<()> {
+        let _ = self.0.send(msg.map(T::into_owned));
+        Ok(())
+    }
+}
+
+/// Forwards every unsubscription reaching the manager to the test
+struct Unsubscriptions(mpsc::UnboundedSender<(String, Vec<String>)>);
+
+impl<T: Decode> MessageCallback<T> for Unsubscriptions {
+    fn message_callback(&mut self, _msg: Result<T::Frame<'_>>) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_unsubscribe(&mut self, channel: &str, topics: &[String]) -> Result<()> {
+        let _ = self.0.send((channel.to_string(), topics.to_vec()));
+        Ok(())
+    }
+}
+
+/// Forwards every depth message to the test with the symbols of the snapshots it came with
+struct DepthCollector(mpsc::UnboundedSender<(Vec<Symbol>, Result<BinanceMessage<'static>>)>);
+
+impl DepthCallback<BinanceMessage<'static>, DepthSnapshot> for DepthCollector {
+    fn depth_callback(
+        &mut self,
+        msg: Result<BinanceMessage<'static>>,
+        snapshots_mp: Option<HashMap<Symbol, DepthSnapshot>>,
+    ) {
+        let symbols = snapshots_mp.map_or(vec![], |mp| mp.into_keys().collect());
+        let _ = self.0.send((symbols, msg));
+    }
+}
+
+#[test]
+fn test_sequences() {
+    let okx = mock::okx::books("BTC-USDT", "update", 122, 123, 0, &[(100.0, 1.0)], &[]);
+    let okx: OkxMessage = serde_json::from_str(&okx).unwrap();
+    assert_eq!(okx.sequence(), Some(("books:BTC-USDT".to_string(), 123)));
+
+    let bybit = mock::bybit::orderbook("BTCUSDT", "delta", 7, &[], &[(101.0, 1.0)]);
+    let bybit: BybitMessage = serde_json::from_str(&bybit).unwrap();
+    assert_eq!(
+        bybit.sequence(),
+        Some(("orderbook.50.BTCUSDT".to_string(), 1007))
+    );
+
+    let coinbase = r#"{"channel":"l2_data","client_id":"","timestamp":"2023-02-09T20:32:50.714964855Z","sequence_num":0,"events":[{"type":"update","product_id":"BTC-USD","updates":[{"side":"bid","event_time":"2023-02-09T20:32:50.714964855Z","price_level":"21921.73","new_quantity":"0.06317902"}]}]}"#;
+    let coinbase: CoinbaseMessage = serde_json::from_str(coinbase).unwrap();
+    assert_eq!(
+        coinbase.sequence(),
+        Some(("BTC-USD".to_string(), 1675974770714964855))
+    );
+}
+
+#[test]
+fn test_arbiter_drops_copies() {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Result<BybitMessage>>();
+    let mut arbiter = Arbiter::new(Collector(tx));
+    let update = |u: u64| -> Result<BybitMessage<'static>> {
+        let msg = mock::bybit::orderbook("BTCUSDT", "delta", u, &[(100.0, 1.0)], &[]);
+        Ok(Decode::into_owned(serde_json::from_str(&msg).unwrap()))
+    };
+
+    // u = 1 would be a snapshot, which is always forwarded
+    for u in [2, 2, 3, 4, 3, 4, 5] {
+        arbiter.message_callback(update(u)).unwrap();
+    }
+    let mut forwarded = Vec::new();
+    while let Ok(Ok(msg)) = rx.try_recv() {
+        forwarded.push(msg.sequence().unwrap().1 - 1000);
+    }
+    assert_eq!(forwarded, vec![2, 3, 4, 5]);
+    assert_eq!(arbiter.duplicates(), 3);
+}
+
+#[test]
+fn test_arbiter_forwards_resync_snapshots() {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Result<CoinbaseMessage>>();
+    let mut arbiter = Arbiter::new(Collector(tx));
+    // snapshot levels are all stamped with the epoch
+    let level2 = |kind, event_time| -> Result<CoinbaseMessage> {
+        let msg = mock::coinbase::level2_at("BTC-USD", kind, 0, event_time, &[("bid", 100.0, 1.0)]);
+        Ok(serde_json::from_str(&msg).unwrap())
+    };
+    let epoch = "1970-01-01T00:00:00Z";
+    let (t1, t2) = ("2023-02-09T20:32:50.1Z", "2023-02-09T20:32:50.2Z");
+
+    // both legs' copies of the snapshot and of an update, then a resync's snapshot
+    for (kind, event_time) in [
+        ("snapshot", epoch),
+        ("snapshot", epoch),
+        ("update", t1),
+        ("update", t1),
+        ("snapshot", epoch),
+        ("update", t2),
+    ] {
+        arbiter.message_callback(level2(kind, event_time)).unwrap();
+    }
+    // the stream starts over once unsubscribed
+    arbiter
+        .on_unsubscribe("level2", &["BTC-USD".to_string()])
+        .unwrap();
+    arbiter.message_callback(level2("update", t1)).unwrap();
+
+    let mut forwarded = Vec::new();
+    while let Ok(Ok(CoinbaseMessage::Level2(msg))) = rx.try_recv() {
+        forwarded.push(msg.events[0].r#type.clone());
+    }
+    assert_eq!(
+        forwarded,
+        vec!["snapshot", "snapshot", "update", "snapshot", "update", "update"]
+    );
+    assert_eq!(arbiter.duplicates(), 1);
+}
+
+#[test]
+fn test_arbiter_tells_apart_batches_sharing_a_time() {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Result<CoinbaseMessage>>();
+    let mut arbiter = Arbiter::new(Collector(tx));
+    let update = |event_time, side, qty| -> Result<CoinbaseMessage> {
+        let msg =
+            mock::coinbase::level2_at("BTC-USD", "update", 0, event_time, &[(side, 100.0, qty)]);
+        Ok(serde_json::from_str(&msg).unwrap())
+    };
+    let (t1, t2) = ("2023-02-09T20:32:50.1Z", "2023-02-09T20:32:50.2Z");
+
+    // each batch arrives from both legs
+    for (event_time, side, qty) in [
+        (t1, "bid", 1.0),
+        (t1, "bid", 1.0),
+        (t1, "offer", 2.0),
+        (t1, "bid", 1.0),
+        (t1, "offer", 2.0),
+        (t2, "bid", 0.0),
+        (t1, "offer", 2.0),
+    ] {
+        arbiter
+            .message_callback(update(event_time, side, qty))
+            .unwrap();
+    }
+
+    let mut forwarded = Vec::new();
+    while let Ok(Ok(CoinbaseMessage::Level2(msg))) = rx.try_recv() {
+        let update = &msg.events[0].updates[0];
+        forwarded.push((update.side.clone(), update.new_quantity));
+    }
+    assert_eq!(
+        forwarded,
+        vec![
+            ("bid".to_string(), 1.0),
+            ("offer".to_string(), 2.0),
+            ("bid".to_string(), 0.0)
+        ]
+    );
+    assert_eq!(arbiter.duplicates(), 4);
+}
+
+#[tokio::test]
+async fn test_redundant_coinbase_feed_checks_each_leg() {
+    let epoch = "1970-01-01T00:00:00Z";
+    let (t1, t2) = ("2023-02-09T20:32:50.1Z", "2023-02-09T20:32:50.2Z");
+    let level2 = |kind, sequence_num, event_time, side, qty| {
+        Step::Send(mock::coinbase::level2_at(
+            "BTC-USD",
+            kind,
+            sequence_num,
+            event_time,
+            &[(side, 100.0, qty)],
+        ))
+    };
+    // the heartbeats and level2 subscriptions are acknowledged with 0 and 1
+    let leg = |last| {
+        vec![
+            Step::AwaitSubscriptions(2),
+            level2("snapshot", 2, epoch, "bid", 1.0),
+            level2("update", 3, t1, "bid", 2.0),
+            level2("update", 4, t1, "offer", 3.0),
+            Step::Sleep(Duration::from_millis(500)),
+            last,
+        ]
+    };
+    let server = MockExchange::new(Dialect::Coinbase)
+        .script(leg(level2("update", 5, t2, "bid", 0.0)))
+        // the second leg misses 5
+        .script(leg(level2("update", 6, t2, "bid", 0.0)))
+        .start()
+        .await;
+
+    let client = WebSocketClient::new()
+        .with_endpoints(Endpoints::new().with(Exchange::COINBASE, server.endpoint()))
+        .with_coinbase_credentials(CoinbaseCredentials::new(
+            "mock",
+            "mock",
+            CoinbaseAuth::Legacy,
+        ));
+    let feed = RedundantFeed::connect(Exchange::COINBASE, client)
+        .await
+        .unwrap();
+    let ack = feed
+        .subscribe(
+            CoinbaseChannel::LEVEL2.to_string(),
+            vec!["BTC-USD".to_string()],
+        )
+        .await
+        .unwrap();
+    // the legs check sequence_num, the manager resubscribes through the first one
+    let manager = CoinbaseManager::new()
+        .with_client(feed.legs()[0].clone())
+        .with_sequence_check(false);
+    tokio::spawn(feed.listen_with(manager));
+    ack.await.unwrap();
+
+    // interleaved legs are each in sequence
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(server.requests().len(), 4);
+
+    let requests = timeout(Duration::from_secs(5), async {
+        while server.requests().len() < 6 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        server.requests()
+    })
+    .await
+    .expect("the manager didn't resync");
+    assert!(requests[4].contains("\"type\":\"unsubscribe\""));
+    assert!(requests[5].contains("\"type\":\"subscribe\""));
+    assert!(requests[5].contains("BTC-USD"));
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(server.requests().len(), 6);
+}
+
+#[tokio::test]
+async fn test_redundant_depth_feed() {
+    let update =
+        |first, last| mock::binance::depth_update("BTCUSDT", first, last, &[(100.0, 2.0)], &[]);
+    let server = MockExchange::new(Dialect::Binance)
+        .rest(
+            "/api/v3/depth",
+            mock::binance::depth_snapshot(10, &[(100.0, 1.0)], &[(101.0, 1.0)]),
+        )
+        // leg A stalls after the first update
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update(9, 11)),
+            Step::Sleep(Duration::from_secs(60)),
+        ])
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update(9, 11)),
+            Step::Send(update(12, 13)),
+            Step::Send(update(14, 15)),
+        ])
+        .start()
+        .await;
+
+    let client = WebSocketClient::new()
+        .with_endpoints(Endpoints::new().with(Exchange::BINANCE, server.endpoint()));
+    let feed = RedundantFeed::connect(Exchange::BINANCE, client)
+        .await
+        .unwrap();
+    let ack = feed
+        .subscribe(
+            BinanceChannel::DEPTH.to_string(),
+            vec!["BTC-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let listener = feed
+        .listen_with_depth::<_, RequestError, BinanceMessage, DepthSnapshot>(DepthCollector(tx));
+    tokio::spawn(listener);
+    ack.await.unwrap();
+
+    let (mut snapshots, mut forwarded) = (Vec::new(), Vec::new());
+    while forwarded.len() < 3 {
+        let (symbols, msg) = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        snapshots.extend(symbols);
+        if let Ok(BinanceMessage::Depth(update)) = msg {
+            forwarded.push(update.final_update_id);
+        }
+    }
+    assert_eq!(forwarded, vec![11, 13, 15]);
+    // one snapshot for both legs
+    assert_eq!(snapshots, vec!["BTCUSDT".to_string()]);
+    assert_eq!(server.rest_requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_redundant_feed_survives_stalled_leg() {
+    let update = |u: u64| mock::bybit::orderbook("BTCUSDT", "delta", u, &[(100.0, 1.0)], &[]);
+    let server = MockExchange::new(Dialect::Bybit)
+        // leg A stalls after the first update
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update(2)),
+            Step::Sleep(Duration::from_secs(60)),
+        ])
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update(2)),
+            Step::Send(update(3)),
+            Step::Send(update(4)),
+        ])
+        .start()
+        .await;
+
+    let client = WebSocketClient::new()
+        .with_endpoints(Endpoints::new().with(Exchange::BYBIT, server.endpoint()));
+    let feed = RedundantFeed::connect(Exchange::BYBIT, client)
+        .await
+        .unwrap();
+    let ack = feed
+        .subscribe(
+            BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+            vec!["BTC-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(feed.listen_with(Collector(tx)));
+    ack.await.unwrap();
+
+    let mut forwarded = Vec::new();
+    while forwarded.len() < 3 {
+        let msg = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if let Ok(BybitMessage::Orderbook(update)) = msg {
+            forwarded.push(update.data.seq.unwrap() - 1000);
+        }
+    }
+    assert_eq!(forwarded, vec![2, 3, 4]);
+    assert!(timeout(Duration::from_millis(200), async {
+        while let Some(msg) = rx.recv().await {
+            if let Ok(BybitMessage::Orderbook(_)) = msg {
+                return;
+            }
+        }
+    })
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_redundant_feed_forwards_each_unsubscription_once() {
+    let server = MockExchange::new(Dialect::Bybit)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Sleep(Duration::from_secs(60)),
+        ])
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Sleep(Duration::from_secs(60)),
+        ])
+        .start()
+        .await;
+
+    let client = WebSocketClient::new()
+        .with_endpoints(Endpoints::new().with(Exchange::BYBIT, server.endpoint()));
+    let feed = RedundantFeed::connect(Exchange::BYBIT, client)
+        .await
+        .unwrap();
+    let channel = BybitChannel::ORDERBOOK(Depth::FIFTY).to_string();
+    let (x, y) = ("BTC-USDT".to_string(), "ETH-USDT".to_string());
+    feed.subscribe(channel.clone(), vec![x.clone(), y.clone()])
+        .await
+        .unwrap();
+    let legs = feed.legs().clone();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(feed.listen_with::<BybitMessage, _>(Unsubscriptions(tx)));
+
+    // the legs' unsubscriptions interleave
+    for leg in &legs {
+        for topic in [&x, &y] {
+            leg.unsubscribe(channel.clone(), vec![topic.clone()])
+                .await
+                .unwrap();
+        }
+    }
+    let mut forwarded = Vec::new();
+    while forwarded.len() < 2 {
+        let (_, topics) = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        forwarded.extend(topics);
+    }
+    forwarded.sort();
+    assert_eq!(forwarded, vec![x.clone(), y.clone()]);
+    assert!(timeout(Duration::from_millis(200), rx.recv()).await.is_err());
+
+    // a topic subscribed to again is forwarded once more when unsubscribed
+    for leg in &legs {
+        leg.subscribe(channel.clone(), vec![x.clone()])
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    for leg in &legs {
+        leg.unsubscribe(channel.clone(), vec![x.clone()])
+            .await
+            .unwrap();
+    }
+    let unsubscribed = timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unsubscribed, (channel, vec![x]));
+    assert!(timeout(Duration::from_millis(200), rx.recv()).await.is_err());
+}
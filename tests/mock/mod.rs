@@ -0,0 +1,678 @@
+//! Local stand-in for the exchanges: a websocket server speaking the Binance, Okx, Bybit or
+//! Coinbase dialect plus an HTTP server for REST snapshots, so clients and managers can be
+//! tested without connectivity
+#![allow(dead_code)]
+
+use async_wss::config::Endpoint;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Binance,
+    Okx,
+    Bybit,
+    Coinbase,
+}
+
+/// A step of the script a connection plays once it is accepted
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// waits until the client has sent this many subscriptions on the connection
+    AwaitSubscriptions(usize),
+    /// sends a text frame as is
+    Send(String),
+    /// sends a binary frame as is
+    SendBinary(Vec<u8>),
+    Sleep(Duration),
+    /// closes the connection with a Close frame
+    Close(u16, String),
+    /// drops the connection without a Close frame
+    Disconnect,
+}
+
+#[derive(Default)]
+struct State {
+    /// text frames received from clients, across connections
+    requests: Vec<String>,
+    /// path and query of every REST request
+    rest_requests: Vec<String>,
+    /// (path and query prefix, bodies served in order, the last one repeatedly)
+    routes: Vec<(String, Vec<String>)>,
+    /// topics whose subscription is rejected
+    rejected: Vec<String>,
+    connections: usize,
+}
+
+pub struct MockExchange {
+    dialect: Dialect,
+    scripts: Vec<Vec<Step>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockExchange {
+    pub fn new(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            scripts: Vec::new(),
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Adds the script of the next connection. Connections without a script only answer
+    /// requests
+    pub fn script(mut self, steps: Vec<Step>) -> Self {
+        self.scripts.push(steps);
+        self
+    }
+
+    /// Serves `body` to REST requests starting with `path`. Several bodies for the same
+    /// path are served in order, the last one repeatedly
+    pub fn rest(self, path: &str, body: String) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            match state.routes.iter_mut().find(|(p, _)| p == path) {
+                Some((_, bodies)) => bodies.push(body),
+                None => state.routes.push((path.to_string(), vec![body])),
+            }
+        }
+        self
+    }
+
+    /// Rejects subscriptions to `topic` (in the client's `X-Y` format)
+    pub fn reject(self, topic: &str) -> Self {
+        self.state.lock().unwrap().rejected.push(topic.to_string());
+        self
+    }
+
+    pub async fn start(self) -> MockServer {
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_addr = ws_listener.local_addr().unwrap();
+        let http_addr = http_listener.local_addr().unwrap();
+
+        let ws_task = tokio::spawn(serve_ws(
+            ws_listener,
+            self.dialect,
+            self.scripts,
+            self.state.clone(),
+        ));
+        let http_task = tokio::spawn(serve_http(http_listener, self.state.clone()));
+
+        MockServer {
+            ws_addr,
+            http_addr,
+            state: self.state,
+            tasks: vec![ws_task, http_task],
+        }
+    }
+}
+
+pub struct MockServer {
+    ws_addr: SocketAddr,
+    http_addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn endpoint(&self) -> Endpoint {
+        Endpoint::new(
+            format!("ws://{}", self.ws_addr),
+            format!("http://{}", self.http_addr),
+        )
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn rest_requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().rest_requests.clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve_ws(
+    listener: TcpListener,
+    dialect: Dialect,
+    scripts: Vec<Vec<Step>>,
+    state: Arc<Mutex<State>>,
+) {
+    let mut scripts = scripts.into_iter();
+    while let Ok((stream, _)) = listener.accept().await {
+        state.lock().unwrap().connections += 1;
+        let script = scripts.next().unwrap_or_default();
+        tokio::spawn(serve_connection(stream, dialect, script, state.clone()));
+    }
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    dialect: Dialect,
+    script: Vec<Step>,
+    state: Arc<Mutex<State>>,
+) {
+    let Ok(socket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let (mut sink, mut stream) = socket.split();
+    // `None` makes the writer drop its half of the socket once earlier frames are sent
+    let (tx, mut rx) = mpsc::unbounded_channel::<Option<Message>>();
+    let subscribed = Arc::new(Notify::new());
+    let subscriptions = Arc::new(Mutex::new(0));
+
+    let writer = tokio::spawn(async move {
+        while let Some(Some(msg)) = rx.recv().await {
+            let is_close = msg.is_close();
+            if sink.send(msg).await.is_err() || is_close {
+                break;
+            }
+        }
+    });
+
+    let reader = {
+        let tx = tx.clone();
+        let subscribed = subscribed.clone();
+        let subscriptions = subscriptions.clone();
+        tokio::spawn(async move {
+            let mut next_seq = 0;
+            while let Some(Ok(msg)) = stream.next().await {
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+                state.lock().unwrap().requests.push(text.clone());
+                let rejected = state.lock().unwrap().rejected.clone();
+                let (replies, is_subscribe) = respond(dialect, &text, &rejected, &mut next_seq);
+                for reply in replies {
+                    let _ = tx.send(Some(Message::text(reply)));
+                }
+                if is_subscribe {
+                    *subscriptions.lock().unwrap() += 1;
+                    subscribed.notify_waiters();
+                }
+            }
+        })
+    };
+
+    for step in script {
+        match step {
+            Step::AwaitSubscriptions(n) => loop {
+                let notified = subscribed.notified();
+                if *subscriptions.lock().unwrap() >= n {
+                    break;
+                }
+                notified.await;
+            },
+            Step::Send(text) => {
+                let _ = tx.send(Some(Message::text(text)));
+            }
+            Step::SendBinary(bytes) => {
+                let _ = tx.send(Some(Message::binary(bytes)));
+            }
+            Step::Sleep(duration) => tokio::time::sleep(duration).await,
+            Step::Close(code, reason) => {
+                let _ = tx.send(Some(Message::Close(Some(CloseFrame {
+                    code: CloseCode::from(code),
+                    reason: reason.into(),
+                }))));
+                let _ = writer.await;
+                reader.abort();
+                return;
+            }
+            Step::Disconnect => {
+                let _ = tx.send(None);
+                let _ = writer.await;
+                reader.abort();
+                return;
+            }
+        }
+    }
+
+    // past its script the connection keeps answering until the client leaves
+    let _ = reader.await;
+    writer.abort();
+}
+
+/// Builds the replies to a client's request. The flag tells whether it was a subscription
+fn respond(
+    dialect: Dialect,
+    text: &str,
+    rejected: &[String],
+    next_seq: &mut u64,
+) -> (Vec<String>, bool) {
+    let Ok(req) = serde_json::from_str::<Value>(text) else {
+        // Okx pings in plain text
+        return match text {
+            "ping" => (vec!["pong".to_string()], false),
+            _ => (vec![], false),
+        };
+    };
+    match dialect {
+        Dialect::Binance => {
+            let method = req["method"].as_str().unwrap_or_default();
+            let params = req["params"].as_array().cloned().unwrap_or_default();
+            let rejected = params.iter().any(|p| {
+                let p = p.as_str().unwrap_or_default();
+                rejected
+                    .iter()
+                    .any(|t| p.starts_with(&t.replace('-', "").to_lowercase()))
+            });
+            let reply = if rejected {
+                json!({"error": {"code": 2, "msg": "Invalid request"}, "id": req["id"]})
+            } else {
+                json!({"result": null, "id": req["id"]})
+            };
+            (vec![reply.to_string()], method == "SUBSCRIBE")
+        }
+        Dialect::Okx => {
+            let op = req["op"].as_str().unwrap_or_default().to_string();
+            let args = req["args"].as_array().cloned().unwrap_or_default();
+            let mut replies = Vec::new();
+            for arg in args {
+                let inst_id = arg["instId"].as_str().unwrap_or_default();
+                if rejected.iter().any(|t| t == inst_id) {
+                    replies.push(
+                        json!({
+                            "event": "error",
+                            "code": "60018",
+                            "msg": format!("Wrong URL or channel:{}, instId:{} doesn't exist.", arg["channel"], inst_id),
+                            "connId": "mock"
+                        })
+                        .to_string(),
+                    );
+                } else {
+                    replies.push(json!({"event": op, "arg": arg, "connId": "mock"}).to_string());
+                }
+            }
+            (replies, op == "subscribe")
+        }
+        Dialect::Bybit => {
+            let op = req["op"].as_str().unwrap_or_default().to_string();
+            if op == "ping" {
+                let reply = json!({
+                    "success": true, "ret_msg": "pong", "conn_id": "mock", "op": "ping"
+                });
+                return (vec![reply.to_string()], false);
+            }
+            let args = req["args"].as_array().cloned().unwrap_or_default();
+            let rejected = args.iter().any(|a| {
+                let a = a.as_str().unwrap_or_default();
+                rejected.iter().any(|t| a.ends_with(&t.replace('-', "")))
+            });
+            let reply = json!({
+                "success": !rejected,
+                "ret_msg": if rejected { "error:handler not found" } else { "" },
+                "conn_id": "mock",
+                "req_id": req["req_id"],
+                "op": op
+            });
+            (vec![reply.to_string()], op == "subscribe")
+        }
+        Dialect::Coinbase => {
+            let kind = req["type"].as_str().unwrap_or_default().to_string();
+            let products = req["product_ids"].as_array().cloned().unwrap_or_default();
+            let rejected = products
+                .iter()
+                .any(|p| rejected.iter().any(|t| p.as_str() == Some(t.as_str())));
+            if rejected {
+                let reply = json!({"type": "error", "message": "Failure to subscribe"});
+                return (vec![reply.to_string()], false);
+            }
+            let channel = req["channel"].as_str().unwrap_or_default();
+            let reply = json!({
+                "channel": "subscriptions",
+                "client_id": "",
+                "timestamp": "2023-02-09T20:33:57.609931463Z",
+                "sequence_num": *next_seq,
+                "events": [{"subscriptions": {channel: products}}]
+            });
+            *next_seq += 1;
+            (vec![reply.to_string()], kind == "subscribe")
+        }
+    }
+}
+
+async fn serve_http(listener: TcpListener, state: Arc<Mutex<State>>) {
+    while let Ok((mut stream, _)) = listener.accept().await {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                match stream.read(&mut chunk).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&buf);
+            let target = request
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+
+            let body = {
+                let mut state = state.lock().unwrap();
+                state.rest_requests.push(target.clone());
+                state
+                    .routes
+                    .iter_mut()
+                    .find(|(path, _)| target.starts_with(path.as_str()))
+                    .map(|(_, bodies)| {
+                        if bodies.len() > 1 {
+                            bodies.remove(0)
+                        } else {
+                            bodies[0].clone()
+                        }
+                    })
+            };
+            let response = match body {
+                Some(body) => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
+
+/// Formats `levels` as the exchanges do: arrays of decimal strings
+fn levels(levels: &[(f64, f64)]) -> Vec<Value> {
+    levels
+        .iter()
+        .map(|(price, qty)| json!([price.to_string(), qty.to_string()]))
+        .collect()
+}
+
+/// Okx levels also carry the deprecated liquidated orders and the order count
+fn okx_levels(l: &[(f64, f64)]) -> Vec<Value> {
+    levels(l)
+        .into_iter()
+        .map(|mut v| {
+            let arr = v.as_array_mut().unwrap();
+            arr.push(Value::from("0"));
+            arr.push(Value::from("1"));
+            v
+        })
+        .collect()
+}
+
+pub mod binance {
+    use super::levels;
+    use serde_json::json;
+
+    /// `symbol` as Binance writes it, e.g. `BTCUSDT`
+    pub fn depth_update(
+        symbol: &str,
+        first_update_id: u64,
+        final_update_id: u64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+    ) -> String {
+        json!({
+            "e": "depthUpdate",
+            "E": 1672515782136u64,
+            "s": symbol,
+            "U": first_update_id,
+            "u": final_update_id,
+            "b": levels(bids),
+            "a": levels(asks)
+        })
+        .to_string()
+    }
+
+    /// Futures diff depth event, chained to the previous one by `pu`
+    pub fn futures_depth_update(
+        symbol: &str,
+        first_update_id: u64,
+        final_update_id: u64,
+        previous_final_update_id: u64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+    ) -> String {
+        json!({
+            "e": "depthUpdate",
+            "E": 1672515782136u64,
+            "T": 1672515782134u64,
+            "s": symbol,
+            "U": first_update_id,
+            "u": final_update_id,
+            "pu": previous_final_update_id,
+            "b": levels(bids),
+            "a": levels(asks)
+        })
+        .to_string()
+    }
+
+    /// REST depth snapshot body
+    pub fn depth_snapshot(last_update_id: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> String {
+        json!({
+            "lastUpdateId": last_update_id,
+            "bids": levels(bids),
+            "asks": levels(asks)
+        })
+        .to_string()
+    }
+}
+
+pub mod okx {
+    use super::okx_levels;
+    use serde_json::json;
+
+    /// Okx's checksum of a whole book, bids best first, asks best first
+    pub fn checksum(bids: &[(f64, f64)], asks: &[(f64, f64)]) -> i64 {
+        let mut parts = Vec::new();
+        for i in 0..25 {
+            for side in [bids, asks] {
+                if let Some((price, qty)) = side.get(i) {
+                    parts.push(format!("{}:{}", price, qty));
+                }
+            }
+        }
+        crc32fast::hash(parts.join(":").as_bytes()) as i32 as i64
+    }
+
+    /// `action` is `snapshot` or `update`. A wrong `checksum` simulates a corrupted book
+    pub fn books(
+        inst_id: &str,
+        action: &str,
+        prev_seq_id: i64,
+        seq_id: i64,
+        checksum: i64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+    ) -> String {
+        json!({
+            "arg": {"channel": "books", "instId": inst_id},
+            "action": action,
+            "data": [{
+                "asks": okx_levels(asks),
+                "bids": okx_levels(bids),
+                "ts": "1597026383085",
+                "checksum": checksum,
+                "prevSeqId": prev_seq_id,
+                "seqId": seq_id
+            }]
+        })
+        .to_string()
+    }
+
+    /// A push of `books5` or `bbo-tbt`, always the whole book and without a checksum
+    pub fn full_book(
+        channel: &str,
+        inst_id: &str,
+        seq_id: i64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+    ) -> String {
+        json!({
+            "arg": {"channel": channel, "instId": inst_id},
+            "data": [{
+                "asks": okx_levels(asks),
+                "bids": okx_levels(bids),
+                "instId": inst_id,
+                "ts": "1597026383085",
+                "seqId": seq_id
+            }]
+        })
+        .to_string()
+    }
+
+    /// `side` is the taker's, `buy` or `sell`
+    pub fn trades(inst_id: &str, trade_id: u64, px: f64, sz: f64, side: &str) -> String {
+        json!({
+            "arg": {"channel": "trades", "instId": inst_id},
+            "data": [{
+                "instId": inst_id,
+                "tradeId": trade_id.to_string(),
+                "px": px.to_string(),
+                "sz": sz.to_string(),
+                "side": side,
+                "ts": "1630048897897",
+                "count": "1"
+            }]
+        })
+        .to_string()
+    }
+}
+
+pub mod bybit {
+    use super::levels;
+    use serde_json::json;
+
+    /// `symbol` as Bybit writes it, e.g. `BTCUSDT`. `kind` is `snapshot` or `delta`
+    pub fn orderbook(
+        symbol: &str,
+        kind: &str,
+        update_id: u64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+    ) -> String {
+        json!({
+            "topic": format!("orderbook.50.{}", symbol),
+            "type": kind,
+            "ts": 1672304484978u64,
+            "data": {
+                "s": symbol,
+                "b": levels(bids),
+                "a": levels(asks),
+                "u": update_id,
+                "seq": update_id + 1000
+            },
+            "cts": 1672304484976u64
+        })
+        .to_string()
+    }
+
+    /// `side` is the taker's, `Buy` or `Sell`
+    pub fn public_trade(symbol: &str, id: &str, price: f64, size: f64, side: &str) -> String {
+        json!({
+            "topic": format!("publicTrade.{}", symbol),
+            "type": "snapshot",
+            "ts": 1672304486868u64,
+            "data": [{
+                "T": 1672304486865u64,
+                "s": symbol,
+                "S": side,
+                "v": size.to_string(),
+                "p": price.to_string(),
+                "L": "PlusTick",
+                "i": id,
+                "BT": false,
+                "seq": 1783284617u64
+            }]
+        })
+        .to_string()
+    }
+
+    /// `kind` is `snapshot` or `delta`. `fields` are the ticker's, e.g. `lastPrice`
+    pub fn tickers(symbol: &str, kind: &str, fields: &[(&str, &str)]) -> String {
+        let mut data = json!({ "symbol": symbol });
+        for (name, value) in fields {
+            data[*name] = json!(value);
+        }
+        json!({
+            "topic": format!("tickers.{}", symbol),
+            "type": kind,
+            "data": data,
+            "cs": 24987956059u64,
+            "ts": 1673272861686u64
+        })
+        .to_string()
+    }
+}
+
+pub mod coinbase {
+    use serde_json::{json, Value};
+
+    /// `kind` is `snapshot` or `update`. Levels are `(side, price, qty)`
+    pub fn level2(
+        product_id: &str,
+        kind: &str,
+        sequence_num: u64,
+        updates: &[(&str, f64, f64)],
+    ) -> String {
+        level2_at(
+            product_id,
+            kind,
+            sequence_num,
+            "1970-01-01T00:00:00Z",
+            updates,
+        )
+    }
+
+    /// [`level2`] whose levels are stamped with `event_time`, as updates are
+    pub fn level2_at(
+        product_id: &str,
+        kind: &str,
+        sequence_num: u64,
+        event_time: &str,
+        updates: &[(&str, f64, f64)],
+    ) -> String {
+        let updates = updates
+            .iter()
+            .map(|(side, price, qty)| {
+                json!({
+                    "side": side,
+                    "event_time": event_time,
+                    "price_level": price.to_string(),
+                    "new_quantity": qty.to_string()
+                })
+            })
+            .collect::<Vec<Value>>();
+        json!({
+            "channel": "l2_data",
+            "client_id": "",
+            "timestamp": "2023-02-09T20:32:50.714964855Z",
+            "sequence_num": sequence_num,
+            "events": [{"type": kind, "product_id": product_id, "updates": updates}]
+        })
+        .to_string()
+    }
+}
@@ -0,0 +1,1507 @@
+mod mock;
+
+use async_wss::aggregator::Transmitor;
+use async_wss::binance::{
+    Binance, Channel as BinanceChannel, DepthMessage, DepthSnapshot, Manager as BinanceManager, Market,
+    Message as BinanceMessage, RequestError,
+};
+use async_wss::bybit::{
+    Category, Channel as BybitChannel, Depth, Manager as BybitManager, Message as BybitMessage,
+};
+use async_wss::coinbase::{Channel as CoinbaseChannel, Manager as CoinbaseManager};
+use async_wss::config::{CoinbaseAuth, CoinbaseCredentials, Endpoints};
+use async_wss::flatbuffer::{
+    make_sequenced_update_event, make_ticker_event, make_trade_event, root_as_ticker_event_message,
+    root_as_trade_event_message, root_as_update_event_message,
+};
+use async_wss::lifecycle::ConnectionState;
+use async_wss::okx::{
+    Channel as OkxChannel, InstType, Manager as OkxManager, Message as OkxMessage, Okx,
+};
+use async_wss::pool::WebSocketPool;
+use async_wss::utils::{
+    CcyPair, Exchange, Origin, Result, Symbol, TorrentError, Trade, ASSET_CONSTANT_MULTIPLIER,
+};
+use async_wss::websocket::{
+    Acknowledgement, Decode, DepthCallback, MessageCallback, Pipeline, RateLimit, WebSocketClient,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mock::{Dialect, MockExchange, MockServer, Step};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::elliptic_curve::rand_core::OsRng;
+use p256::pkcs8::LineEnding;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+/// Forwards every message to the test
+struct Collector<T>(mpsc::UnboundedSender<Result<T>>);
+
+impl<T: Decode> MessageCallback<T> for Collector<T> {
+    fn message_callback(&mut self, msg: Result<T::Frame<'_>>) -> Result<()> {
+        let _ = self.0.send(msg.map(T::into_owned));
+        Ok(())
+    }
+}
+
+fn client(exchange: Exchange, server: &MockServer) -> WebSocketClient {
+    WebSocketClient::new().with_endpoints(Endpoints::new().with(exchange, server.endpoint()))
+}
+
+fn coinbase_client(server: &MockServer) -> WebSocketClient {
+    client(Exchange::COINBASE, server).with_coinbase_credentials(CoinbaseCredentials::new(
+        "mock",
+        "mock",
+        CoinbaseAuth::Legacy,
+    ))
+}
+
+async fn recv<T>(rx: &mut mpsc::UnboundedReceiver<Result<T>>) -> Result<T> {
+    timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no message from the mock exchange")
+        .expect("listener stopped")
+}
+
+#[tokio::test]
+async fn test_bybit_orderbook_stream() {
+    let server = MockExchange::new(Dialect::Bybit)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(mock::bybit::orderbook(
+                "BTCUSDT",
+                "snapshot",
+                1,
+                &[(100.0, 1.0)],
+                &[(101.0, 2.0)],
+            )),
+            Step::Send(mock::bybit::orderbook(
+                "BTCUSDT",
+                "delta",
+                2,
+                &[(100.0, 0.0)],
+                &[],
+            )),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BYBIT, &server);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    let ack = wss
+        .subscribe(
+            BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+            vec!["BTC-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+    ack.await.unwrap();
+
+    assert!(matches!(
+        recv(&mut rx).await,
+        Ok(BybitMessage::Subscribe(_))
+    ));
+    match recv(&mut rx).await {
+        Ok(BybitMessage::Orderbook(update)) => {
+            assert_eq!(update.r#type, "snapshot");
+            assert_eq!(update.data.s, "BTCUSDT");
+        }
+        m => panic!("expected a snapshot, got {:?}", m),
+    }
+    match recv(&mut rx).await {
+        Ok(BybitMessage::Orderbook(update)) => assert_eq!(update.r#type, "delta"),
+        m => panic!("expected a delta, got {:?}", m),
+    }
+}
+
+#[tokio::test]
+async fn test_bybit_resyncs_on_update_gap() {
+    let bids = [(100.0, 1.0)];
+    let asks = [(101.0, 2.0)];
+    let server = MockExchange::new(Dialect::Bybit)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(mock::bybit::orderbook(
+                "BTCUSDT", "snapshot", 5, &bids, &asks,
+            )),
+            Step::Send(mock::bybit::orderbook("BTCUSDT", "delta", 6, &bids, &[])),
+            Step::Sleep(Duration::from_millis(500)),
+            // 7 is missed
+            Step::Send(mock::bybit::orderbook("BTCUSDT", "delta", 8, &bids, &[])),
+            Step::AwaitSubscriptions(2),
+            // after a service restart, the snapshot comes as a delta with u = 1
+            Step::Send(mock::bybit::orderbook("BTCUSDT", "delta", 1, &bids, &asks)),
+            Step::Send(mock::bybit::orderbook("BTCUSDT", "delta", 2, &bids, &[])),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BYBIT, &server);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    let manager = BybitManager::new().with_client(wss.clone());
+    let channel = BybitChannel::ORDERBOOK(Depth::FIFTY).to_string();
+    let ack = wss
+        .subscribe(channel.clone(), vec!["BTC-USDT".to_string()])
+        .await
+        .unwrap();
+    tokio::spawn(WebSocketClient::listen_with(reader, manager));
+    ack.await.unwrap();
+
+    // the snapshot and the next delta are in sequence
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(server.requests().len(), 1);
+
+    let requests = timeout(Duration::from_secs(5), async {
+        while server.requests().len() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        server.requests()
+    })
+    .await
+    .expect("the manager didn't resync");
+    let request = serde_json::from_str::<serde_json::Value>(&requests[2]).unwrap();
+    assert_eq!(request["op"], "subscribe");
+    assert_eq!(request["args"][0], "orderbook.50.BTCUSDT");
+
+    // u = 1 restarts the book, so 2 doesn't resync again
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(server.requests().len(), 3);
+    assert_eq!(
+        wss.subscriptions().get(&channel),
+        Some(&vec!["BTC-USDT".to_string()])
+    );
+
+    // Bybit's cross sequence and matching engine time travel with the event
+    let event = make_sequenced_update_event(
+        vec![],
+        vec![],
+        CcyPair {
+            base: "BTC".to_string(),
+            quote: "USDT".to_string(),
+            product: "spot".to_string(),
+        },
+        Exchange::BYBIT,
+        Origin {
+            sequence: 1002,
+            timestamp: 1672304484976000,
+        },
+    )
+    .unwrap();
+    let event = root_as_update_event_message(&event.buff)
+        .unwrap()
+        .update_event()
+        .unwrap();
+    assert_eq!(event.sequence(), 1002);
+    assert_eq!(event.exchange_timestamp(), 1672304484976000);
+}
+
+#[test]
+fn test_bybit_categories() {
+    let product = |category: Category, symbol: &str| {
+        BybitManager::new()
+            .with_category(category)
+            .resolve_symbol(&symbol.to_string())
+            .map(|ccy_pair| ccy_pair.to_string())
+    };
+    // the same symbol is a spot pair or a perpetual depending on the endpoint
+    assert_eq!(
+        product(Category::SPOT, "BTCUSDT"),
+        Some("BTC-USDT-spot".to_string())
+    );
+    assert_eq!(
+        product(Category::LINEAR, "BTCUSDT"),
+        Some("BTC-USDT-swap".to_string())
+    );
+    assert_eq!(
+        product(Category::LINEAR, "BTCPERP"),
+        Some("BTC-USDC-swap".to_string())
+    );
+    assert_eq!(
+        product(Category::LINEAR, "BTC-29DEC23"),
+        Some("BTC-USDC-futures-231229".to_string())
+    );
+    assert_eq!(
+        product(Category::LINEAR, "ETHUSDT-27DEC24"),
+        Some("ETH-USDT-futures-241227".to_string())
+    );
+    assert_eq!(
+        product(Category::INVERSE, "BTCUSD"),
+        Some("BTC-USD-swap".to_string())
+    );
+    assert_eq!(
+        product(Category::INVERSE, "BTCUSDH24"),
+        Some("BTC-USD-futures-240329".to_string())
+    );
+    assert_eq!(
+        product(Category::INVERSE, "ETHUSDZ24"),
+        Some("ETH-USD-futures-241227".to_string())
+    );
+    assert_eq!(
+        product(Category::INVERSE, "BTCUSDM24"),
+        Some("BTC-USD-futures-240628".to_string())
+    );
+    assert_eq!(
+        product(Category::OPTION, "BTC-29DEC23-40000-C"),
+        Some("BTC-USDC-option-231229-40000-C".to_string())
+    );
+    assert_eq!(product(Category::LINEAR, "BTC"), None);
+    assert_eq!(product(Category::OPTION, "BTCUSDT"), None);
+    assert_eq!(
+        Category::INVERSE.url(),
+        "wss://stream.bybit.com/v5/public/inverse"
+    );
+}
+
+#[test]
+fn test_bybit_trades_and_tickers() {
+    let frame = mock::bybit::public_trade(
+        "BTCUSDT",
+        "20f43950-d8dd-5b31-9112-a178eb6023af",
+        16578.5,
+        0.001,
+        "Buy",
+    );
+    match serde_json::from_str(&frame) {
+        Ok(BybitMessage::Trades(update)) => {
+            let trade = &update.data[0];
+            assert_eq!(trade.i, "20f43950-d8dd-5b31-9112-a178eb6023af");
+            assert_eq!((trade.p, trade.v), (16578.5, 0.001));
+            assert_eq!(trade.side, "Buy");
+            assert_eq!(trade.t, 1672304486865);
+        }
+        m => panic!("expected trades, got {:?}", m),
+    }
+
+    // linear tickers are a snapshot followed by deltas of the changed fields
+    let mut manager = BybitManager::new().with_category(Category::LINEAR);
+    let symbol = "BTCUSDT".to_string();
+    for frame in [
+        mock::bybit::tickers(
+            "BTCUSDT",
+            "snapshot",
+            &[
+                ("lastPrice", "17216.00"),
+                ("markPrice", "17217.33"),
+                ("volume24h", "91705.276"),
+                ("fundingRate", "-0.000212"),
+            ],
+        ),
+        mock::bybit::tickers("BTCUSDT", "delta", &[("lastPrice", "17220.50")]),
+    ] {
+        let msg = serde_json::from_str::<BybitMessage>(&frame).unwrap();
+        manager.message_callback(Ok(msg)).unwrap();
+    }
+    let ticker = manager.ticker(&symbol, 1673272861686).unwrap();
+    assert_eq!(ticker.last_price, 172205000000000);
+    assert_eq!(ticker.mark_price, Some(172173300000000));
+    assert_eq!(ticker.volume_24h, 917052760000000);
+    assert_eq!(ticker.funding_rate, Some(-0.000212));
+
+    // futures have no funding rate, and inverse volumes are taken from the coin turnover
+    let mut manager = BybitManager::new().with_category(Category::INVERSE);
+    let frame = mock::bybit::tickers(
+        "BTCUSDH24",
+        "snapshot",
+        &[
+            ("lastPrice", "40000"),
+            ("volume24h", "80000"),
+            ("turnover24h", "2"),
+            ("fundingRate", ""),
+        ],
+    );
+    let msg = serde_json::from_str::<BybitMessage>(&frame).unwrap();
+    manager.message_callback(Ok(msg)).unwrap();
+    let ticker = manager.ticker(&"BTCUSDH24".to_string(), 0).unwrap();
+    assert_eq!(ticker.volume_24h, 20000000000);
+    assert_eq!(ticker.funding_rate, None);
+
+    let event = make_ticker_event(
+        manager
+            .ticker(&"BTCUSDH24".to_string(), 1673272861686)
+            .unwrap(),
+        manager.resolve_symbol(&"BTCUSDH24".to_string()).unwrap(),
+        Exchange::BYBIT,
+    )
+    .unwrap();
+    let event = root_as_ticker_event_message(&event.buff)
+        .unwrap()
+        .ticker_event()
+        .unwrap();
+    assert_eq!(event.instrument(), Some("BTC-USD-futures-240329"));
+    assert_eq!(event.last_price(), 400000000000000);
+    assert_eq!(event.mark_price(), 0);
+    assert_eq!(event.exchange_timestamp(), 1673272861686000);
+
+    // symbols the category can't resolve are reported rather than published
+    let mut manager = BybitManager::new().with_category(Category::LINEAR);
+    for frame in [
+        mock::bybit::public_trade("ETH", "1", 1000.0, 1.0, "Sell"),
+        mock::bybit::tickers("ETH", "snapshot", &[("lastPrice", "1000")]),
+        mock::bybit::orderbook("ETH", "snapshot", 1, &[(1000.0, 1.0)], &[]),
+    ] {
+        let msg = serde_json::from_str::<BybitMessage>(&frame).unwrap();
+        assert!(matches!(
+            manager.message_callback(Ok(msg)),
+            Err(TorrentError::BadParse(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_okx_rejected_subscription() {
+    let server = MockExchange::new(Dialect::Okx)
+        .reject("ETH-USDT")
+        .start()
+        .await;
+
+    let wss = client(Exchange::OKX, &server);
+    let reader = wss.connect(Exchange::OKX).await.unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel::<Result<OkxMessage>>();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+
+    let ack = wss
+        .subscribe(OkxChannel::BOOKS.to_string(), vec!["BTC-USDT".to_string()])
+        .await
+        .unwrap();
+    ack.await.unwrap();
+
+    let ack = wss
+        .subscribe(
+            OkxChannel::BOOKS.to_string(),
+            vec!["SOL-USDT".to_string(), "ETH-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    assert!(matches!(ack.await, Err(TorrentError::BadSubscription(_))));
+}
+
+#[tokio::test]
+async fn test_okx_resubscription_is_acknowledged() {
+    let server = MockExchange::new(Dialect::Okx)
+        .reject("ETH-USDT")
+        .start()
+        .await;
+
+    let wss = client(Exchange::OKX, &server);
+    let reader = wss.connect(Exchange::OKX).await.unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel::<Result<OkxMessage>>();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+
+    let books = OkxChannel::BOOKS.to_string();
+    let ack = wss
+        .subscribe(books.clone(), vec!["BTC-USDT".to_string()])
+        .await
+        .unwrap();
+    ack.await.unwrap();
+
+    // the unsubscribe and the subscribe are acknowledged for the same arg
+    let ack = wss
+        .resubscribe(books.clone(), vec!["BTC-USDT".to_string()])
+        .unwrap();
+    ack.await.unwrap();
+    assert_eq!(server.requests().len(), 3);
+
+    // nothing is left pending to take the blame for a later rejection
+    let ack = wss
+        .subscribe(books.clone(), vec!["ETH-USDT".to_string()])
+        .await
+        .unwrap();
+    assert!(matches!(ack.await, Err(TorrentError::BadSubscription(_))));
+    let ack = wss
+        .subscribe(books, vec!["SOL-USDT".to_string()])
+        .await
+        .unwrap();
+    ack.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_replays_subscriptions() {
+    let snapshot = mock::bybit::orderbook("BTCUSDT", "snapshot", 1, &[(100.0, 1.0)], &[]);
+    let server = MockExchange::new(Dialect::Bybit)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(snapshot.clone()),
+            Step::Disconnect,
+        ])
+        .script(vec![Step::AwaitSubscriptions(1), Step::Send(snapshot)])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BYBIT, &server);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    let channel = BybitChannel::ORDERBOOK(Depth::FIFTY).to_string();
+    let ack = wss
+        .subscribe(channel, vec!["BTC-USDT".to_string()])
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(wss.clone().listen_with_reconnect(reader, Collector(tx)));
+    ack.await.unwrap();
+
+    let mut snapshots = 0;
+    while snapshots < 2 {
+        if let Ok(BybitMessage::Orderbook(_)) = recv(&mut rx).await {
+            snapshots += 1;
+        }
+    }
+    assert_eq!(server.connections(), 2);
+    let subscriptions = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.contains(r#""op":"subscribe""#))
+        .count();
+    assert_eq!(subscriptions, 2);
+}
+
+/// Forwards depth messages along with the snapshots handed over by the `DepthManager`
+struct DepthCollector(mpsc::UnboundedSender<(Option<Vec<Symbol>>, Result<BinanceMessage<'static>>)>);
+
+impl DepthCallback<BinanceMessage<'static>, DepthSnapshot> for DepthCollector {
+    fn depth_callback(
+        &mut self,
+        msg: Result<BinanceMessage<'static>>,
+        snapshots_mp: Option<HashMap<Symbol, DepthSnapshot>>,
+    ) {
+        let symbols = snapshots_mp.map(|mp| mp.into_keys().collect());
+        let _ = self.0.send((symbols, msg));
+    }
+}
+
+#[tokio::test]
+async fn test_binance_depth_snapshot_over_rest() {
+    let server = MockExchange::new(Dialect::Binance)
+        .rest(
+            "/api/v3/depth",
+            mock::binance::depth_snapshot(10, &[(100.0, 1.0)], &[(101.0, 1.0)]),
+        )
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(mock::binance::depth_update(
+                "BTCUSDT",
+                9,
+                11,
+                &[(100.0, 2.0)],
+                &[],
+            )),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BINANCE, &server);
+    let reader = wss.connect(Exchange::BINANCE).await.unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let _listener = wss
+        .depth_subscribe::<_, RequestError, BinanceMessage, DepthSnapshot>(
+            reader,
+            vec!["BTC-USDT".to_string()],
+            DepthCollector(tx),
+        )
+        .await
+        .unwrap();
+
+    loop {
+        let (snapshots, msg) = timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if let Some(symbols) = snapshots {
+            assert_eq!(symbols, vec!["BTCUSDT".to_string()]);
+        }
+        if let Ok(BinanceMessage::Depth(update)) = msg {
+            assert_eq!(update.final_update_id, 11);
+            break;
+        }
+    }
+    assert_eq!(
+        server.rest_requests(),
+        vec!["/api/v3/depth?symbol=BTCUSDT&limit=5000".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn test_binance_resyncs_on_update_gap() {
+    let depth =
+        |first, last| mock::binance::depth_update("BTCUSDT", first, last, &[(100.0, 2.0)], &[]);
+    let server = MockExchange::new(Dialect::Binance)
+        .rest(
+            "/api/v3/depth",
+            mock::binance::depth_snapshot(10, &[(100.0, 1.0)], &[(101.0, 1.0)]),
+        )
+        .rest(
+            "/api/v3/depth",
+            mock::binance::depth_snapshot(16, &[(100.0, 1.0)], &[(101.0, 1.0)]),
+        )
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(depth(9, 11)),
+            Step::Send(depth(12, 13)),
+            // 14 is missed
+            Step::Send(depth(15, 16)),
+            Step::Send(depth(17, 19)),
+            Step::Sleep(Duration::from_millis(500)),
+            // the fresh snapshot comes with this one and the buffer is replayed on top of it
+            Step::Send(depth(20, 22)),
+            Step::Send(depth(23, 24)),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BINANCE, &server);
+    let reader = wss.connect(Exchange::BINANCE).await.unwrap();
+    let _listener = wss
+        .depth_subscribe::<_, RequestError, BinanceMessage, DepthSnapshot>(
+            reader,
+            vec!["BTC-USDT".to_string()],
+            BinanceManager::new(),
+        )
+        .await
+        .unwrap();
+
+    timeout(Duration::from_secs(5), async {
+        while server.rest_requests().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the manager didn't resync");
+    // the replay lines up with the second snapshot, so no third one is requested
+    tokio::time::sleep(Duration::from_millis(750)).await;
+    assert_eq!(
+        server.rest_requests(),
+        vec!["/api/v3/depth?symbol=BTCUSDT&limit=5000".to_string(); 2]
+    );
+
+    // the book is out of sync from the gap until the next snapshot lines up
+    let update = |first, last| {
+        let frame = depth(first, last);
+        let update: DepthMessage = serde_json::from_str(&frame).unwrap();
+        Ok(BinanceMessage::Depth(update.into_owned()))
+    };
+    let snapshot = |last_update_id| {
+        let body = mock::binance::depth_snapshot(last_update_id, &[(100.0, 1.0)], &[]);
+        let snapshot: DepthSnapshot = serde_json::from_str(&body).unwrap();
+        Some(HashMap::from([("BTCUSDT".to_string(), snapshot)]))
+    };
+    let mut manager = BinanceManager::new();
+    manager.depth_callback(update(9, 11), snapshot(10));
+    manager.depth_callback(update(12, 13), None);
+    assert_eq!(manager.last_update_id("BTCUSDT"), Some(13));
+    manager.depth_callback(update(15, 16), None);
+    manager.depth_callback(update(17, 19), None);
+    assert_eq!(manager.last_update_id("BTCUSDT"), None);
+    // a snapshot older than the buffer doesn't line up either
+    manager.depth_callback(update(20, 22), snapshot(13));
+    assert_eq!(manager.last_update_id("BTCUSDT"), None);
+    manager.depth_callback(update(23, 24), snapshot(18));
+    assert_eq!(manager.last_update_id("BTCUSDT"), Some(24));
+}
+
+#[tokio::test]
+async fn test_binance_futures_depth() {
+    let depth = |first, last, previous| {
+        mock::binance::futures_depth_update("BTCUSDT", first, last, previous, &[(100.0, 2.0)], &[])
+    };
+    let server = MockExchange::new(Dialect::Binance)
+        .rest(
+            "/fapi/v1/depth",
+            mock::binance::depth_snapshot(10, &[(100.0, 1.0)], &[(101.0, 1.0)]),
+        )
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            // the first event contains lastUpdateId, the next ones chain by pu
+            Step::Send(depth(9, 11, 8)),
+            Step::Send(depth(12, 15, 11)),
+            Step::Send(depth(16, 18, 15)),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BINANCE, &server).with_binance_market(Market::USD_M);
+    let reader = wss.connect(Exchange::BINANCE).await.unwrap();
+    let _listener = wss
+        .depth_subscribe::<_, RequestError, BinanceMessage, DepthSnapshot>(
+            reader,
+            vec!["BTC-USDT".to_string()],
+            BinanceManager::new().with_market(Market::USD_M),
+        )
+        .await
+        .unwrap();
+
+    // events chained by pu don't resync
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(
+        server.rest_requests(),
+        vec!["/fapi/v1/depth?symbol=BTCUSDT&limit=1000".to_string()]
+    );
+
+    let update = |first, last, previous| {
+        let frame = depth(first, last, previous);
+        let update: DepthMessage = serde_json::from_str(&frame).unwrap();
+        Ok(BinanceMessage::Depth(update.into_owned()))
+    };
+    let snapshot = |last_update_id| {
+        let body = mock::binance::depth_snapshot(last_update_id, &[(100.0, 1.0)], &[]);
+        let snapshot: DepthSnapshot = serde_json::from_str(&body).unwrap();
+        Some(HashMap::from([("BTCUSDT".to_string(), snapshot)]))
+    };
+    let mut manager = BinanceManager::new().with_market(Market::USD_M);
+    // ids don't have to be contiguous on futures
+    manager.depth_callback(update(9, 11, 8), snapshot(11));
+    manager.depth_callback(update(14, 15, 11), None);
+    assert_eq!(manager.last_update_id("BTCUSDT"), Some(15));
+    // pu doesn't match the previous u
+    manager.depth_callback(update(18, 19, 17), None);
+    assert_eq!(manager.last_update_id("BTCUSDT"), None);
+
+    // perpetuals and quarterlies are told apart from each other and from spot
+    let resolve = |market, symbol: &str| {
+        BinanceManager::new()
+            .with_market(market)
+            .resolve_symbol(&symbol.to_string())
+            .map(|pair| pair.to_string())
+    };
+    assert_eq!(resolve(Market::SPOT, "BTCUSDT").unwrap(), "BTC-USDT-spot");
+    assert_eq!(resolve(Market::USD_M, "BTCUSDT").unwrap(), "BTC-USDT-swap");
+    assert_eq!(
+        resolve(Market::USD_M, "ETHUSDT_240329").unwrap(),
+        "ETH-USDT-futures-240329"
+    );
+    assert_eq!(
+        resolve(Market::COIN_M, "BTCUSD_PERP").unwrap(),
+        "BTC-USD-swap"
+    );
+    assert_eq!(
+        resolve(Market::COIN_M, "BTCUSD_240329").unwrap(),
+        "BTC-USD-futures-240329"
+    );
+    assert_eq!(resolve(Market::COIN_M, "BTCUSD"), None);
+    // and books of symbols that can't be resolved are reported rather than published
+    let manager = BinanceManager::new().with_market(Market::COIN_M);
+    assert!(matches!(
+        manager.transmit("BTCUSD".to_string(), vec![], vec![], true),
+        Err(TorrentError::BadParse(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_binance_coin_m_contracts() {
+    let exchange_info = serde_json::json!({
+        "symbols": [
+            {"symbol": "BTCUSD_PERP", "contractSize": 100},
+            {"symbol": "ETHUSD_240329", "contractSize": 10}
+        ]
+    });
+    let server = MockExchange::new(Dialect::Binance)
+        .rest("/dapi/v1/exchangeInfo", exchange_info.to_string())
+        .start()
+        .await;
+
+    let instruments = Binance::instruments(&server.endpoint().rest, Market::COIN_M)
+        .await
+        .unwrap();
+    assert_eq!(
+        server.rest_requests(),
+        vec!["/dapi/v1/exchangeInfo".to_string()]
+    );
+    // 10 contracts of 100 USD at 50000, 10 contracts of 10 USD at 2000
+    assert_eq!(instruments[0].base_qty(50000.0, 10.0), 0.02);
+    assert_eq!(instruments[1].base_qty(2000.0, 10.0), 0.05);
+}
+
+#[tokio::test]
+async fn test_okx_resyncs_on_bad_checksum_or_missed_push() {
+    let bids = [(100.0, 1.0), (99.5, 2.0)];
+    let asks = [(101.0, 1.5)];
+    let updated_bids = [(100.0, 3.0), (99.5, 2.0)];
+    let server = MockExchange::new(Dialect::Okx)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(mock::okx::books(
+                "BTC-USDT",
+                "snapshot",
+                -1,
+                10,
+                mock::okx::checksum(&bids, &asks),
+                &bids,
+                &asks,
+            )),
+            Step::Send(mock::okx::books(
+                "BTC-USDT",
+                "update",
+                10,
+                11,
+                mock::okx::checksum(&updated_bids, &asks),
+                &[(100.0, 3.0)],
+                &[],
+            )),
+            Step::Sleep(Duration::from_millis(500)),
+            // a corrupted book
+            Step::Send(mock::okx::books(
+                "BTC-USDT",
+                "update",
+                11,
+                12,
+                0,
+                &[(99.5, 0.0)],
+                &[],
+            )),
+            Step::AwaitSubscriptions(2),
+            Step::Send(mock::okx::books(
+                "BTC-USDT",
+                "snapshot",
+                -1,
+                20,
+                mock::okx::checksum(&bids, &asks),
+                &bids,
+                &asks,
+            )),
+            // 21 is missed
+            Step::Send(mock::okx::books(
+                "BTC-USDT",
+                "update",
+                21,
+                22,
+                mock::okx::checksum(&updated_bids, &asks),
+                &[(100.0, 3.0)],
+                &[],
+            )),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::OKX, &server);
+    let reader = wss.connect(Exchange::OKX).await.unwrap();
+    let manager = OkxManager::new().with_client(wss.clone());
+    let ack = wss
+        .subscribe(OkxChannel::BOOKS.to_string(), vec!["BTC-USDT".to_string()])
+        .await
+        .unwrap();
+    tokio::spawn(WebSocketClient::listen_with(reader, manager));
+    ack.await.unwrap();
+
+    // the snapshot and update match their checksums
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(server.requests().len(), 1);
+
+    // each resync unsubscribes and subscribes again
+    let requests = timeout(Duration::from_secs(5), async {
+        while server.requests().len() < 5 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        server.requests()
+    })
+    .await
+    .expect("the manager didn't resync twice");
+    let ops = requests
+        .iter()
+        .map(|r| serde_json::from_str::<serde_json::Value>(r).unwrap()["op"].to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        ops,
+        vec![
+            "\"subscribe\"",
+            "\"unsubscribe\"",
+            "\"subscribe\"",
+            "\"unsubscribe\"",
+            "\"subscribe\""
+        ]
+    );
+    assert_eq!(
+        wss.subscriptions().get(&OkxChannel::BOOKS.to_string()),
+        Some(&vec!["BTC-USDT".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn test_okx_book_channels() {
+    let bids = [(100.0, 1.0), (99.5, 2.0)];
+    let asks = [(101.0, 1.5)];
+    let tbt = |action: &str, seq_id: i64, checksum: i64| {
+        mock::okx::books("BTC-USDT", action, -1, seq_id, checksum, &bids, &asks)
+            .replace(r#""channel":"books""#, r#""channel":"books-l2-tbt""#)
+    };
+    let server = MockExchange::new(Dialect::Okx)
+        .script(vec![
+            Step::AwaitSubscriptions(3),
+            // full books carry neither an action nor a checksum
+            Step::Send(mock::okx::full_book("books5", "BTC-USDT", 10, &bids, &asks)),
+            Step::Send(mock::okx::full_book(
+                "bbo-tbt",
+                "BTC-USDT",
+                11,
+                &bids[..1],
+                &asks,
+            )),
+            Step::Send(mock::okx::full_book(
+                "books5",
+                "BTC-USDT",
+                12,
+                &bids[1..],
+                &asks,
+            )),
+            Step::Send(tbt("snapshot", 13, mock::okx::checksum(&bids, &asks))),
+            Step::Sleep(Duration::from_millis(500)),
+            // a corrupted tick-by-tick book
+            Step::Send(tbt("snapshot", 14, 0)),
+        ])
+        .start()
+        .await;
+
+    assert!(matches!(
+        serde_json::from_str(&mock::okx::full_book(
+            "books5", "BTC-USDT", 10, &bids, &asks
+        )),
+        Ok(OkxMessage::Books(_))
+    ));
+
+    let wss = client(Exchange::OKX, &server);
+    let reader = wss.connect(Exchange::OKX).await.unwrap();
+    let manager = OkxManager::new().with_client(wss.clone());
+    let mut acks = Vec::new();
+    for channel in [
+        OkxChannel::BOOKS5,
+        OkxChannel::BBO_TBT,
+        OkxChannel::BOOKS_L2_TBT,
+    ] {
+        acks.push(
+            wss.subscribe(channel.to_string(), vec!["BTC-USDT".to_string()])
+                .await
+                .unwrap(),
+        );
+    }
+    tokio::spawn(WebSocketClient::listen_with(reader, manager));
+    for ack in acks {
+        ack.await.unwrap();
+    }
+
+    // full books are never validated, and the tick-by-tick snapshot matches its checksum
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(server.requests().len(), 3);
+
+    // only the tick-by-tick channel is resubscribed
+    let requests = timeout(Duration::from_secs(5), async {
+        while server.requests().len() < 5 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        server.requests()
+    })
+    .await
+    .expect("the manager didn't resync");
+    for request in &requests[3..] {
+        let request = serde_json::from_str::<serde_json::Value>(request).unwrap();
+        assert_eq!(request["args"][0]["channel"], "books-l2-tbt");
+    }
+}
+
+#[tokio::test]
+async fn test_okx_derivative_contracts() {
+    let instruments = serde_json::json!({
+        "code": "0",
+        "msg": "",
+        "data": [
+            {"instId": "BTC-USDT-SWAP", "instType": "SWAP", "ctVal": "0.01", "ctMult": "1", "ctValCcy": "BTC"},
+            {"instId": "BTC-USD-SWAP", "instType": "SWAP", "ctVal": "100", "ctMult": "1", "ctValCcy": "USD"}
+        ]
+    });
+    let server = MockExchange::new(Dialect::Okx)
+        .rest("/api/v5/public/instruments", instruments.to_string())
+        .start()
+        .await;
+
+    let instruments = Okx::instruments(&server.endpoint().rest, InstType::SWAP, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        server.rest_requests(),
+        vec!["/api/v5/public/instruments?instType=SWAP".to_string()]
+    );
+    // 10 linear contracts of 0.01 BTC, 10 inverse contracts of 100 USD at 50000
+    assert_eq!(instruments[0].base_qty(50000.0, 10.0), 0.1);
+    assert_eq!(instruments[1].base_qty(50000.0, 10.0), 0.02);
+
+    // margin instIds look like spot ones, only their instrument tells them apart
+    let margin = serde_json::json!({"instId": "ETH-USDT", "instType": "MARGIN", "ctVal": "", "ctMult": ""});
+    let margin = serde_json::from_value(margin).unwrap();
+    assert_eq!(InstType::from_inst_id("ETH-USDT"), InstType::SPOT);
+
+    let manager = OkxManager::new()
+        .with_instruments(instruments)
+        .with_instruments(vec![margin]);
+    assert_eq!(manager.inst_type("ETH-USDT"), InstType::MARGIN);
+    assert_eq!(manager.inst_type("BTC-USDT"), InstType::SPOT);
+    assert_eq!(manager.inst_type("BTC-USDT-SWAP"), InstType::SWAP);
+    assert!(manager.inst_type("ETH-USDT").is_spot());
+    let product = |inst_id: &str| {
+        manager
+            .resolve_symbol(&inst_id.to_string())
+            .map(|ccy_pair| ccy_pair.to_string())
+    };
+    assert_eq!(product("BTC-USDT"), Some("BTC-USDT-spot".to_string()));
+    assert_eq!(product("ETH-USDT"), Some("ETH-USDT-spot".to_string()));
+    assert_eq!(product("BTC-USDT-SWAP"), Some("BTC-USDT-swap".to_string()));
+    assert_eq!(
+        product("BTC-USD-240329"),
+        Some("BTC-USD-futures-240329".to_string())
+    );
+    assert_eq!(
+        product("BTC-USD-240329-60000-C"),
+        Some("BTC-USD-option-240329-60000-C".to_string())
+    );
+    assert_eq!(product("BTC"), None);
+    assert_eq!(InstType::from_inst_id("ETH-USD-240329"), InstType::FUTURES);
+}
+
+#[tokio::test]
+async fn test_okx_trades_stream() {
+    let server = MockExchange::new(Dialect::Okx)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(mock::okx::trades(
+                "BTC-USDT", 130639474, 42219.9, 0.12, "sell",
+            )),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::OKX, &server);
+    let reader = wss.connect(Exchange::OKX).await.unwrap();
+    let ack = wss
+        .subscribe(OkxChannel::TRADES.to_string(), vec!["BTC-USDT".to_string()])
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+    ack.await.unwrap();
+
+    assert!(matches!(recv(&mut rx).await, Ok(OkxMessage::Subscribe(_))));
+    let trade = match recv(&mut rx).await {
+        Ok(OkxMessage::Trades(mut update)) => update.data.remove(0),
+        m => panic!("expected a trade, got {:?}", m),
+    };
+    assert_eq!(trade.trade_id, "130639474");
+    assert_eq!((trade.px, trade.sz), (42219.9, 0.12));
+    assert_eq!(trade.side, "sell");
+    assert_eq!(trade.ts, 1630048897897);
+
+    let event = make_trade_event(
+        Trade {
+            id: trade.trade_id,
+            price: (trade.px * ASSET_CONSTANT_MULTIPLIER) as u64,
+            qty: (trade.sz * ASSET_CONSTANT_MULTIPLIER) as u64,
+            is_buy: trade.side == "buy",
+            timestamp: trade.ts * 1000,
+        },
+        CcyPair {
+            base: "BTC".to_string(),
+            quote: "USDT".to_string(),
+            product: "spot".to_string(),
+        },
+        Exchange::OKX,
+    )
+    .unwrap();
+    let event = root_as_trade_event_message(&event.buff)
+        .unwrap()
+        .trade_event()
+        .unwrap();
+    assert_eq!(event.exchange(), Some("Okx"));
+    assert_eq!(event.instrument(), Some("BTC-USDT-spot"));
+    assert_eq!(event.trade_id(), Some("130639474"));
+    assert_eq!(event.price(), 422199000000000);
+    assert!(!event.is_buy());
+    assert_eq!(event.trade_timestamp(), 1630048897897000);
+
+    // trades of instIds that can't be resolved are reported rather than published
+    let frame = mock::okx::trades("BTC", 130639475, 42219.9, 0.12, "buy");
+    let msg = serde_json::from_str::<OkxMessage>(&frame).unwrap();
+    assert!(matches!(
+        OkxManager::new().message_callback(Ok(msg)),
+        Err(TorrentError::BadParse(_))
+    ));
+
+    // so are books of derivatives whose contract value is unknown
+    let bids = [(42219.9, 10.0)];
+    let checksum = mock::okx::checksum(&bids, &[]);
+    let frame = mock::okx::books("BTC-USDT-SWAP", "snapshot", -1, 10, checksum, &bids, &[]);
+    let msg = serde_json::from_str::<OkxMessage>(&frame).unwrap();
+    assert!(matches!(
+        OkxManager::new().message_callback(Ok(msg)),
+        Err(TorrentError::BadParse(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_coinbase_requires_credentials() {
+    // neither given nor in KEY_NAME and PRIVATE_KEY
+    let result = WebSocketClient::new().connect(Exchange::COINBASE).await;
+    assert!(matches!(result, Err(TorrentError::BadRequest(_))));
+}
+
+#[tokio::test]
+async fn test_coinbase_acknowledges_in_order() {
+    let server = MockExchange::new(Dialect::Coinbase)
+        .reject("ETH-USD")
+        .start()
+        .await;
+
+    let wss = coinbase_client(&server);
+    let reader = wss.connect(Exchange::COINBASE).await.unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel::<Result<serde_json::Value>>();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+
+    let level2 = CoinbaseChannel::LEVEL2.to_string();
+    let ack = wss
+        .subscribe(level2.clone(), vec!["BTC-USD".to_string()])
+        .await
+        .unwrap();
+    ack.await.unwrap();
+    let ack = wss
+        .subscribe(level2, vec!["ETH-USD".to_string()])
+        .await
+        .unwrap();
+    assert!(matches!(ack.await, Err(TorrentError::BadSubscription(_))));
+}
+
+#[tokio::test]
+async fn test_coinbase_resubscribes_on_sequence_gap() {
+    // the heartbeats and level2 subscriptions are acknowledged with 0 and 1
+    let server = MockExchange::new(Dialect::Coinbase)
+        .script(vec![
+            Step::AwaitSubscriptions(2),
+            Step::Send(mock::coinbase::level2(
+                "BTC-USD",
+                "snapshot",
+                2,
+                &[("bid", 100.0, 1.0), ("offer", 101.0, 2.0)],
+            )),
+            Step::Send(mock::coinbase::level2(
+                "BTC-USD",
+                "update",
+                3,
+                &[("bid", 100.0, 0.0)],
+            )),
+            // 4 is missed
+            Step::Send(mock::coinbase::level2(
+                "BTC-USD",
+                "update",
+                5,
+                &[("offer", 101.0, 3.0)],
+            )),
+        ])
+        .start()
+        .await;
+
+    let wss = coinbase_client(&server);
+    let reader = wss.connect(Exchange::COINBASE).await.unwrap();
+    let manager = CoinbaseManager::new().with_client(wss.clone());
+    let ack = wss
+        .subscribe(
+            CoinbaseChannel::LEVEL2.to_string(),
+            vec!["BTC-USD".to_string()],
+        )
+        .await
+        .unwrap();
+    tokio::spawn(WebSocketClient::listen_with(reader, manager));
+    ack.await.unwrap();
+
+    let resubscribed = timeout(Duration::from_secs(5), async {
+        while server.requests().len() < 4 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        server.requests()
+    })
+    .await
+    .expect("the manager didn't resubscribe");
+    assert!(resubscribed[2].contains("\"type\":\"unsubscribe\""));
+    assert!(resubscribed[3].contains("\"type\":\"subscribe\""));
+    assert!(resubscribed[3].contains("BTC-USD"));
+    // a resubscription leaves the active subscriptions as they were
+    assert_eq!(
+        wss.subscriptions()
+            .get(&CoinbaseChannel::LEVEL2.to_string()),
+        Some(&vec!["BTC-USD".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn test_coinbase_signs_a_jwt_per_request() {
+    let key = SigningKey::random(&mut OsRng);
+    let pem = p256::SecretKey::from(&key)
+        .to_sec1_pem(LineEnding::LF)
+        .unwrap();
+    let credentials = CoinbaseCredentials::new(
+        "organizations/mock/apiKeys/mock",
+        pem.as_str(),
+        CoinbaseAuth::Jwt,
+    );
+    let server = MockExchange::new(Dialect::Coinbase).start().await;
+
+    let wss = client(Exchange::COINBASE, &server).with_coinbase_credentials(credentials);
+    let reader = wss.connect(Exchange::COINBASE).await.unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel::<Result<serde_json::Value>>();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+    let ack = wss
+        .subscribe(
+            CoinbaseChannel::LEVEL2.to_string(),
+            vec!["BTC-USD".to_string()],
+        )
+        .await
+        .unwrap();
+    ack.await.unwrap();
+
+    // heartbeats, then level2
+    let jwts = server
+        .requests()
+        .iter()
+        .map(|r| {
+            let req: serde_json::Value = serde_json::from_str(r).unwrap();
+            req["jwt"].as_str().unwrap().to_string()
+        })
+        .collect::<Vec<String>>();
+    assert_eq!(jwts.len(), 2);
+    assert_ne!(jwts[0], jwts[1]);
+
+    let verifying_key = VerifyingKey::from(&key);
+    for jwt in jwts {
+        let (message, signature) = jwt.rsplit_once('.').unwrap();
+        let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature).unwrap()).unwrap();
+        verifying_key
+            .verify(message.as_bytes(), &signature)
+            .unwrap();
+
+        let part = |encoded: &str| -> serde_json::Value {
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(encoded).unwrap()).unwrap()
+        };
+        let (header, claims) = message.split_once('.').unwrap();
+        let (header, claims) = (part(header), part(claims));
+        assert_eq!(header["alg"], "ES256");
+        assert_eq!(header["kid"], "organizations/mock/apiKeys/mock");
+        assert!(header["nonce"].is_string());
+        assert_eq!(claims["sub"], "organizations/mock/apiKeys/mock");
+        let (nbf, exp) = (
+            claims["nbf"].as_u64().unwrap(),
+            claims["exp"].as_u64().unwrap(),
+        );
+        assert_eq!(exp - nbf, 120);
+    }
+}
+
+#[tokio::test]
+async fn test_pool_shards_over_connection_limit() {
+    let update = |symbol: &str| mock::binance::depth_update(symbol, 1, 2, &[(100.0, 1.0)], &[]);
+    let server = MockExchange::new(Dialect::Binance)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update("A0USDT")),
+        ])
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update("A1029USDT")),
+        ])
+        .start()
+        .await;
+
+    let pool = WebSocketPool::with_client(Exchange::BINANCE, client(Exchange::BINANCE, &server));
+    let topics = (0..1030)
+        .map(|i| format!("A{}-USDT", i))
+        .collect::<Vec<_>>();
+    let ack = pool
+        .subscribe(BinanceChannel::DEPTH.to_string(), topics)
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let listener = pool.clone();
+    tokio::spawn(async move { listener.listen_with(Collector(tx)).await });
+    ack.await.unwrap();
+    assert_eq!(pool.connections().await, 2);
+
+    let mut symbols = Vec::new();
+    while symbols.len() < 2 {
+        if let Ok(BinanceMessage::Depth(update)) = recv(&mut rx).await {
+            symbols.push(update.symbol);
+        }
+    }
+    symbols.sort();
+    assert_eq!(symbols, vec!["A0USDT", "A1029USDT"]);
+
+    // already subscribed topics don't open further connections
+    let ack = pool
+        .subscribe(
+            BinanceChannel::DEPTH.to_string(),
+            vec!["A5-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    ack.await.unwrap();
+    assert_eq!(pool.connections().await, 2);
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn test_pool_shards_futures_at_their_own_limit() {
+    let update = |symbol: &str| {
+        mock::binance::futures_depth_update(symbol, 1, 2, 0, &[(100.0, 1.0)], &[])
+    };
+    let server = MockExchange::new(Dialect::Binance)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update("A0USDT")),
+        ])
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update("A200USDT")),
+        ])
+        .start()
+        .await;
+
+    // futures connections carry 200 streams where spot ones carry 1024
+    let client = client(Exchange::BINANCE, &server).with_binance_market(Market::USD_M);
+    let pool = WebSocketPool::with_client(Exchange::BINANCE, client);
+    let topics = (0..201)
+        .map(|i| format!("A{}-USDT", i))
+        .collect::<Vec<_>>();
+    let ack = pool
+        .subscribe(BinanceChannel::DEPTH.to_string(), topics)
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let listener = pool.clone();
+    tokio::spawn(async move { listener.listen_with(Collector(tx)).await });
+    ack.await.unwrap();
+    assert_eq!(pool.connections().await, 2);
+
+    let mut symbols = Vec::new();
+    while symbols.len() < 2 {
+        if let Ok(BinanceMessage::Depth(update)) = recv(&mut rx).await {
+            symbols.push(update.symbol);
+        }
+    }
+    symbols.sort();
+    assert_eq!(symbols, vec!["A0USDT", "A200USDT"]);
+}
+
+#[tokio::test]
+async fn test_control_and_binary_frames() {
+    let snapshot = mock::bybit::orderbook("BTCUSDT", "snapshot", 1, &[(100.0, 1.0)], &[]);
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    gzip.write_all(snapshot.as_bytes()).unwrap();
+    let server = MockExchange::new(Dialect::Bybit)
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::SendBinary(gzip.finish().unwrap()),
+            Step::Close(4000, "maintenance".to_string()),
+        ])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BYBIT, &server);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    let ack = wss
+        .subscribe(
+            BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+            vec!["BTC-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Result<BybitMessage>>();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+    ack.await.unwrap();
+
+    assert!(matches!(
+        recv(&mut rx).await,
+        Ok(BybitMessage::Subscribe(_))
+    ));
+    assert!(matches!(
+        recv(&mut rx).await,
+        Ok(BybitMessage::Orderbook(_))
+    ));
+    match recv(&mut rx).await {
+        Err(TorrentError::Closed(code, reason)) => {
+            assert_eq!(code, 4000);
+            assert_eq!(reason, "maintenance");
+        }
+        m => panic!("expected the close frame, got {:?}", m),
+    }
+}
+
+#[tokio::test]
+async fn test_pipeline_applies_backpressure() {
+    /// Blocks on every message like a manager publishing synchronously
+    struct Slow(Vec<u64>);
+
+    impl MessageCallback<BybitMessage<'_>> for Slow {
+        fn message_callback(&mut self, msg: Result<BybitMessage<'_>>) -> Result<()> {
+            std::thread::sleep(Duration::from_millis(20));
+            if let Ok(BybitMessage::Orderbook(update)) = msg {
+                self.0.push(update.data.seq.unwrap() - 1000);
+            }
+            Ok(())
+        }
+    }
+
+    let update = |u: u64| mock::bybit::orderbook("BTCUSDT", "delta", u, &[(100.0, 1.0)], &[]);
+    let mut script = vec![Step::AwaitSubscriptions(1)];
+    script.extend((1..=10).map(|u| Step::Send(update(u))));
+    script.push(Step::Disconnect);
+    let server = MockExchange::new(Dialect::Bybit)
+        .script(script)
+        .start()
+        .await;
+
+    let wss = client(Exchange::BYBIT, &server);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    wss.subscribe(
+        BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+        vec!["BTC-USDT".to_string()],
+    )
+    .await
+    .unwrap();
+    let (pipeline, worker) = Pipeline::spawn(Slow(Vec::new()), 1);
+    let pipeline = timeout(
+        Duration::from_secs(5),
+        WebSocketClient::listen_with_async(reader, pipeline),
+    )
+    .await
+    .unwrap();
+    drop(pipeline);
+
+    let manager = worker.await.unwrap();
+    assert_eq!(manager.0, (1..=10).collect::<Vec<u64>>());
+}
+
+#[tokio::test]
+async fn test_lifecycle_transitions() {
+    let update = |u: u64| mock::bybit::orderbook("BTCUSDT", "delta", u, &[(100.0, 1.0)], &[]);
+    let server = MockExchange::new(Dialect::Bybit)
+        // goes silent after the first update
+        .script(vec![
+            Step::AwaitSubscriptions(1),
+            Step::Send(update(1)),
+            Step::Sleep(Duration::from_secs(60)),
+        ])
+        .script(vec![Step::AwaitSubscriptions(1), Step::Send(update(2))])
+        .start()
+        .await;
+
+    let wss = client(Exchange::BYBIT, &server).with_idle_timeout(Duration::from_millis(400));
+    let mut transitions = wss.transitions();
+    assert_eq!(wss.state(), ConnectionState::Disconnected);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    wss.subscribe(
+        BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+        vec!["BTC-USDT".to_string()],
+    )
+    .await
+    .unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel::<Result<BybitMessage>>();
+    tokio::spawn(wss.clone().listen_with_reconnect(reader, Collector(tx)));
+
+    let mut states = Vec::new();
+    let mut reasons = Vec::new();
+    while states.len() < 8 {
+        let transition = timeout(Duration::from_secs(5), transitions.recv())
+            .await
+            .expect("no transition")
+            .unwrap();
+        assert_eq!(transition.exchange, Some(Exchange::BYBIT));
+        assert_eq!(
+            Some(transition.from),
+            states
+                .last()
+                .copied()
+                .or(Some(ConnectionState::Disconnected))
+        );
+        states.push(transition.to);
+        reasons.push(transition.reason);
+    }
+    assert_eq!(
+        states,
+        vec![
+            ConnectionState::Connecting,
+            ConnectionState::Subscribed,
+            ConnectionState::Streaming,
+            ConnectionState::Stale,
+            ConnectionState::Disconnected,
+            ConnectionState::Reconnecting,
+            ConnectionState::Subscribed,
+            ConnectionState::Streaming,
+        ]
+    );
+    assert!(reasons[4].as_ref().unwrap().contains("no data or pong"));
+    assert_eq!(wss.state(), ConnectionState::Streaming);
+}
+
+#[tokio::test]
+async fn test_outbound_rate_limit() {
+    let server = MockExchange::new(Dialect::Bybit).start().await;
+    let wss = client(Exchange::BYBIT, &server)
+        .with_rate_limit(RateLimit::new(2, Duration::from_millis(200)));
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel::<Result<BybitMessage>>();
+    tokio::spawn(WebSocketClient::listen_with(reader, Collector(tx)));
+
+    let started = std::time::Instant::now();
+    let mut acks = Vec::new();
+    for symbol in ["BTC", "ETH", "SOL", "XRP", "ADA", "DOT"] {
+        let ack = wss
+            .subscribe(
+                BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+                vec![format!("{}-USDT", symbol)],
+            )
+            .await
+            .unwrap();
+        acks.push(ack);
+    }
+    assert!(wss.outbound_queue().queued > 0);
+    Acknowledgement::join(acks).await.unwrap();
+
+    // a burst of 2, then 2 more every 200ms
+    assert!(started.elapsed() >= Duration::from_millis(350));
+    assert_eq!(server.requests().len(), 6);
+    let stats = wss.outbound_queue();
+    assert_eq!(stats.queued, 0);
+    assert!(stats.max_delay >= Duration::from_millis(350));
+}
+
+#[tokio::test]
+async fn test_shutdown_unsubscribes_and_closes() {
+    let server = MockExchange::new(Dialect::Bybit).start().await;
+    let wss = client(Exchange::BYBIT, &server);
+    let reader = wss.connect(Exchange::BYBIT).await.unwrap();
+    let ack = wss
+        .subscribe(
+            BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(),
+            vec!["BTC-USDT".to_string()],
+        )
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Result<BybitMessage>>();
+    let listener = tokio::spawn(wss.clone().listen_with_reconnect(reader, Collector(tx)));
+    ack.await.unwrap();
+
+    wss.shutdown().await.unwrap();
+    // no reconnect: the listener returns once the exchange answers the Close frame
+    timeout(Duration::from_secs(5), listener)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(wss.subscriptions().is_empty());
+    assert!(server.requests()[1].contains("\"op\":\"unsubscribe\""));
+    assert_eq!(server.connections(), 1);
+    assert_eq!(wss.state(), ConnectionState::Disconnected);
+
+    let mut closed = None;
+    while let Some(msg) = rx.recv().await {
+        if let Err(TorrentError::Closed(code, reason)) = msg {
+            closed = Some((code, reason));
+        }
+    }
+    assert_eq!(closed, Some((1000, "shutdown".to_string())));
+}
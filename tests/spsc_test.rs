@@ -80,3 +80,29 @@ fn test_send_receive_flatbuffer() {
         .pop()
         .is_ok_and(|v| v.stream_id == 2 && v.buff == vec![11, 22, 33]));
 }
+
+#[test]
+fn test_drain_after_producer_dropped() {
+    let (mut tx, mut rx) = SPSCQueue::new::<u64>(1000);
+    let producer = std::thread::spawn(move || {
+        for i in 0..500 {
+            tx.push(i);
+        }
+    });
+
+    let mut received = Vec::new();
+    let mut closed = false;
+    loop {
+        match rx.pop() {
+            Ok(v) => received.push(v),
+            Err(QueueError::EmptyQueue) => {
+                if closed {
+                    break;
+                }
+                closed = rx.is_closed();
+            }
+        }
+    }
+    producer.join().unwrap();
+    assert_eq!(received, (0..500).collect::<Vec<u64>>());
+}
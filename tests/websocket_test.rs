@@ -0,0 +1,246 @@
+use async_wss::binance::{Binance, Channel as BinanceChannel};
+use async_wss::bybit::{Bybit, Channel as BybitChannel, Depth};
+use async_wss::coinbase::Coinbase;
+use async_wss::config::{Endpoint, Endpoints};
+use async_wss::okx::{Channel as OkxChannel, Okx};
+use async_wss::utils::Exchange;
+use async_wss::websocket::{decode_binary, Ack, Backoff, Wss};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[test]
+fn test_backoff_grows_and_caps() {
+    let base = Duration::from_millis(100);
+    let max = Duration::from_millis(1000);
+    let mut backoff = Backoff::new(base, max);
+
+    let mut ceiling = base;
+    for _ in 0..10 {
+        let delay = backoff.next_delay();
+        assert!(delay >= ceiling / 2 && delay <= ceiling);
+        ceiling = (ceiling * 2).min(max);
+    }
+
+    backoff.reset();
+    assert!(backoff.next_delay() <= base);
+}
+
+#[test]
+fn test_unsubscribe_requests() {
+    let topics = vec!["BTC-USDT".to_string()];
+
+    let mut binance = Binance::new();
+    let req = binance
+        .unsubscribe(BinanceChannel::DEPTH.to_string(), topics.clone())
+        .unwrap();
+    assert_eq!(
+        req.payload,
+        r#"{"method":"UNSUBSCRIBE","params":["btcusdt@depth"],"id":0}"#
+    );
+    // futures are subscribed with Binance's own symbols
+    let req = binance
+        .subscribe(
+            BinanceChannel::DEPTH.to_string(),
+            vec!["BTCUSD_PERP".to_string(), "BTCUSDT_240329".to_string()],
+        )
+        .unwrap();
+    assert_eq!(
+        req.payload,
+        r#"{"method":"SUBSCRIBE","params":["btcusd_perp@depth","btcusdt_240329@depth"],"id":1}"#
+    );
+
+    let mut okx = Okx::new();
+    let req = okx
+        .unsubscribe(OkxChannel::BOOKS.to_string(), topics.clone())
+        .unwrap();
+    assert_eq!(
+        req.payload,
+        r#"{"op":"unsubscribe","args":[{"channel":"books","instId":"BTC-USDT","instType":"SPOT"}]}"#
+    );
+    let req = okx
+        .subscribe(
+            OkxChannel::BOOKS.to_string(),
+            vec!["BTC-USDT-SWAP".to_string(), "BTC-USD-240329".to_string()],
+        )
+        .unwrap();
+    assert_eq!(
+        req.payload,
+        r#"{"op":"subscribe","args":[{"channel":"books","instId":"BTC-USDT-SWAP","instType":"SWAP"},{"channel":"books","instId":"BTC-USD-240329","instType":"FUTURES"}]}"#
+    );
+
+    let mut bybit = Bybit::new();
+    let req = bybit
+        .unsubscribe(BybitChannel::ORDERBOOK(Depth::FIFTY).to_string(), topics)
+        .unwrap();
+    assert_eq!(
+        req.payload,
+        r#"{"req_id":"0","op":"unsubscribe","args":["orderbook.50.BTCUSDT"]}"#
+    );
+    // derivatives are subscribed with Bybit's own symbols
+    let req = bybit
+        .subscribe(
+            BybitChannel::ORDERBOOK(Depth::TWENTY_FIVE).to_string(),
+            vec!["BTC-29DEC23-40000-C".to_string(), "BTCUSD".to_string()],
+        )
+        .unwrap();
+    assert_eq!(
+        req.payload,
+        r#"{"req_id":"1","op":"subscribe","args":["orderbook.25.BTC-29DEC23-40000-C","orderbook.25.BTCUSD"]}"#
+    );
+}
+
+#[test]
+fn test_parse_acks() {
+    assert_eq!(
+        Binance::parse_ack(r#"{"result":null,"id":3}"#),
+        Some(Ack::Success(Some("3".to_string())))
+    );
+    assert_eq!(
+        Binance::parse_ack(r#"{"error":{"code":2,"msg":"Invalid request"},"id":4}"#),
+        Some(Ack::Failure(
+            Some("4".to_string()),
+            "code: 2, msg: Invalid request".to_string()
+        ))
+    );
+
+    assert_eq!(
+        Okx::parse_ack(
+            r#"{"event":"subscribe","arg":{"channel":"books","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#
+        ),
+        Some(Ack::Success(Some("subscribe:books:BTC-USDT".to_string())))
+    );
+    assert_eq!(
+        Okx::parse_ack(
+            r#"{"event":"unsubscribe","arg":{"channel":"books","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#
+        ),
+        Some(Ack::Success(Some("unsubscribe:books:BTC-USDT".to_string())))
+    );
+    assert_eq!(
+        Okx::parse_ack(
+            r#"{"event":"error","code":"60012","msg":"Invalid request","connId":"a4d3ae55"}"#
+        ),
+        Some(Ack::Failure(None, "60012: Invalid request".to_string()))
+    );
+
+    assert_eq!(
+        Bybit::parse_ack(
+            r#"{"success":false,"ret_msg":"error:handler not found","conn_id":"2324d924","req_id":"1","op":"subscribe"}"#
+        ),
+        Some(Ack::Failure(
+            Some("1".to_string()),
+            "error:handler not found".to_string()
+        ))
+    );
+    assert_eq!(
+        Bybit::parse_ack(r#"{"success":true,"ret_msg":"pong","conn_id":"2324d924","op":"ping"}"#),
+        None
+    );
+
+    assert_eq!(
+        Coinbase::parse_ack(r#"{"type":"error","message":"authentication failure"}"#),
+        Some(Ack::Failure(None, "authentication failure".to_string()))
+    );
+}
+
+#[test]
+fn test_endpoints_override() {
+    let vars = HashMap::from([(
+        "BYBIT_WS_URL",
+        "wss://stream-testnet.bybit.com/v5/public/spot",
+    )]);
+    let endpoints = Endpoints::from_vars(|name| vars.get(name).map(|url| url.to_string()));
+    assert_eq!(
+        endpoints.get(&Exchange::BYBIT),
+        &Endpoint::new(
+            "wss://stream-testnet.bybit.com/v5/public/spot",
+            Bybit::REST_URL
+        )
+    );
+    assert_eq!(endpoints.get(&Exchange::OKX).websocket, Okx::URL);
+
+    let endpoints = endpoints.with(
+        Exchange::BINANCE,
+        Endpoint::new("ws://127.0.0.1:9443/ws", "http://127.0.0.1:8080"),
+    );
+    assert_eq!(
+        endpoints.get(&Exchange::BINANCE).rest,
+        "http://127.0.0.1:8080"
+    );
+}
+
+#[test]
+fn test_decode_binary() {
+    use flate2::write::{DeflateEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    let text = r#"{"op":"pong"}"#;
+    let mut deflate = DeflateEncoder::new(Vec::new(), Compression::default());
+    deflate.write_all(text.as_bytes()).unwrap();
+    assert_eq!(decode_binary(&deflate.finish().unwrap()).unwrap(), text);
+
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+    zlib.write_all(text.as_bytes()).unwrap();
+    assert_eq!(decode_binary(&zlib.finish().unwrap()).unwrap(), text);
+
+    assert_eq!(decode_binary(text.as_bytes()).unwrap(), text);
+    assert!(decode_binary(&[0x1f, 0x8b, 0x00]).is_err());
+}
+
+#[test]
+fn test_borrowed_decoding() {
+    use async_wss::binance::DepthMessage;
+    use async_wss::bybit::{OrderbookEvent, PublicResponse};
+    use async_wss::okx::BooksMessage;
+    use std::borrow::Cow;
+
+    let within = |frame: &str, s: &Cow<str>| {
+        matches!(s, Cow::Borrowed(_)) && frame.as_bytes().as_ptr_range().contains(&s.as_ptr())
+    };
+
+    let frame = r#"{"e":"depthUpdate","E":1672515782136,"s":"BNBBTC","U":157,"u":160,"b":[["0.0024","10"]],"a":[["0.0026","100"]]}"#;
+    let borrowed: DepthMessage = serde_json::from_str(frame).unwrap();
+    assert!(within(frame, &borrowed.symbol));
+    assert_eq!(*borrowed.bids[0], [0.0024, 10.0]);
+    let owned = borrowed.into_owned();
+    assert!(matches!(owned.symbol, Cow::Owned(_)));
+    assert_eq!(owned.symbol, "BNBBTC");
+    assert_eq!(*owned.asks[0], [0.0026, 100.0]);
+
+    let frame = r#"{"arg":{"channel":"books","instId":"BTC-USDT"},"action":"snapshot","data":[{"asks":[["8476.98","415","0","13"]],"bids":[["8476.97","256","0","12"]],"ts":"1597026383085","checksum":-855196043,"prevSeqId":-1,"seqId":123456}]}"#;
+    let borrowed: BooksMessage = serde_json::from_str(frame).unwrap();
+    assert!(within(frame, &borrowed.action));
+    assert!(within(frame, &borrowed.data[0].ts));
+    assert_eq!(*borrowed.data[0].asks[0], [8476.98, 415.0, 0.0, 13.0]);
+
+    let frame = r#"{"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1687940967466,"data":{"s":"BTCUSDT","b":[["30247.20","30.028"]],"a":[],"u":177400507,"seq":66544703342},"cts":1687940967464}"#;
+    let borrowed: PublicResponse<OrderbookEvent> = serde_json::from_str(frame).unwrap();
+    assert!(within(frame, &borrowed.topic));
+    assert!(within(frame, &borrowed.data.s));
+    assert_eq!(*borrowed.data.b[0], [30247.2, 30.028]);
+
+    // escaped strings can't be borrowed and are unescaped into owned ones
+    let frame = r#"{"e":"depthUpdate","E":1,"s":"BNB\/BTC\u0021","U":1,"u":2,"b":[],"a":[]}"#;
+    let msg: DepthMessage = serde_json::from_str(frame).unwrap();
+    assert!(matches!(msg.symbol, Cow::Owned(_)));
+    assert_eq!(msg.symbol, "BNB/BTC!");
+    assert!(within(frame, &msg.event_type));
+
+    // levels must still be decimal strings
+    let frame = r#"{"e":"depthUpdate","E":1,"s":"BNBBTC","U":1,"u":2,"b":[["abc","10"]],"a":[]}"#;
+    assert!(serde_json::from_str::<DepthMessage>(frame).is_err());
+}
+
+#[test]
+fn test_okx_levels_keep_their_decimals() {
+    use async_wss::okx::BooksMessage;
+
+    // checksums are computed over the prices and quantities exactly as they were sent
+    let frame = r#"{"arg":{"channel":"books","instId":"BTC-USDT"},"action":"update","data":[{"asks":[["8476.980","0.50","0","13"]],"bids":[["8476","256","0","12"]],"ts":"1597026383085","checksum":-855196043,"prevSeqId":123455,"seqId":123456}]}"#;
+    let msg: BooksMessage = serde_json::from_str(frame).unwrap();
+    let (ask, bid) = (&msg.data[0].asks[0], &msg.data[0].bids[0]);
+    assert_eq!(ask.price().to_string(), "8476.980");
+    assert_eq!(ask.qty().to_string(), "0.50");
+    assert_eq!(bid.price().to_string(), "8476");
+    assert_eq!(**ask, [8476.98, 0.5, 0.0, 13.0]);
+}